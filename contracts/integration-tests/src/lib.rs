@@ -0,0 +1,420 @@
+//! Shared harness for the end-to-end tests: deploys the UL-NFT, liquidity
+//! wrapper, marketplace and metadata manager against the mock Euclid router
+//! on a cw-multi-test chain, wired together the way `scripts/deploy.ts` does.
+//! A mock price oracle is deployed alongside, the wrapper values positions
+//! from pool reserves until it is pointed at it.
+
+use cosmwasm_std::{coins, Addr, Coin, Decimal, Empty, StdResult, Uint128};
+use cw721::OwnerOfResponse;
+use cw_multi_test::{
+    App, AppBuilder, AppResponse, BankSudo, Contract, ContractWrapper, Executor, SudoMsg,
+};
+use liquidity_wrapper::state::{FailedOperation, PendingOperation, PoolStatus, PositionInfo};
+use mock_euclid_router::msg::SudoMsg as RouterSudoMsg;
+use mock_oracle::msg::SudoMsg as OracleSudoMsg;
+
+pub const ADMIN: &str = "admin";
+pub const ALICE: &str = "alice";
+pub const BOB: &str = "bob";
+pub const KEEPER: &str = "keeper";
+pub const UPDATER: &str = "updater";
+
+// Initial balance of every user in each of these denoms
+pub const DENOMS: [&str; 3] = ["uarch", "uusdc", "uatom"];
+pub const INITIAL_BALANCE: u128 = 1_000_000;
+
+// Chains registered with the wrapper, by id and display name
+pub const CHAINS: [(&str, &str); 2] = [("archway", "Archway"), ("osmosis", "Osmosis")];
+
+pub type AnyResult<T> = Result<T, cw_multi_test::error::AnyError>;
+
+fn ul_nft_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        ul_nft_core::contract::execute,
+        ul_nft_core::contract::instantiate,
+        ul_nft_core::contract::query,
+    ))
+}
+
+fn wrapper_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(
+        ContractWrapper::new(
+            liquidity_wrapper::contract::execute,
+            liquidity_wrapper::contract::instantiate,
+            liquidity_wrapper::contract::query,
+        )
+        .with_reply(liquidity_wrapper::contract::reply),
+    )
+}
+
+fn marketplace_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        marketplace::contract::execute,
+        marketplace::contract::instantiate,
+        marketplace::contract::query,
+    ))
+}
+
+fn metadata_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        metadata_manager::contract::execute,
+        metadata_manager::contract::instantiate,
+        metadata_manager::contract::query,
+    ))
+}
+
+fn router_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(
+        ContractWrapper::new(
+            mock_euclid_router::contract::execute,
+            mock_euclid_router::contract::instantiate,
+            mock_euclid_router::contract::query,
+        )
+        .with_sudo(mock_euclid_router::contract::sudo),
+    )
+}
+
+fn oracle_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(
+        ContractWrapper::new(
+            mock_oracle::contract::execute,
+            mock_oracle::contract::instantiate,
+            mock_oracle::contract::query,
+        )
+        .with_sudo(mock_oracle::contract::sudo),
+    )
+}
+
+pub struct Suite {
+    pub app: App,
+    pub router: Addr,
+    pub oracle: Addr,
+    pub ul_nft: Addr,
+    pub wrapper: Addr,
+    pub marketplace: Addr,
+    pub metadata: Addr,
+}
+
+impl Suite {
+    pub fn new() -> Self {
+        let mut app = AppBuilder::new().build(|router, _, storage| {
+            for user in [ALICE, BOB] {
+                let balance = DENOMS
+                    .iter()
+                    .map(|denom| Coin::new(INITIAL_BALANCE, *denom))
+                    .collect();
+                router
+                    .bank
+                    .init_balance(storage, &Addr::unchecked(user), balance)
+                    .unwrap();
+            }
+        });
+        let admin = Addr::unchecked(ADMIN);
+
+        let router_id = app.store_code(router_contract());
+        let router = app
+            .instantiate_contract(
+                router_id,
+                admin.clone(),
+                &mock_euclid_router::msg::InstantiateMsg {},
+                &[],
+                "mock-euclid-router",
+                None,
+            )
+            .unwrap();
+
+        let oracle_id = app.store_code(oracle_contract());
+        let oracle = app
+            .instantiate_contract(
+                oracle_id,
+                admin.clone(),
+                &mock_oracle::msg::InstantiateMsg {},
+                &[],
+                "mock-oracle",
+                None,
+            )
+            .unwrap();
+
+        let ul_nft_id = app.store_code(ul_nft_contract());
+        let ul_nft = app
+            .instantiate_contract(
+                ul_nft_id,
+                admin.clone(),
+                &ul_nft_core::msg::InstantiateMsg {
+                    name: "Unified Liquidity NFT".to_string(),
+                    symbol: "ULNFT".to_string(),
+                    minter: None,
+                    euclid_router: router.to_string(),
+                },
+                &[],
+                "ul-nft-core",
+                None,
+            )
+            .unwrap();
+
+        let wrapper_id = app.store_code(wrapper_contract());
+        let wrapper = app
+            .instantiate_contract(
+                wrapper_id,
+                admin.clone(),
+                &liquidity_wrapper::msg::InstantiateMsg {
+                    ul_nft_contract: ul_nft.to_string(),
+                    euclid_router: router.to_string(),
+                    euclid_factory: router.to_string(),
+                },
+                &[],
+                "liquidity-wrapper",
+                None,
+            )
+            .unwrap();
+
+        let marketplace_id = app.store_code(marketplace_contract());
+        let marketplace = app
+            .instantiate_contract(
+                marketplace_id,
+                admin.clone(),
+                &marketplace::msg::InstantiateMsg {
+                    ul_nft_contract: ul_nft.to_string(),
+                    fee_percentage: 250,
+                },
+                &[],
+                "marketplace",
+                None,
+            )
+            .unwrap();
+
+        let metadata_id = app.store_code(metadata_contract());
+        let metadata = app
+            .instantiate_contract(
+                metadata_id,
+                admin.clone(),
+                &metadata_manager::msg::InstantiateMsg {
+                    ul_nft_contract: ul_nft.to_string(),
+                    updater_addresses: vec![UPDATER.to_string()],
+                },
+                &[],
+                "metadata-manager",
+                None,
+            )
+            .unwrap();
+
+        // Hand minting to the wrapper and keep position owners in sync
+        for msg in [
+            ul_nft_core::msg::PositionExecuteMsg::UpdateMinter {
+                minter: wrapper.to_string(),
+            },
+            ul_nft_core::msg::PositionExecuteMsg::UpdateTransferHook {
+                transfer_hook: Some(wrapper.to_string()),
+            },
+        ] {
+            app.execute_contract(
+                admin.clone(),
+                ul_nft.clone(),
+                &ul_nft_core::msg::ExecuteMsg::Extension { msg },
+                &[],
+            )
+            .unwrap();
+        }
+
+        // Both chains carry the uarch/uusdc and uatom/uarch pools
+        let mut registry = vec![];
+        for (chain_id, display_name) in CHAINS {
+            registry.push(liquidity_wrapper::msg::ExecuteMsg::SetChain {
+                chain_id: chain_id.to_string(),
+                display_name: display_name.to_string(),
+                enabled: true,
+            });
+        }
+        for (pool_id, token_pair) in [("pool1", ("uarch", "uusdc")), ("pool2", ("uarch", "uatom"))]
+        {
+            registry.push(liquidity_wrapper::msg::ExecuteMsg::SetPool {
+                pool_id: pool_id.to_string(),
+                token_pair: (token_pair.0.to_string(), token_pair.1.to_string()),
+                chains: CHAINS.iter().map(|(id, _)| id.to_string()).collect(),
+                status: PoolStatus::Active,
+            });
+        }
+        for msg in registry {
+            app.execute_contract(admin.clone(), wrapper.clone(), &msg, &[])
+                .unwrap();
+        }
+        // The router knows both pools before their first deposit
+        for (pool_id, token_pair) in [("pool1", ("uarch", "uusdc")), ("pool2", ("uarch", "uatom"))]
+        {
+            let msg = RouterSudoMsg::SetPool {
+                pool_id: pool_id.to_string(),
+                token_pair: (token_pair.0.to_string(), token_pair.1.to_string()),
+                reserves: (Uint128::zero(), Uint128::zero()),
+                total_shares: Uint128::zero(),
+            };
+            app.wasm_sudo(router.clone(), &msg).unwrap();
+        }
+
+        Suite {
+            app,
+            router,
+            oracle,
+            ul_nft,
+            wrapper,
+            marketplace,
+            metadata,
+        }
+    }
+
+    pub fn execute_wrapper(
+        &mut self,
+        sender: &str,
+        msg: &liquidity_wrapper::msg::ExecuteMsg,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), self.wrapper.clone(), msg, funds)
+    }
+
+    pub fn execute_nft(
+        &mut self,
+        sender: &str,
+        msg: &ul_nft_core::msg::ExecuteMsg,
+    ) -> AnyResult<AppResponse> {
+        self.app
+            .execute_contract(Addr::unchecked(sender), self.ul_nft.clone(), msg, &[])
+    }
+
+    // Deposits `amount` of both tokens of the uarch/uusdc pool
+    pub fn add_liquidity(
+        &mut self,
+        sender: &str,
+        pool_id: &str,
+        amount: u128,
+    ) -> AnyResult<AppResponse> {
+        let msg = liquidity_wrapper::msg::ExecuteMsg::AddLiquidity {
+            pool_id: pool_id.to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amounts: (Uint128::new(amount), Uint128::new(amount)),
+            min_shares: Uint128::zero(),
+            lock_duration: None,
+        };
+        let funds = [Coin::new(amount, "uarch"), Coin::new(amount, "uusdc")];
+        self.execute_wrapper(sender, &msg, &funds)
+    }
+
+    pub fn remove_liquidity(
+        &mut self,
+        sender: &str,
+        token_id: &str,
+        amount: Option<u128>,
+    ) -> AnyResult<AppResponse> {
+        let msg = liquidity_wrapper::msg::ExecuteMsg::RemoveLiquidity {
+            token_id: token_id.to_string(),
+            amount: amount.map(Uint128::new),
+            min_amounts_out: vec![],
+            deadline: None,
+        };
+        self.execute_wrapper(sender, &msg, &[])
+    }
+
+    // Scripts the router
+    pub fn router_sudo(&mut self, msg: RouterSudoMsg) -> AnyResult<AppResponse> {
+        self.app.wasm_sudo(self.router.clone(), &msg)
+    }
+
+    // Scripts the price oracle
+    pub fn oracle_sudo(&mut self, msg: OracleSudoMsg) -> AnyResult<AppResponse> {
+        self.app.wasm_sudo(self.oracle.clone(), &msg)
+    }
+
+    // Points the wrapper at the price oracle, quoting each denom at its
+    // price in uusdc
+    pub fn use_price_oracle(&mut self, prices: &[(&str, Decimal)]) {
+        let msg = liquidity_wrapper::msg::ExecuteMsg::SetPriceOracle {
+            oracle: Some(self.oracle.to_string()),
+            quote_denom: "uusdc".to_string(),
+        };
+        self.execute_wrapper(ADMIN, &msg, &[]).unwrap();
+        for (denom, price) in prices {
+            self.oracle_sudo(OracleSudoMsg::SetPrice {
+                denom: denom.to_string(),
+                quote_denom: "uusdc".to_string(),
+                price: *price,
+            })
+            .unwrap();
+        }
+    }
+
+    // Mints tokens straight into an account, e.g. rewards held by the router
+    pub fn mint(&mut self, to: &Addr, amount: u128, denom: &str) {
+        self.app
+            .sudo(SudoMsg::Bank(BankSudo::Mint {
+                to_address: to.to_string(),
+                amount: coins(amount, denom),
+            }))
+            .unwrap();
+    }
+
+    pub fn balance(&self, address: impl Into<String>, denom: &str) -> u128 {
+        self.app
+            .wrap()
+            .query_balance(address, denom)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    pub fn position(&self, token_id: &str) -> Option<PositionInfo> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.wrapper,
+                &liquidity_wrapper::msg::QueryMsg::GetPosition {
+                    token_id: token_id.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn owner_of(&self, token_id: &str) -> StdResult<String> {
+        let response: OwnerOfResponse = self.app.wrap().query_wasm_smart(
+            &self.ul_nft,
+            &ul_nft_core::msg::QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            },
+        )?;
+        Ok(response.owner)
+    }
+
+    pub fn pending_operations(&self, user: Option<&str>) -> Vec<PendingOperation> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.wrapper,
+                &liquidity_wrapper::msg::QueryMsg::GetPendingOperations {
+                    user: user.map(|user| user.to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn failed_operations(&self, user: Option<&str>) -> Vec<FailedOperation> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.wrapper,
+                &liquidity_wrapper::msg::QueryMsg::GetFailedOperations {
+                    user: user.map(|user| user.to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+    }
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,2180 @@
+use cosmwasm_std::{coin, coins, Addr, Coin, Decimal, Event, Uint128};
+use cw_multi_test::Executor;
+use integration_tests::{Suite, ADMIN, ALICE, BOB, INITIAL_BALANCE, KEEPER, UPDATER};
+use liquidity_wrapper::euclid::{
+    EstimateRewardsResponse, EuclidAction, EuclidQueryMsg, PoolInfoResponse, PoolResponse,
+    PoolsResponse, SimulateAddLiquidityResponse, SimulateRemoveLiquidityResponse,
+};
+use liquidity_wrapper::msg::{
+    BoostReserveResponse, ExecuteMsg as WrapperExecuteMsg, LockStatusResponse,
+    PortfolioValueResponse, PositionValueResponse, QueryMsg as WrapperQueryMsg, RewardsResponse,
+    TvlResponse,
+};
+use liquidity_wrapper::state::{
+    LockTier, PausableAction, PauseState, PoolStatus, PositionInfo, RewardClaim, TimeLock,
+    Transfer, TransferStatus, Tvl,
+};
+use mock_euclid_router::msg::{Behaviour, QueryMsg as RouterQueryMsg, QueueResponse, SudoMsg};
+use mock_oracle::msg::SudoMsg as OracleSudoMsg;
+
+#[test]
+fn deposit_mints_and_withdrawals_burn_the_nft() {
+    let mut suite = Suite::new();
+
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    assert_eq!(suite.owner_of("ulp-1").unwrap(), ALICE);
+    let position = suite.position("ulp-1").unwrap();
+    assert_eq!(position.owner, Addr::unchecked(ALICE));
+    assert_eq!(position.position.amount, Uint128::new(1_000));
+    assert!(suite.pending_operations(None).is_empty());
+
+    let pool: PoolResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.router,
+            &RouterQueryMsg::Pool {
+                pool_id: "pool1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pool.reserves, (Uint128::new(1_000), Uint128::new(1_000)));
+
+    // Metadata updaters can describe the new token
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(UPDATER),
+            suite.metadata.clone(),
+            &metadata_manager::msg::ExecuteMsg::UpdateMetadata {
+                token_id: "ulp-1".to_string(),
+                metadata: metadata_manager::state::TokenMetadata {
+                    name: "uarch/uusdc position".to_string(),
+                    description: "Liquidity in pool1".to_string(),
+                    image: None,
+                    external_url: None,
+                    attributes: vec![],
+                    animation_url: None,
+                    background_color: None,
+                    last_updated: 0,
+                },
+            },
+            &[],
+        )
+        .unwrap();
+
+    // A partial withdrawal pays out and shrinks the position
+    suite.remove_liquidity(ALICE, "ulp-1", Some(400)).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 600);
+    assert_eq!(
+        suite.position("ulp-1").unwrap().position.amount,
+        Uint128::new(600)
+    );
+
+    // Withdrawing the rest burns the NFT
+    suite.remove_liquidity(ALICE, "ulp-1", None).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE);
+    assert!(suite.position("ulp-1").is_none());
+    assert!(suite.owner_of("ulp-1").is_err());
+}
+
+#[test]
+fn failed_deposits_are_refunded() {
+    let mut suite = Suite::new();
+
+    // The router reports a failure and returns the funds
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::AddLiquidity,
+            behaviour: Behaviour::Fail {
+                error: "pool is paused".to_string(),
+            },
+        })
+        .unwrap();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+
+    // The router call reverts
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::AddLiquidity,
+            behaviour: Behaviour::Revert {
+                error: "out of gas".to_string(),
+            },
+        })
+        .unwrap();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 0);
+
+    let failed = suite.failed_operations(Some(ALICE));
+    assert_eq!(failed.len(), 2);
+    assert_eq!(failed[0].error, "pool is paused");
+    assert!(failed[1].error.contains("out of gas"));
+    assert!(suite.owner_of("ulp-1").is_err());
+}
+
+#[test]
+fn deposits_follow_the_pool_ratio() {
+    let mut suite = Suite::new();
+    suite
+        .router_sudo(SudoMsg::SetPool {
+            pool_id: "pool1".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            reserves: (Uint128::new(100_000), Uint128::new(200_000)),
+            total_shares: Uint128::new(100_000),
+        })
+        .unwrap();
+    let deposit = |amounts: (u128, u128), min_shares: u128| WrapperExecuteMsg::AddLiquidity {
+        pool_id: "pool1".to_string(),
+        chain_id: "archway".to_string(),
+        token_pair: ("uarch".to_string(), "uusdc".to_string()),
+        amounts: (Uint128::new(amounts.0), Uint128::new(amounts.1)),
+        min_shares: Uint128::new(min_shares),
+        lock_duration: None,
+    };
+
+    // Equal amounts no longer fit a 1:2 pool
+    let err = suite
+        .execute_wrapper(
+            ALICE,
+            &deposit((1_000, 1_000), 0),
+            &[coin(1_000, "uarch"), coin(1_000, "uusdc")],
+        )
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("Deposit does not match the pool's ratio"));
+
+    // The minimum is checked against the shares minted, not the amounts
+    let funds = [coin(1_000, "uarch"), coin(2_000, "uusdc")];
+    suite
+        .execute_wrapper(ALICE, &deposit((1_000, 2_000), 1_001), &funds)
+        .unwrap();
+    let failed = suite.failed_operations(Some(ALICE));
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].error.contains("Slippage exceeded"));
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE);
+    suite
+        .execute_wrapper(ALICE, &deposit((1_000, 2_000), 1_000), &funds)
+        .unwrap();
+    assert_eq!(
+        suite.position("ulp-2").unwrap().position.amount,
+        Uint128::new(1_000)
+    );
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 1_000);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE - 2_000);
+
+    // Drift within the tolerance is accepted, the admin may tighten it
+    let tolerance = |tolerance| WrapperExecuteMsg::SetDepositRatioTolerance { tolerance };
+    let drifting = deposit((1_000, 2_010), 0);
+    let funds = [coin(1_000, "uarch"), coin(2_010, "uusdc")];
+    suite
+        .execute_wrapper(ADMIN, &tolerance(Decimal::zero()), &[])
+        .unwrap();
+    let err = suite.execute_wrapper(BOB, &drifting, &funds).unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("Deposit does not match the pool's ratio"));
+    suite
+        .execute_wrapper(ADMIN, &tolerance(Decimal::percent(1)), &[])
+        .unwrap();
+    suite.execute_wrapper(BOB, &drifting, &funds).unwrap();
+    assert_eq!(
+        suite.position("ulp-3").unwrap().position.amount,
+        Uint128::new(1_000)
+    );
+
+    // A pool the router cannot describe is refused rather than taken as empty
+    suite
+        .execute_wrapper(
+            ADMIN,
+            &WrapperExecuteMsg::SetPool {
+                pool_id: "pool3".to_string(),
+                token_pair: ("uarch".to_string(), "uusdc".to_string()),
+                chains: vec!["archway".to_string()],
+                status: PoolStatus::Active,
+            },
+            &[],
+        )
+        .unwrap();
+    let unknown = WrapperExecuteMsg::AddLiquidity {
+        pool_id: "pool3".to_string(),
+        chain_id: "archway".to_string(),
+        token_pair: ("uarch".to_string(), "uusdc".to_string()),
+        amounts: (Uint128::new(1_000), Uint128::new(1_000)),
+        min_shares: Uint128::zero(),
+        lock_duration: None,
+    };
+    let funds = [coin(1_000, "uarch"), coin(1_000, "uusdc")];
+    assert!(suite.execute_wrapper(ALICE, &unknown, &funds).is_err());
+}
+
+#[test]
+fn delayed_operations_complete_through_the_callback() {
+    let mut suite = Suite::new();
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::AddLiquidity,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite.add_liquidity(BOB, "pool1", 500).unwrap();
+    assert_eq!(suite.pending_operations(None).len(), 2);
+    assert!(suite.owner_of("ulp-1").is_err());
+
+    let queue: QueueResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.router, &RouterQueryMsg::Queue {})
+        .unwrap();
+    assert_eq!(queue.calls.len(), 2);
+
+    // Bob's deposit lands first, alice's fails on the destination chain
+    suite
+        .router_sudo(SudoMsg::Complete { id: 2, error: None })
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::Complete {
+            id: 1,
+            error: Some("destination chain halted".to_string()),
+        })
+        .unwrap();
+
+    // Ids are reserved on submission, so alice's failure leaves a gap
+    assert_eq!(suite.owner_of("ulp-2").unwrap(), BOB);
+    assert!(suite.owner_of("ulp-1").is_err());
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert_eq!(suite.failed_operations(Some(ALICE)).len(), 1);
+    assert!(suite.pending_operations(None).is_empty());
+
+    // Only the router may complete operations
+    let err = suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::EuclidCallback {
+                tx_id: 1,
+                response: liquidity_wrapper::euclid::EuclidResponse::failure("spoofed"),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+}
+
+#[test]
+fn rewards_accrue_on_the_router() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite.add_liquidity(BOB, "pool1", 3_000).unwrap();
+
+    suite
+        .router_sudo(SudoMsg::SetRewardRate {
+            pool_id: "pool1".to_string(),
+            rate: Decimal::percent(1),
+        })
+        .unwrap();
+    let estimate: EstimateRewardsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.router,
+            &EuclidQueryMsg::EstimateRewards {
+                pool_id: "pool1".to_string(),
+                amount: Uint128::new(1_000),
+                duration: 100,
+            },
+        )
+        .unwrap();
+    assert_eq!(estimate.rewards, Uint128::new(1_000));
+
+    let router = suite.router.clone();
+    suite.mint(&router, 400, "uatom");
+    suite
+        .router_sudo(SudoMsg::AccrueRewards {
+            pool_id: "pool1".to_string(),
+            rewards: coins(400, "uatom"),
+        })
+        .unwrap();
+
+    // The wrapper estimates from the last claim, not from the epoch
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(100));
+    let estimate: Uint128 = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::EstimateRewards {
+                token_id: "ulp-1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(estimate, Uint128::new(1_000));
+
+    // Alice holds a quarter of the pool and is paid on the reply
+    let claim = WrapperExecuteMsg::ClaimRewards {
+        token_id: "ulp-1".to_string(),
+    };
+    suite.execute_wrapper(ALICE, &claim, &[]).unwrap();
+    assert_eq!(suite.balance(&suite.router, "uatom"), 300);
+    assert_eq!(suite.balance(ALICE, "uatom"), INITIAL_BALANCE + 100);
+    assert_eq!(suite.balance(&suite.wrapper, "uatom"), 0);
+
+    let rewards: RewardsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetRewards {
+                token_id: "ulp-1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(rewards.denoms, vec!["uatom".to_string()]);
+    assert_eq!(rewards.claimed, coins(100, "uatom"));
+    assert!(rewards.accrued.is_zero());
+    assert!(rewards.last_claimed.is_some());
+
+    // The UL-NFT cannot change hands while a delayed claim is in flight
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::ClaimRewards,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+    suite.execute_wrapper(ALICE, &claim, &[]).unwrap();
+    let transfer = ul_nft_core::msg::ExecuteMsg::TransferNft {
+        recipient: BOB.to_string(),
+        token_id: "ulp-1".to_string(),
+    };
+    let err = suite.execute_nft(ALICE, &transfer).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Position locked"));
+    let router = suite.router.clone();
+    suite.mint(&router, 400, "uatom");
+    suite
+        .router_sudo(SudoMsg::AccrueRewards {
+            pool_id: "pool1".to_string(),
+            rewards: coins(400, "uatom"),
+        })
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .unwrap();
+    assert_eq!(suite.balance(ALICE, "uatom"), INITIAL_BALANCE + 275);
+    suite.execute_nft(ALICE, &transfer).unwrap();
+
+    let history: Vec<RewardClaim> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetRewardHistory {
+                token_id: "ulp-1".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].recipient, Addr::unchecked(ALICE));
+    assert_eq!(history[1].recipient, Addr::unchecked(ALICE));
+    assert_eq!(history[1].rewards, coins(175, "uatom"));
+
+    let page: Vec<RewardClaim> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetRewardHistory {
+                token_id: "ulp-1".to_string(),
+                start_after: Some(history[0].operation_id),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(page, history[1..].to_vec());
+}
+
+#[test]
+fn keepers_compound_rewards_into_the_position() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 100_000).unwrap();
+    // Rewards are swapped through a uatom/uarch pool
+    suite
+        .execute_wrapper(
+            BOB,
+            &WrapperExecuteMsg::AddLiquidity {
+                pool_id: "pool2".to_string(),
+                chain_id: "archway".to_string(),
+                token_pair: ("uatom".to_string(), "uarch".to_string()),
+                amounts: (Uint128::new(100_000), Uint128::new(100_000)),
+                min_shares: Uint128::zero(),
+                lock_duration: None,
+            },
+            &[coin(100_000, "uarch"), coin(100_000, "uatom")],
+        )
+        .unwrap();
+    let router = suite.router.clone();
+    suite.mint(&router, 1_000, "uatom");
+    suite
+        .router_sudo(SudoMsg::AccrueRewards {
+            pool_id: "pool1".to_string(),
+            rewards: coins(1_000, "uatom"),
+        })
+        .unwrap();
+
+    // Keepers need the owner's opt-in, and the bounty is capped
+    let compound = WrapperExecuteMsg::Compound {
+        token_id: "ulp-1".to_string(),
+    };
+    let err = suite.execute_wrapper(KEEPER, &compound, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("not enabled"));
+    let err = suite
+        .execute_wrapper(
+            ADMIN,
+            &WrapperExecuteMsg::SetCompoundBounty {
+                bounty: Decimal::percent(20),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("maximum"));
+    suite
+        .execute_wrapper(
+            ADMIN,
+            &WrapperExecuteMsg::SetCompoundBounty {
+                bounty: Decimal::percent(5),
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SetAutoCompound {
+                token_id: "ulp-1".to_string(),
+                enabled: true,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // The swaps are bounded by the oracle, without one there is no compounding
+    let err = suite.execute_wrapper(KEEPER, &compound, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("No price oracle"));
+    suite.use_price_oracle(&[("uarch", Decimal::one()), ("uatom", Decimal::one())]);
+
+    // 950 uatom become 941 uarch, 470 of which are swapped to 467 uusdc, the
+    // uusdc left over at the pool's ratio goes to Alice
+    suite.execute_wrapper(KEEPER, &compound, &[]).unwrap();
+    assert_eq!(suite.balance(KEEPER, "uatom"), 50);
+    assert_eq!(
+        suite.position("ulp-1").unwrap().position.amount.u128(),
+        100_468
+    );
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 100_000);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE - 100_000 + 1);
+    assert!(suite.pending_operations(None).is_empty());
+    for denom in ["uarch", "uusdc", "uatom"] {
+        assert_eq!(suite.balance(&suite.wrapper, denom), 0);
+    }
+
+    let history: Vec<RewardClaim> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetRewardHistory {
+                token_id: "ulp-1".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(history[0].compounded);
+    assert_eq!(history[0].rewards, coins(1_000, "uatom"));
+
+    // The opt-in does not pass to the next owner
+    suite
+        .execute_nft(
+            ALICE,
+            &ul_nft_core::msg::ExecuteMsg::TransferNft {
+                recipient: BOB.to_string(),
+                token_id: "ulp-1".to_string(),
+            },
+        )
+        .unwrap();
+    let err = suite.execute_wrapper(KEEPER, &compound, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("not enabled"));
+}
+
+#[test]
+fn compound_swaps_are_bounded_by_the_oracle() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 100_000).unwrap();
+    suite
+        .execute_wrapper(
+            BOB,
+            &WrapperExecuteMsg::AddLiquidity {
+                pool_id: "pool2".to_string(),
+                chain_id: "archway".to_string(),
+                token_pair: ("uatom".to_string(), "uarch".to_string()),
+                amounts: (Uint128::new(100_000), Uint128::new(100_000)),
+                min_shares: Uint128::zero(),
+                lock_duration: None,
+            },
+            &[coin(100_000, "uarch"), coin(100_000, "uatom")],
+        )
+        .unwrap();
+    let router = suite.router.clone();
+    suite.mint(&router, 1_000, "uatom");
+    suite
+        .router_sudo(SudoMsg::AccrueRewards {
+            pool_id: "pool1".to_string(),
+            rewards: coins(1_000, "uatom"),
+        })
+        .unwrap();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SetAutoCompound {
+                token_id: "ulp-1".to_string(),
+                enabled: true,
+            },
+            &[],
+        )
+        .unwrap();
+    suite.use_price_oracle(&[("uarch", Decimal::one()), ("uatom", Decimal::one())]);
+
+    // Someone moves the uatom price ahead of the keeper, the router's quote
+    // moves with it but the oracle's does not, so the reward swap is refused
+    suite
+        .router_sudo(SudoMsg::SetPool {
+            pool_id: "pool2".to_string(),
+            token_pair: ("uatom".to_string(), "uarch".to_string()),
+            reserves: (Uint128::new(100_000), Uint128::new(90_000)),
+            total_shares: Uint128::new(100_000),
+        })
+        .unwrap();
+    let err = suite
+        .execute_wrapper(
+            KEEPER,
+            &WrapperExecuteMsg::Compound {
+                token_id: "ulp-1".to_string(),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Slippage"));
+    assert!(suite.pending_operations(None).is_empty());
+}
+
+#[test]
+fn swaps_route_through_router_pools() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+
+    // 1_000 in against 100_000/100_000 reserves yields 990 out
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SwapExactIn {
+                route: vec!["uarch".to_string(), "uusdc".to_string()],
+                min_amount_out: Uint128::new(990),
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 1_000);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE + 990);
+    assert_eq!(suite.balance(&suite.wrapper, "uusdc"), 0);
+
+    // Unused input of an exact-out swap is refunded
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SwapExactOut {
+                route: vec!["uusdc".to_string(), "uarch".to_string()],
+                amount_out: Uint128::new(500),
+                max_amount_in: Uint128::new(2_000),
+                deadline: None,
+            },
+            &coins(2_000, "uusdc"),
+        )
+        .unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 500);
+    assert!(suite.balance(ALICE, "uusdc") > INITIAL_BALANCE + 990 - 600);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 0);
+
+    // Slippage beyond the limit fails the swap and refunds the input
+    let uarch = suite.balance(ALICE, "uarch");
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SwapExactIn {
+                route: vec!["uarch".to_string(), "uusdc".to_string()],
+                min_amount_out: Uint128::new(1_000),
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), uarch);
+    assert!(suite.failed_operations(Some(ALICE))[0]
+        .error
+        .contains("Slippage exceeded"));
+}
+
+#[test]
+fn factory_queries_describe_pools_before_signing() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+    suite
+        .router_sudo(SudoMsg::SetPool {
+            pool_id: "pool2".to_string(),
+            token_pair: ("uarch".to_string(), "uatom".to_string()),
+            reserves: (Uint128::zero(), Uint128::zero()),
+            total_shares: Uint128::zero(),
+        })
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::SetFeeTier {
+            pool_id: "pool1".to_string(),
+            fee_tier: Decimal::permille(3),
+        })
+        .unwrap();
+
+    // The wrapper pages through the factory's pools
+    let page: PoolsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::ListPools {
+                start_after: None,
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(page.pools.len(), 1);
+    assert_eq!(page.pools[0].pool_id, "pool1");
+    assert_eq!(page.pools[0].fee_tier, Decimal::permille(3));
+    let page: PoolsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::ListPools {
+                start_after: Some("pool1".to_string()),
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(page.pools.len(), 1);
+    assert_eq!(page.pools[0].pool_id, "pool2");
+
+    // A balanced pool takes an even deposit without impact
+    let simulate_add = |suite: &Suite| -> SimulateAddLiquidityResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.wrapper,
+                &WrapperQueryMsg::SimulateAddLiquidity {
+                    pool_id: "pool1".to_string(),
+                    amounts: (Uint128::new(1_000), Uint128::new(1_000)),
+                },
+            )
+            .unwrap()
+    };
+    let quote = simulate_add(&suite);
+    assert_eq!(quote.shares, Uint128::new(1_000));
+    assert_eq!(quote.price_impact, Decimal::zero());
+
+    // A swap moves the price, and the quotes with it
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SwapExactIn {
+                route: vec!["uarch".to_string(), "uusdc".to_string()],
+                min_amount_out: Uint128::zero(),
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    let info: PoolInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::PoolInfo {
+                pool_id: "pool1".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(info.reserves, (Uint128::new(101_000), Uint128::new(99_010)));
+    assert_eq!(info.total_shares, Uint128::new(100_000));
+    assert_eq!(
+        simulate_add(&suite).price_impact,
+        Decimal::one() - Decimal::from_ratio(99_010u128, 101_000u128)
+    );
+
+    // The quoted withdrawal is what the router pays out
+    let quote: SimulateRemoveLiquidityResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::SimulateRemoveLiquidity {
+                pool_id: "pool1".to_string(),
+                amount: Uint128::new(50_000),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        quote.assets,
+        vec![coin(50_500, "uarch"), coin(49_505, "uusdc")]
+    );
+    let (uarch, uusdc) = (suite.balance(BOB, "uarch"), suite.balance(BOB, "uusdc"));
+    suite.remove_liquidity(BOB, "ulp-1", Some(50_000)).unwrap();
+    assert_eq!(suite.balance(BOB, "uarch"), uarch + 50_500);
+    assert_eq!(suite.balance(BOB, "uusdc"), uusdc + 49_505);
+}
+
+#[test]
+fn positions_are_valued_from_reserves_or_the_oracle() {
+    let mut suite = Suite::new();
+    for (sender, amount) in [(ALICE, 1_000), (BOB, 2_000), (ALICE, 1_000)] {
+        suite.add_liquidity(sender, "pool1", amount).unwrap();
+    }
+    // 1_000 uarch in against 4_000/4_000 reserves leaves 5_000/3_200
+    suite
+        .execute_wrapper(
+            BOB,
+            &WrapperExecuteMsg::SwapExactIn {
+                route: vec!["uarch".to_string(), "uusdc".to_string()],
+                min_amount_out: Uint128::zero(),
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    let position_value = |suite: &Suite, quote_denom: &str| {
+        suite.app.wrap().query_wasm_smart::<PositionValueResponse>(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetPositionValue {
+                token_id: "ulp-1".to_string(),
+                quote_denom: quote_denom.to_string(),
+            },
+        )
+    };
+    let portfolio = |suite: &Suite| -> PortfolioValueResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.wrapper,
+                &WrapperQueryMsg::GetPortfolioValue {
+                    owner: ALICE.to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    // Without an oracle the pool's reserves price each side in the other
+    let value = position_value(&suite, "uusdc").unwrap();
+    assert_eq!(value.assets, vec![coin(1_250, "uarch"), coin(800, "uusdc")]);
+    assert_eq!(value.value, Uint128::new(1_600));
+    assert_eq!(
+        position_value(&suite, "uarch").unwrap().value,
+        Uint128::new(2_500)
+    );
+    assert!(position_value(&suite, "uatom").is_err());
+    let value = portfolio(&suite);
+    assert_eq!(value.quote_denom, "uusdc");
+    assert_eq!(value.positions.len(), 2);
+    assert_eq!(value.value, Uint128::new(3_200));
+
+    // Only the admin points the wrapper at an oracle
+    let set_oracle = WrapperExecuteMsg::SetPriceOracle {
+        oracle: Some(suite.oracle.to_string()),
+        quote_denom: "uatom".to_string(),
+    };
+    let err = suite.execute_wrapper(ALICE, &set_oracle, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(ADMIN, &set_oracle, &[]).unwrap();
+    for (denom, price) in [
+        ("uarch", Decimal::percent(200)),
+        ("uusdc", Decimal::percent(50)),
+    ] {
+        suite
+            .oracle_sudo(OracleSudoMsg::SetPrice {
+                denom: denom.to_string(),
+                quote_denom: "uatom".to_string(),
+                price,
+            })
+            .unwrap();
+    }
+    assert_eq!(
+        position_value(&suite, "uatom").unwrap().value,
+        Uint128::new(2_900)
+    );
+    assert!(position_value(&suite, "uosmo").is_err());
+    let value = portfolio(&suite);
+    assert_eq!(value.quote_denom, "uatom");
+    assert_eq!(value.value, Uint128::new(5_800));
+}
+
+#[test]
+fn delayed_swaps_respect_the_deadline() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::SwapExactIn,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+
+    let deadline = suite.app.block_info().time.seconds() + 60;
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::SwapExactIn {
+                route: vec!["uarch".to_string(), "uusdc".to_string()],
+                min_amount_out: Uint128::zero(),
+                deadline: Some(deadline),
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    assert_eq!(suite.pending_operations(Some(ALICE)).len(), 1);
+
+    // The router can no longer fill it once the deadline has passed
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(120));
+    assert!(suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .is_err());
+    suite
+        .router_sudo(SudoMsg::Complete {
+            id: 1,
+            error: Some("deadline exceeded".to_string()),
+        })
+        .unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert_eq!(suite.failed_operations(Some(ALICE)).len(), 1);
+}
+
+#[test]
+fn zaps_deposit_and_withdraw_a_single_token() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+
+    // 500 of the 1_000 uarch buy 497 uusdc, 495 of which match the pool's
+    // ratio to the remaining 500 uarch
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapIn {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                input_denom: "uarch".to_string(),
+                min_shares: Uint128::new(497),
+                max_slippage: None,
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    assert_eq!(suite.owner_of("ulp-2").unwrap(), ALICE);
+    assert_eq!(
+        suite.position("ulp-2").unwrap().position.amount,
+        Uint128::new(497)
+    );
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 1_000);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE + 2);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 0);
+    assert_eq!(suite.balance(&suite.wrapper, "uusdc"), 0);
+
+    // Withdrawing everything as uarch burns the NFT
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapOut {
+                token_id: "ulp-2".to_string(),
+                amount: None,
+                output_denom: "uarch".to_string(),
+                min_amount_out: Uint128::new(980),
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(suite.owner_of("ulp-2").is_err());
+    assert!(suite.balance(ALICE, "uarch") >= INITIAL_BALANCE - 20);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE + 2);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 0);
+    assert_eq!(suite.balance(&suite.wrapper, "uusdc"), 0);
+}
+
+#[test]
+fn failed_zaps_roll_back() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+
+    // The deposit step fails after the swap, nothing of the zap remains
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::AddLiquidity,
+            behaviour: Behaviour::Fail {
+                error: "pool is paused".to_string(),
+            },
+        })
+        .unwrap();
+    let err = suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapIn {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                input_denom: "uarch".to_string(),
+                min_shares: Uint128::zero(),
+                max_slippage: None,
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("pool is paused"));
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert!(suite.pending_operations(None).is_empty());
+
+    // Once a step was deferred, a later failure is refunded instead
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::SwapExactIn,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapIn {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                input_denom: "uarch".to_string(),
+                min_shares: Uint128::zero(),
+                max_slippage: None,
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .unwrap();
+    let failed = suite.failed_operations(Some(ALICE));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].operation.action, EuclidAction::AddLiquidity);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 0);
+    assert_eq!(suite.balance(&suite.wrapper, "uusdc"), 0);
+    assert_eq!(
+        suite.balance(ALICE, "uarch") + suite.balance(ALICE, "uusdc"),
+        2 * INITIAL_BALANCE - 3
+    );
+}
+
+#[test]
+fn zap_swaps_are_bounded_by_the_oracle() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+    let set_reserves = |suite: &mut Suite, reserves: (u128, u128)| {
+        suite
+            .router_sudo(SudoMsg::SetPool {
+                pool_id: "pool1".to_string(),
+                token_pair: ("uarch".to_string(), "uusdc".to_string()),
+                reserves: (Uint128::new(reserves.0), Uint128::new(reserves.1)),
+                total_shares: Uint128::new(100_000),
+            })
+            .unwrap();
+    };
+
+    // The pool is moved ahead of the zap in, the router's quote moves with it
+    // but the oracle's price does not, so the swap is refused and refunded
+    suite.use_price_oracle(&[("uarch", Decimal::one())]);
+    set_reserves(&mut suite, (100_000, 90_000));
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::SwapExactIn,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapIn {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                input_denom: "uarch".to_string(),
+                min_shares: Uint128::zero(),
+                max_slippage: Some(Decimal::percent(1)),
+                deadline: None,
+            },
+            &coins(1_000, "uarch"),
+        )
+        .unwrap();
+    assert!(suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .is_err());
+    suite
+        .router_sudo(SudoMsg::Complete {
+            id: 1,
+            error: Some("slippage exceeded".to_string()),
+        })
+        .unwrap();
+    let failed = suite.failed_operations(Some(ALICE));
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].refunded, coins(1_000, "uarch"));
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert!(suite.position("ulp-2").is_none());
+
+    // A settled zap out short of its minimum is refunded, not paid out
+    set_reserves(&mut suite, (100_000, 100_000));
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::RemoveLiquidity,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapOut {
+                token_id: "ulp-2".to_string(),
+                amount: None,
+                output_denom: "uarch".to_string(),
+                min_amount_out: Uint128::new(1_990),
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap();
+    // All of the pool's value sits in uarch now, nothing is left to swap
+    suite
+        .router_sudo(SudoMsg::SetPool {
+            pool_id: "pool1".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            reserves: (Uint128::new(101_000), Uint128::zero()),
+            total_shares: Uint128::new(101_000),
+        })
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::Complete { id: 2, error: None })
+        .unwrap();
+    let failed = suite.failed_operations(Some(ALICE));
+    assert_eq!(failed.len(), 2);
+    assert_eq!(failed[1].operation.action, EuclidAction::RemoveLiquidity);
+    assert_eq!(failed[1].refunded, coins(1_000, "uarch"));
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 0);
+    assert!(suite.position("ulp-2").is_none());
+}
+
+#[test]
+fn withdrawals_and_moves_respect_the_limits() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+
+    let remove = |min_amounts_out, deadline| WrapperExecuteMsg::RemoveLiquidity {
+        token_id: "ulp-1".to_string(),
+        amount: None,
+        min_amounts_out,
+        deadline,
+    };
+    let err = suite
+        .execute_wrapper(ALICE, &remove(coins(1_001, "uarch"), None), &[])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("Slippage limit exceeded"));
+    let expired = suite.app.block_info().time.seconds() - 1;
+    let err = suite
+        .execute_wrapper(ALICE, &remove(vec![], Some(expired)), &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Deadline exceeded"));
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 1_000);
+
+    // Moving to another chain costs 50 uatom and 1% of the liquidity
+    suite
+        .router_sudo(SudoMsg::SetTransferCost {
+            fee: coins(50, "uatom"),
+            slippage: Decimal::percent(1),
+        })
+        .unwrap();
+    let transfer = |min_amount_out: u128, max_fee: u128| WrapperExecuteMsg::TransferPosition {
+        token_id: "ulp-1".to_string(),
+        to_chain_id: "osmosis".to_string(),
+        min_amount_out: Some(Uint128::new(min_amount_out)),
+        max_fee: Some(Coin::new(max_fee, "uatom")),
+        deadline: None,
+    };
+    let err = suite
+        .execute_wrapper(ALICE, &transfer(990, 40), &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Fee limit exceeded"));
+    let err = suite
+        .execute_wrapper(ALICE, &transfer(995, 50), &[])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("Slippage limit exceeded"));
+
+    suite
+        .execute_wrapper(ALICE, &transfer(990, 50), &[])
+        .unwrap();
+    let position = suite.position("ulp-1").unwrap().position;
+    assert_eq!(position.chain_id, "osmosis");
+    assert_eq!(position.amount, Uint128::new(990));
+}
+
+#[test]
+fn settled_operations_missing_their_limits_still_apply() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(BOB, "pool1", 100_000).unwrap();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    for action in [
+        EuclidAction::RemoveLiquidity,
+        EuclidAction::TransferLiquidity,
+    ] {
+        suite
+            .router_sudo(SudoMsg::SetBehaviour {
+                action,
+                behaviour: Behaviour::Delay,
+            })
+            .unwrap();
+    }
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::RemoveLiquidity {
+                token_id: "ulp-2".to_string(),
+                amount: None,
+                min_amounts_out: coins(1_000, "uarch"),
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // The uarch side drops by a tenth before the router pays out, too late
+    // to revert, so the withdrawal is applied and the miss reported
+    suite
+        .router_sudo(SudoMsg::SetPool {
+            pool_id: "pool1".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            reserves: (Uint128::new(90_900), Uint128::new(101_000)),
+            total_shares: Uint128::new(101_000),
+        })
+        .unwrap();
+    let response = suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .unwrap();
+    assert!(response
+        .has_event(&Event::new("wasm").add_attribute("limit_missed", "Slippage limit exceeded")));
+    assert!(suite.failed_operations(Some(ALICE)).is_empty());
+    assert!(suite.pending_operations(None).is_empty());
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 100);
+    assert!(suite.position("ulp-2").is_none());
+    assert!(suite.owner_of("ulp-2").is_err());
+
+    // A move arriving short of its minimum still lands, the miss stays on
+    // the transfer record
+    suite
+        .router_sudo(SudoMsg::SetTransferCost {
+            fee: vec![],
+            slippage: Decimal::percent(1),
+        })
+        .unwrap();
+    suite
+        .execute_wrapper(
+            BOB,
+            &WrapperExecuteMsg::TransferPosition {
+                token_id: "ulp-1".to_string(),
+                to_chain_id: "osmosis".to_string(),
+                min_amount_out: Some(Uint128::new(100_000)),
+                max_fee: None,
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::Complete { id: 2, error: None })
+        .unwrap();
+    let transfer: Option<Transfer> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetTransferStatus {
+                token_id: "ulp-1".to_string(),
+            },
+        )
+        .unwrap();
+    let transfer = transfer.unwrap();
+    assert_eq!(transfer.status, TransferStatus::Completed);
+    assert_eq!(transfer.error.as_deref(), Some("Slippage limit exceeded"));
+    assert!(suite.pending_operations(None).is_empty());
+    let position = suite.position("ulp-1").unwrap().position;
+    assert_eq!(position.chain_id, "osmosis");
+    assert_eq!(position.amount, Uint128::new(99_000));
+}
+
+#[test]
+fn positions_change_through_the_router() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    let pool_liquidity = |suite: &Suite| -> Uint128 {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.wrapper,
+                &liquidity_wrapper::msg::QueryMsg::GetPoolLiquidity {
+                    pool_id: "pool1".to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    // Increasing deposits through the router and keeps the token
+    let increase = WrapperExecuteMsg::IncreaseLiquidity {
+        token_id: "ulp-1".to_string(),
+        amounts: (Uint128::new(500), Uint128::new(500)),
+        min_shares: Uint128::zero(),
+    };
+    let err = suite
+        .execute_wrapper(BOB, &increase, &[coin(500, "uarch"), coin(500, "uusdc")])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite
+        .execute_wrapper(ALICE, &increase, &[coin(500, "uarch"), coin(500, "uusdc")])
+        .unwrap();
+    assert_eq!(
+        suite.position("ulp-1").unwrap().position.amount.u128(),
+        1_500
+    );
+    assert_eq!(pool_liquidity(&suite).u128(), 1_500);
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 1_500);
+
+    // Decreasing withdraws through the router, a full exit must burn instead
+    let decrease = |amount| WrapperExecuteMsg::DecreaseLiquidity {
+        token_id: "ulp-1".to_string(),
+        amount: Uint128::new(amount),
+        min_amounts_out: vec![],
+        deadline: None,
+    };
+    let err = suite
+        .execute_wrapper(ALICE, &decrease(1_500), &[])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("Invalid position amount"));
+    suite.execute_wrapper(ALICE, &decrease(600), &[]).unwrap();
+    assert_eq!(suite.position("ulp-1").unwrap().position.amount.u128(), 900);
+    assert_eq!(suite.owner_of("ulp-1").unwrap(), ALICE);
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 900);
+
+    // Drift on the router is reconciled by the admin only
+    suite
+        .router_sudo(SudoMsg::SetPosition {
+            token_id: "ulp-1".to_string(),
+            amount: Uint128::new(800),
+        })
+        .unwrap();
+    let sync = WrapperExecuteMsg::SyncPosition {
+        token_id: "ulp-1".to_string(),
+    };
+    let err = suite.execute_wrapper(ALICE, &sync, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(ADMIN, &sync, &[]).unwrap();
+    assert_eq!(suite.position("ulp-1").unwrap().position.amount.u128(), 800);
+    assert_eq!(pool_liquidity(&suite).u128(), 800);
+
+    // A position the router no longer holds is closed out
+    suite
+        .router_sudo(SudoMsg::SetPosition {
+            token_id: "ulp-1".to_string(),
+            amount: Uint128::zero(),
+        })
+        .unwrap();
+    suite.execute_wrapper(ADMIN, &sync, &[]).unwrap();
+    assert!(suite.position("ulp-1").is_none());
+    assert!(suite.owner_of("ulp-1").is_err());
+    assert!(pool_liquidity(&suite).is_zero());
+}
+
+#[test]
+fn positions_page_by_owner_pool_and_chain() {
+    let mut suite = Suite::new();
+    for sender in [ALICE, BOB, ALICE] {
+        suite.add_liquidity(sender, "pool1", 1_000).unwrap();
+    }
+    let token_ids = |suite: &Suite, msg: WrapperQueryMsg| -> Vec<String> {
+        let positions: Vec<PositionInfo> = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.wrapper, &msg)
+            .unwrap();
+        positions.into_iter().map(|info| info.token_id).collect()
+    };
+    let by_owner = |owner: &str, start_after: Option<&str>| WrapperQueryMsg::GetPositionsByOwner {
+        owner: owner.to_string(),
+        start_after: start_after.map(str::to_string),
+        limit: Some(1),
+    };
+    let by_chain = |chain_id: &str| WrapperQueryMsg::GetChainPositions {
+        chain_id: chain_id.to_string(),
+        start_after: None,
+        limit: None,
+    };
+
+    assert_eq!(token_ids(&suite, by_owner(ALICE, None)), ["ulp-1"]);
+    assert_eq!(token_ids(&suite, by_owner(ALICE, Some("ulp-1"))), ["ulp-3"]);
+    assert!(token_ids(&suite, by_owner(ALICE, Some("ulp-3"))).is_empty());
+    assert_eq!(
+        token_ids(
+            &suite,
+            WrapperQueryMsg::GetPoolPositions {
+                pool_id: "pool1".to_string(),
+                start_after: Some("ulp-1".to_string()),
+                limit: None,
+            }
+        ),
+        ["ulp-2", "ulp-3"]
+    );
+
+    // Indexes follow the NFT and the position across chains
+    suite
+        .execute_nft(
+            ALICE,
+            &ul_nft_core::msg::ExecuteMsg::TransferNft {
+                recipient: BOB.to_string(),
+                token_id: "ulp-1".to_string(),
+            },
+        )
+        .unwrap();
+    suite
+        .execute_wrapper(
+            BOB,
+            &WrapperExecuteMsg::TransferPosition {
+                token_id: "ulp-2".to_string(),
+                to_chain_id: "osmosis".to_string(),
+                min_amount_out: None,
+                max_fee: None,
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(token_ids(&suite, by_owner(ALICE, None)), ["ulp-3"]);
+    assert_eq!(token_ids(&suite, by_owner(BOB, Some("ulp-1"))), ["ulp-2"]);
+    assert_eq!(token_ids(&suite, by_chain("archway")), ["ulp-1", "ulp-3"]);
+    assert_eq!(token_ids(&suite, by_chain("osmosis")), ["ulp-2"]);
+
+    // Closed positions drop out
+    suite.remove_liquidity(ALICE, "ulp-3", None).unwrap();
+    assert!(token_ids(&suite, by_owner(ALICE, None)).is_empty());
+    assert_eq!(token_ids(&suite, by_chain("archway")), ["ulp-1"]);
+}
+
+#[test]
+fn tvl_follows_positions_and_reconciles() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite.add_liquidity(BOB, "pool1", 500).unwrap();
+    suite.add_liquidity(ALICE, "pool1", 200).unwrap();
+    let tvl = |liquidity: u128, positions: u64, depositors: u64| Tvl {
+        liquidity: Uint128::new(liquidity),
+        positions,
+        depositors,
+    };
+    let query = |suite: &Suite, msg: WrapperQueryMsg| -> Tvl {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.wrapper, &msg)
+            .unwrap()
+    };
+    let pool = || WrapperQueryMsg::GetPoolTvl {
+        pool_id: "pool1".to_string(),
+    };
+    let chain = |chain_id: &str| WrapperQueryMsg::GetChainTvl {
+        chain_id: chain_id.to_string(),
+    };
+    let total = |suite: &Suite| -> TvlResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.wrapper, &WrapperQueryMsg::GetTvl {})
+            .unwrap()
+    };
+    assert_eq!(query(&suite, pool()), tvl(1_700, 3, 2));
+    assert_eq!(query(&suite, chain("archway")), tvl(1_700, 3, 2));
+
+    // Moves shift liquidity between chains, sales between depositors
+    suite
+        .execute_wrapper(
+            BOB,
+            &WrapperExecuteMsg::TransferPosition {
+                token_id: "ulp-2".to_string(),
+                to_chain_id: "osmosis".to_string(),
+                min_amount_out: None,
+                max_fee: None,
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute_nft(
+            ALICE,
+            &ul_nft_core::msg::ExecuteMsg::TransferNft {
+                recipient: BOB.to_string(),
+                token_id: "ulp-3".to_string(),
+            },
+        )
+        .unwrap();
+    suite.remove_liquidity(ALICE, "ulp-1", None).unwrap();
+    assert_eq!(query(&suite, pool()), tvl(700, 2, 1));
+    assert_eq!(query(&suite, chain("archway")), tvl(200, 1, 1));
+    assert_eq!(query(&suite, chain("osmosis")), tvl(500, 1, 1));
+    assert_eq!(total(&suite).total, tvl(700, 2, 1));
+
+    // Syncing a drifted position adjusts the totals
+    suite
+        .router_sudo(SudoMsg::SetPosition {
+            token_id: "ulp-2".to_string(),
+            amount: Uint128::new(450),
+        })
+        .unwrap();
+    let sync = WrapperExecuteMsg::SyncPosition {
+        token_id: "ulp-2".to_string(),
+    };
+    suite.execute_wrapper(ADMIN, &sync, &[]).unwrap();
+    assert_eq!(query(&suite, chain("osmosis")), tvl(450, 1, 1));
+
+    // Only the admin reconciles, a batch at a time
+    let reconcile = WrapperExecuteMsg::Reconcile { limit: Some(1) };
+    let err = suite.execute_wrapper(BOB, &reconcile, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(ADMIN, &reconcile, &[]).unwrap();
+    let status = total(&suite);
+    assert!(status.reconciling);
+    assert_eq!(status.total, tvl(450, 1, 1));
+
+    // Positions the batches have not reached yet change without effect
+    suite.remove_liquidity(BOB, "ulp-3", Some(50)).unwrap();
+    let response = suite.execute_wrapper(ADMIN, &reconcile, &[]).unwrap();
+    assert!(response.has_event(&Event::new("wasm").add_attribute("done", "false")));
+    let response = suite.execute_wrapper(ADMIN, &reconcile, &[]).unwrap();
+    assert!(response.has_event(&Event::new("wasm").add_attribute("done", "true")));
+    let status = total(&suite);
+    assert!(!status.reconciling);
+    assert_eq!(status.total, tvl(600, 2, 1));
+    assert_eq!(query(&suite, pool()), tvl(600, 2, 1));
+    assert_eq!(query(&suite, chain("archway")), tvl(150, 1, 1));
+    assert_eq!(
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart::<Uint128>(
+                &suite.wrapper,
+                &WrapperQueryMsg::GetPoolLiquidity {
+                    pool_id: "pool1".to_string(),
+                },
+            )
+            .unwrap(),
+        Uint128::new(600)
+    );
+}
+
+#[test]
+fn locked_positions_earn_boosts_and_pay_to_leave_early() {
+    let mut suite = Suite::new();
+    let tiers = vec![
+        LockTier {
+            duration: 100,
+            multiplier: Decimal::percent(150),
+        },
+        LockTier {
+            duration: 1_000,
+            multiplier: Decimal::percent(200),
+        },
+    ];
+    let set_tiers = |early_exit_penalty| WrapperExecuteMsg::SetLockTiers {
+        tiers: tiers.clone(),
+        early_exit_penalty,
+    };
+    let err = suite
+        .execute_wrapper(ALICE, &set_tiers(None), &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    let err = suite
+        .execute_wrapper(ADMIN, &set_tiers(Some(Decimal::percent(150))), &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("at most 1"));
+    suite
+        .execute_wrapper(ADMIN, &set_tiers(Some(Decimal::percent(10))), &[])
+        .unwrap();
+
+    // Alice locks at deposit, the 500 seconds qualify for the 100 second tier
+    let deposit = |lock_duration| WrapperExecuteMsg::AddLiquidity {
+        pool_id: "pool1".to_string(),
+        chain_id: "archway".to_string(),
+        token_pair: ("uarch".to_string(), "uusdc".to_string()),
+        amounts: (Uint128::new(1_000), Uint128::new(1_000)),
+        min_shares: Uint128::zero(),
+        lock_duration,
+    };
+    let funds = [coin(1_000, "uarch"), coin(1_000, "uusdc")];
+    let err = suite
+        .execute_wrapper(ALICE, &deposit(Some(50)), &funds)
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("No lock tier"));
+    suite
+        .execute_wrapper(ALICE, &deposit(Some(500)), &funds)
+        .unwrap();
+    let now = suite.app.block_info().time.seconds();
+    let alice_lock = TimeLock {
+        locked_at: now,
+        unlocks_at: now + 500,
+        multiplier: Decimal::percent(150),
+    };
+    let lock_of = |suite: &Suite, token_id: &str| -> LockStatusResponse {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &suite.wrapper,
+                &WrapperQueryMsg::GetLock {
+                    token_id: token_id.to_string(),
+                },
+            )
+            .unwrap()
+    };
+    let status = lock_of(&suite, "ulp-1");
+    assert_eq!(status.lock, Some(alice_lock.clone()));
+    assert!(status.locked);
+
+    // The UL-NFT shows when the position unlocks
+    let attributes_of = |suite: &Suite, token_id: &str| -> Vec<ul_nft_core::state::Attribute> {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart::<ul_nft_core::msg::AttributesResponse>(
+                &suite.ul_nft,
+                &ul_nft_core::msg::QueryMsg::Extension {
+                    msg: ul_nft_core::msg::PositionQueryMsg::Attributes {
+                        token_id: token_id.to_string(),
+                    },
+                },
+            )
+            .unwrap()
+            .attributes
+    };
+    let attributes = attributes_of(&suite, "ulp-1");
+    assert_eq!(attributes.len(), 1);
+    assert_eq!(attributes[0].trait_type, "locked_until");
+    assert_eq!(attributes[0].value, alice_lock.unlocks_at.to_string());
+
+    // Bob locks an existing position, and may only push the lock later
+    suite.add_liquidity(BOB, "pool1", 1_000).unwrap();
+    let lock = |duration| WrapperExecuteMsg::LockPosition {
+        token_id: "ulp-2".to_string(),
+        duration,
+    };
+    let err = suite.execute_wrapper(ALICE, &lock(1_000), &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(BOB, &lock(1_000), &[]).unwrap();
+    let err = suite.execute_wrapper(BOB, &lock(100), &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("No lock tier"));
+    let err = suite
+        .execute_wrapper(BOB, &lock(u64::MAX), &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("No lock tier"));
+    assert_eq!(
+        lock_of(&suite, "ulp-2").lock.unwrap().multiplier,
+        Decimal::percent(200)
+    );
+
+    // Locked positions stay on their chain and cannot be zapped out
+    let err = suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::TransferPosition {
+                token_id: "ulp-1".to_string(),
+                to_chain_id: "osmosis".to_string(),
+                min_amount_out: None,
+                max_fee: None,
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("time-locked"));
+    let err = suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::ZapOut {
+                token_id: "ulp-1".to_string(),
+                amount: None,
+                output_denom: "uarch".to_string(),
+                min_amount_out: Uint128::zero(),
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("time-locked"));
+
+    // Boosts are paid from the reserve as far as it goes
+    suite.mint(&Addr::unchecked(KEEPER), 100, "uatom");
+    suite
+        .execute_wrapper(
+            KEEPER,
+            &WrapperExecuteMsg::FundBoostReserve {},
+            &coins(100, "uatom"),
+        )
+        .unwrap();
+    let router = suite.router.clone();
+    suite.mint(&router, 400, "uatom");
+    suite
+        .router_sudo(SudoMsg::AccrueRewards {
+            pool_id: "pool1".to_string(),
+            rewards: coins(400, "uatom"),
+        })
+        .unwrap();
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(100));
+    for (owner, token_id) in [(ALICE, "ulp-1"), (BOB, "ulp-2")] {
+        suite
+            .execute_wrapper(
+                owner,
+                &WrapperExecuteMsg::ClaimRewards {
+                    token_id: token_id.to_string(),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+    // Alice earns half again on her 200, Bob's boost finds the reserve empty
+    assert_eq!(suite.balance(ALICE, "uatom"), INITIAL_BALANCE + 300);
+    assert_eq!(suite.balance(BOB, "uatom"), INITIAL_BALANCE + 100);
+    let history: Vec<RewardClaim> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.wrapper,
+            &WrapperQueryMsg::GetRewardHistory {
+                token_id: "ulp-1".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history[0].rewards, coins(300, "uatom"));
+    assert_eq!(history[0].boost, coins(100, "uatom"));
+
+    // Moving to a higher tier starts the lock over, so the new multiplier
+    // only boosts rewards from now on
+    let now = suite.app.block_info().time.seconds();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::LockPosition {
+                token_id: "ulp-1".to_string(),
+                duration: 1_000,
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        lock_of(&suite, "ulp-1").lock,
+        Some(TimeLock {
+            locked_at: now,
+            unlocks_at: now + 1_000,
+            multiplier: Decimal::percent(200),
+        })
+    );
+
+    // Leaving early forfeits a tenth of the tokens to the reserve and ends
+    // the lock on what is left
+    suite.remove_liquidity(ALICE, "ulp-1", Some(500)).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 550);
+    assert!(!lock_of(&suite, "ulp-1").locked);
+    assert!(attributes_of(&suite, "ulp-1").is_empty());
+    suite.remove_liquidity(ALICE, "ulp-1", None).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 50);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE - 50);
+    assert_eq!(lock_of(&suite, "ulp-1").lock, None);
+    let reserve: BoostReserveResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.wrapper, &WrapperQueryMsg::GetBoostReserve {})
+        .unwrap();
+    assert_eq!(reserve.funds, vec![coin(50, "uarch"), coin(50, "uusdc")]);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 50);
+
+    // Without a penalty locked positions cannot leave until they unlock
+    suite.execute_wrapper(ADMIN, &set_tiers(None), &[]).unwrap();
+    let err = suite.remove_liquidity(BOB, "ulp-2", None).unwrap_err();
+    assert!(err.root_cause().to_string().contains("time-locked"));
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(1_000));
+    assert!(!lock_of(&suite, "ulp-2").locked);
+    // The expired lock leaves the UL-NFT the next time the position changes
+    assert_eq!(attributes_of(&suite, "ulp-2").len(), 1);
+    suite.remove_liquidity(BOB, "ulp-2", Some(500)).unwrap();
+    assert!(attributes_of(&suite, "ulp-2").is_empty());
+    suite.remove_liquidity(BOB, "ulp-2", None).unwrap();
+    assert_eq!(suite.balance(BOB, "uarch"), INITIAL_BALANCE);
+}
+
+#[test]
+fn pausing_blocks_new_operations_but_owners_can_exit() {
+    const GUARDIAN: &str = "guardian";
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite
+        .execute_wrapper(
+            ADMIN,
+            &WrapperExecuteMsg::SetLockTiers {
+                tiers: vec![LockTier {
+                    duration: 100,
+                    multiplier: Decimal::percent(150),
+                }],
+                early_exit_penalty: Some(Decimal::percent(10)),
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::LockPosition {
+                token_id: "ulp-1".to_string(),
+                duration: 1_000,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let set_guardian = WrapperExecuteMsg::SetGuardian {
+        guardian: Some(GUARDIAN.to_string()),
+    };
+    let err = suite
+        .execute_wrapper(GUARDIAN, &set_guardian, &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(ADMIN, &set_guardian, &[]).unwrap();
+
+    // The guardian pauses deposits and cross-chain moves
+    let pause = WrapperExecuteMsg::Pause {
+        actions: vec![PausableAction::AddLiquidity, PausableAction::Transfer],
+    };
+    let err = suite.execute_wrapper(BOB, &pause, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    let response = suite.execute_wrapper(GUARDIAN, &pause, &[]).unwrap();
+    assert!(response.has_event(
+        &Event::new("wasm-paused")
+            .add_attribute("actions", "add_liquidity,transfer")
+            .add_attribute("sender", GUARDIAN)
+    ));
+
+    let err = suite.add_liquidity(BOB, "pool1", 1_000).unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("AddLiquidity is paused"));
+    let err = suite
+        .execute_wrapper(
+            ALICE,
+            &WrapperExecuteMsg::TransferPosition {
+                token_id: "ulp-1".to_string(),
+                to_chain_id: "osmosis".to_string(),
+                min_amount_out: None,
+                max_fee: None,
+                deadline: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Transfer is paused"));
+
+    // Only the admin resumes
+    let unpause = |actions| WrapperExecuteMsg::Unpause { actions };
+    let err = suite
+        .execute_wrapper(GUARDIAN, &unpause(vec![PausableAction::Transfer]), &[])
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+
+    // The emergency exit stays closed while withdrawals still work
+    let exit = |token_id: &str| WrapperExecuteMsg::EmergencyWithdraw {
+        token_id: token_id.to_string(),
+    };
+    let err = suite
+        .execute_wrapper(ALICE, &exit("ulp-1"), &[])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("RemoveLiquidity is not paused"));
+
+    // Pausing withdrawals too opens it, leaving the lock early still costs
+    // the penalty
+    suite
+        .execute_wrapper(
+            ADMIN,
+            &WrapperExecuteMsg::Pause {
+                actions: vec![PausableAction::RemoveLiquidity],
+            },
+            &[],
+        )
+        .unwrap();
+    let state: PauseState = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.wrapper, &WrapperQueryMsg::GetPauseState {})
+        .unwrap();
+    assert_eq!(
+        state.paused,
+        vec![
+            PausableAction::AddLiquidity,
+            PausableAction::Transfer,
+            PausableAction::RemoveLiquidity
+        ]
+    );
+    assert_eq!(state.updated_by, Some(Addr::unchecked(ADMIN)));
+    let err = suite.remove_liquidity(ALICE, "ulp-1", None).unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("RemoveLiquidity is paused"));
+
+    let err = suite.execute_wrapper(BOB, &exit("ulp-1"), &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(ALICE, &exit("ulp-1"), &[]).unwrap();
+    assert_eq!(suite.balance(ALICE, "uarch"), INITIAL_BALANCE - 100);
+    assert_eq!(suite.balance(ALICE, "uusdc"), INITIAL_BALANCE - 100);
+    assert_eq!(suite.balance(&suite.wrapper, "uarch"), 100);
+    assert!(suite.position("ulp-1").is_none());
+
+    // Once withdrawals resume the emergency exit closes, pausing anything
+    // else does not reopen it
+    let response = suite
+        .execute_wrapper(
+            ADMIN,
+            &unpause(vec![
+                PausableAction::AddLiquidity,
+                PausableAction::RemoveLiquidity,
+                PausableAction::Transfer,
+            ]),
+            &[],
+        )
+        .unwrap();
+    assert!(response.has_event(&Event::new("wasm-unpaused").add_attribute("paused", "")));
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite
+        .execute_wrapper(
+            GUARDIAN,
+            &WrapperExecuteMsg::Pause {
+                actions: vec![PausableAction::ClaimRewards],
+            },
+            &[],
+        )
+        .unwrap();
+    let err = suite
+        .execute_wrapper(ALICE, &exit("ulp-2"), &[])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("RemoveLiquidity is not paused"));
+}
+
+#[test]
+fn cross_chain_moves_are_tracked_until_they_land() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    suite.add_liquidity(ALICE, "pool1", 2_000).unwrap();
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::TransferLiquidity,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+    let transfer = |token_id: &str| WrapperExecuteMsg::TransferPosition {
+        token_id: token_id.to_string(),
+        to_chain_id: "osmosis".to_string(),
+        min_amount_out: None,
+        max_fee: None,
+        deadline: None,
+    };
+    let status = |suite: &Suite, token_id: &str| -> Transfer {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart::<Option<Transfer>>(
+                &suite.wrapper,
+                &WrapperQueryMsg::GetTransferStatus {
+                    token_id: token_id.to_string(),
+                },
+            )
+            .unwrap()
+            .unwrap()
+    };
+
+    // The position is locked while the move is in flight
+    suite
+        .execute_wrapper(ALICE, &transfer("ulp-1"), &[])
+        .unwrap();
+    assert_eq!(status(&suite, "ulp-1").status, TransferStatus::InFlight);
+    let err = suite.remove_liquidity(ALICE, "ulp-1", None).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Position locked"));
+
+    // The router's callback completes it
+    suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .unwrap();
+    assert_eq!(status(&suite, "ulp-1").status, TransferStatus::Completed);
+    assert_eq!(
+        suite.position("ulp-1").unwrap().position.chain_id,
+        "osmosis"
+    );
+
+    // A move the router never finishes can be recovered once it timed out
+    suite
+        .execute_wrapper(
+            ADMIN,
+            &WrapperExecuteMsg::SetTransferTimeout { timeout: 600 },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute_wrapper(ALICE, &transfer("ulp-2"), &[])
+        .unwrap();
+    let recover = WrapperExecuteMsg::RecoverTimedOutTransfer {
+        token_id: "ulp-2".to_string(),
+    };
+    let err = suite.execute_wrapper(ALICE, &recover, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("not timed out"));
+    // A timeout too long to ever pass does not overflow
+    let set_timeout = |suite: &mut Suite, timeout: u64| {
+        suite
+            .execute_wrapper(
+                ADMIN,
+                &WrapperExecuteMsg::SetTransferTimeout { timeout },
+                &[],
+            )
+            .unwrap();
+    };
+    set_timeout(&mut suite, u64::MAX);
+    let err = suite.execute_wrapper(ALICE, &recover, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("not timed out"));
+    set_timeout(&mut suite, 600);
+    suite
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(600));
+    let err = suite.execute_wrapper(BOB, &recover, &[]).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Unauthorized"));
+    suite.execute_wrapper(ALICE, &recover, &[]).unwrap();
+
+    let recovered = status(&suite, "ulp-2");
+    assert_eq!(recovered.status, TransferStatus::TimedOut);
+    assert_eq!(
+        suite.position("ulp-2").unwrap().position.chain_id,
+        "archway"
+    );
+    assert!(suite.pending_operations(None).is_empty());
+    suite.remove_liquidity(ALICE, "ulp-2", Some(500)).unwrap();
+
+    // A late callback no longer applies
+    assert!(suite
+        .router_sudo(SudoMsg::Complete { id: 2, error: None })
+        .is_err());
+
+    // Failures are recorded on the transfer
+    suite
+        .execute_wrapper(ALICE, &transfer("ulp-2"), &[])
+        .unwrap();
+    suite
+        .router_sudo(SudoMsg::Complete {
+            id: 3,
+            error: Some("channel closed".to_string()),
+        })
+        .unwrap();
+    let failed = status(&suite, "ulp-2");
+    assert_eq!(failed.status, TransferStatus::Failed);
+    assert_eq!(failed.error.as_deref(), Some("channel closed"));
+}
+
+#[test]
+fn registry_entries_with_positions_cannot_be_removed() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    let remove_pool = WrapperExecuteMsg::RemovePool {
+        pool_id: "pool1".to_string(),
+    };
+    let remove_chain = WrapperExecuteMsg::RemoveChain {
+        chain_id: "archway".to_string(),
+    };
+
+    let err = suite.execute_wrapper(ADMIN, &remove_pool, &[]).unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("still backs positions"));
+    let err = suite
+        .execute_wrapper(ADMIN, &remove_chain, &[])
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .to_string()
+        .contains("still hosts positions"));
+
+    // Once the last position is withdrawn both can go
+    suite.remove_liquidity(ALICE, "ulp-1", None).unwrap();
+    assert!(suite.position("ulp-1").is_none());
+    suite.execute_wrapper(ADMIN, &remove_pool, &[]).unwrap();
+    suite.execute_wrapper(ADMIN, &remove_chain, &[]).unwrap();
+}
+
+#[test]
+fn marketplace_sale_moves_position_authority() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+
+    let marketplace = suite.marketplace.to_string();
+    suite
+        .execute_nft(
+            ALICE,
+            &ul_nft_core::msg::ExecuteMsg::Approve {
+                spender: marketplace,
+                token_id: "ulp-1".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ALICE),
+            suite.marketplace.clone(),
+            &marketplace::msg::ExecuteMsg::ListNFT {
+                token_id: "ulp-1".to_string(),
+                price: Uint128::new(10_000),
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(BOB),
+            suite.marketplace.clone(),
+            &marketplace::msg::ExecuteMsg::BuyNFT {
+                token_id: "ulp-1".to_string(),
+            },
+            &coins(10_000, "uarch"),
+        )
+        .unwrap();
+
+    assert_eq!(suite.owner_of("ulp-1").unwrap(), BOB);
+    assert_eq!(suite.position("ulp-1").unwrap().owner, Addr::unchecked(BOB));
+
+    // The seller lost control of the liquidity, the buyer withdraws it
+    assert!(suite.remove_liquidity(ALICE, "ulp-1", None).is_err());
+    suite.remove_liquidity(BOB, "ulp-1", None).unwrap();
+    assert_eq!(suite.balance(BOB, "uusdc"), INITIAL_BALANCE + 1_000);
+}
+
+#[test]
+fn positions_with_operations_in_flight_cannot_change_hands() {
+    let mut suite = Suite::new();
+    suite.add_liquidity(ALICE, "pool1", 1_000).unwrap();
+    let marketplace = suite.marketplace.to_string();
+    suite
+        .execute_nft(
+            ALICE,
+            &ul_nft_core::msg::ExecuteMsg::Approve {
+                spender: marketplace,
+                token_id: "ulp-1".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(ALICE),
+            suite.marketplace.clone(),
+            &marketplace::msg::ExecuteMsg::ListNFT {
+                token_id: "ulp-1".to_string(),
+                price: Uint128::new(10_000),
+            },
+            &[],
+        )
+        .unwrap();
+
+    // Alice starts draining the position to herself before it sells
+    suite
+        .router_sudo(SudoMsg::SetBehaviour {
+            action: EuclidAction::RemoveLiquidity,
+            behaviour: Behaviour::Delay,
+        })
+        .unwrap();
+    suite.remove_liquidity(ALICE, "ulp-1", Some(600)).unwrap();
+    let buy = |suite: &mut Suite| {
+        suite.app.execute_contract(
+            Addr::unchecked(BOB),
+            suite.marketplace.clone(),
+            &marketplace::msg::ExecuteMsg::BuyNFT {
+                token_id: "ulp-1".to_string(),
+            },
+            &coins(10_000, "uarch"),
+        )
+    };
+    let err = buy(&mut suite).unwrap_err();
+    assert!(err.root_cause().to_string().contains("Position locked"));
+    assert_eq!(suite.owner_of("ulp-1").unwrap(), ALICE);
+    assert_eq!(suite.balance(BOB, "uarch"), INITIAL_BALANCE);
+
+    // Once the withdrawal settles the buyer gets what is left
+    suite
+        .router_sudo(SudoMsg::Complete { id: 1, error: None })
+        .unwrap();
+    buy(&mut suite).unwrap();
+    assert_eq!(suite.owner_of("ulp-1").unwrap(), BOB);
+    assert_eq!(
+        suite.position("ulp-1").unwrap().position.amount,
+        Uint128::new(400)
+    );
+}
@@ -1,25 +1,52 @@
 use crate::error::ContractError;
-use crate::msg::{EuclidAction, EuclidMsg, EuclidResponse, ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::euclid::{
+    parse_router_response, AddLiquidityRequest, AddLiquidityResponse, ClaimRewardsRequest,
+    ClaimRewardsResponse, EuclidAction, EuclidFactory, EuclidRequest, EuclidResponse, EuclidRouter,
+    RemoveLiquidityRequest, RemoveLiquidityResponse, SwapExactInRequest, SwapExactOutRequest,
+    SwapResponse, TransferLiquidityRequest, TransferLiquidityResponse,
+};
+use crate::msg::{
+    BoostReserveResponse, ExecuteMsg, InstantiateMsg, LockStatusResponse, PortfolioValueResponse,
+    PositionValueResponse, QueryMsg, ReceiveMsg, RewardsResponse, TvlResponse,
+};
+use crate::oracle::{OracleContract, PoolReserveOracle, PriceOracle};
 use crate::state::{
-    Config, LiquidityPosition, PositionInfo, CONFIG, PENDING_OPERATIONS, POOL_LIQUIDITY, POSITIONS,
+    Compound, Config, EarlyExit, FailedOperation, LiquidityPosition, LockTier, OperationLimits,
+    PausableAction, PauseState, PendingOperation, PoolStatus, PositionInfo, RegisteredChain,
+    RegisteredPool, RewardClaim, RewardLedger, SwapOrder, TimeLock, Transfer, TransferStatus, Tvl,
+    Zap, ZapKind, AUTO_COMPOUND, BOOST_RESERVE, CHAINS, CHAIN_TVL, CONFIG, CW20_DEPOSITS,
+    DEFAULT_DEPOSIT_RATIO_TOLERANCE, DEFAULT_MAX_SLIPPAGE, DEFAULT_QUOTE_DENOM,
+    DEFAULT_TRANSFER_TIMEOUT, FAILED_OPERATIONS, LOCKED_POSITIONS, OPERATION_COUNT,
+    OPERATION_LIMITS, PAUSE_STATE, PENDING_COMPOUNDS, PENDING_CW20, PENDING_EXITS, PENDING_LOCKS,
+    PENDING_OPERATIONS, PENDING_SWAPS, PENDING_ZAPS, POOLS, POOL_TVL, POSITIONS, RECONCILING,
+    REWARD_HISTORY, REWARD_LEDGERS, TIME_LOCKS, TOKEN_COUNT, TOTAL_TVL, TRANSFERS,
 };
 use cosmwasm_std::{
-    from_json, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, ReplyOn,
-    Response, StdError, StdResult, SubMsg, Uint128, WasmMsg,
+    from_json, to_json_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, Event,
+    MessageInfo, Order, Reply, ReplyOn, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgResult, Uint128, Uint64, WasmMsg,
 };
 use cw2::set_contract_version;
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw721::{ApprovalResponse, OwnerOfResponse};
+use cw_storage_plus::{Bound, Prefix};
+use cw_utils::must_pay;
+use ul_nft_core::msg::{ExecuteMsg as NftExecuteMsg, PositionExecuteMsg, QueryMsg as NftQueryMsg};
+use ul_nft_core::state::Attribute;
 
 // Contract name and version
 const CONTRACT_NAME: &str = "crates.io:liquidity-wrapper";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-// Reply IDs for submessages
-const REPLY_ADD_LIQUIDITY: u64 = 1;
-const REPLY_REMOVE_LIQUIDITY: u64 = 2;
-const REPLY_TRANSFER_POSITION: u64 = 3;
+// Pagination defaults for list queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+
+// Upper bound on the keeper's share of compounded rewards
+const MAX_COMPOUND_BOUNTY: Decimal = Decimal::percent(10);
+
+// UL-NFT attribute showing when a position unlocks
+const LOCK_ATTRIBUTE: &str = "locked_until";
 
 #[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn instantiate(
@@ -33,6 +60,15 @@ pub fn instantiate(
         ul_nft_contract: deps.api.addr_validate(&msg.ul_nft_contract)?,
         euclid_router: deps.api.addr_validate(&msg.euclid_router)?,
         euclid_factory: deps.api.addr_validate(&msg.euclid_factory)?,
+        compound_bounty: Decimal::zero(),
+        transfer_timeout: DEFAULT_TRANSFER_TIMEOUT,
+        price_oracle: None,
+        quote_denom: DEFAULT_QUOTE_DENOM.to_string(),
+        lock_tiers: vec![],
+        early_exit_penalty: None,
+        guardian: None,
+        max_slippage: DEFAULT_MAX_SLIPPAGE,
+        deposit_ratio_tolerance: DEFAULT_DEPOSIT_RATIO_TOLERANCE,
     };
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
@@ -57,484 +93,4876 @@ pub fn execute(
             pool_id,
             chain_id,
             token_pair,
-            amount,
+            amounts,
             min_shares,
+            lock_duration,
         } => execute_add_liquidity(
-            deps, env, info, pool_id, chain_id, token_pair, amount, min_shares,
+            deps,
+            env,
+            info,
+            pool_id,
+            chain_id,
+            token_pair,
+            amounts,
+            min_shares,
+            lock_duration,
         ),
-        ExecuteMsg::RemoveLiquidity { token_id, amount } => {
-            execute_remove_liquidity(deps, env, info, token_id, amount)
-        }
+        ExecuteMsg::RemoveLiquidity {
+            token_id,
+            amount,
+            min_amounts_out,
+            deadline,
+        } => execute_remove_liquidity(deps, env, info, token_id, amount, min_amounts_out, deadline),
         ExecuteMsg::TransferPosition {
             token_id,
             to_chain_id,
-        } => execute_transfer_position(deps, env, info, token_id, to_chain_id),
-        ExecuteMsg::UpdatePosition {
+            min_amount_out,
+            max_fee,
+            deadline,
+        } => execute_transfer_position(
+            deps,
+            env,
+            info,
+            token_id,
+            to_chain_id,
+            OperationLimits {
+                min_amounts_out: vec![],
+                min_amount_out,
+                max_fee,
+                deadline,
+                min_shares: Uint128::zero(),
+            },
+        ),
+        ExecuteMsg::RecoverTimedOutTransfer { token_id } => {
+            execute_recover_timed_out_transfer(deps, env, info, token_id)
+        }
+        ExecuteMsg::SetChain {
+            chain_id,
+            display_name,
+            enabled,
+        } => execute_set_chain(deps, info, chain_id, display_name, enabled),
+        ExecuteMsg::RemoveChain { chain_id } => execute_remove_chain(deps, info, chain_id),
+        ExecuteMsg::SetPool {
+            pool_id,
+            token_pair,
+            chains,
+            status,
+        } => execute_set_pool(deps, info, pool_id, token_pair, chains, status),
+        ExecuteMsg::RemovePool { pool_id } => execute_remove_pool(deps, info, pool_id),
+        ExecuteMsg::SetGuardian { guardian } => execute_set_guardian(deps, info, guardian),
+        ExecuteMsg::Pause { actions } => execute_pause(deps, env, info, actions),
+        ExecuteMsg::Unpause { actions } => execute_unpause(deps, env, info, actions),
+        ExecuteMsg::EmergencyWithdraw { token_id } => {
+            execute_emergency_withdraw(deps, env, info, token_id)
+        }
+        ExecuteMsg::SetTransferTimeout { timeout } => {
+            execute_set_transfer_timeout(deps, info, timeout)
+        }
+        ExecuteMsg::SetPriceOracle {
+            oracle,
+            quote_denom,
+        } => execute_set_price_oracle(deps, info, oracle, quote_denom),
+        ExecuteMsg::SetLockTiers {
+            tiers,
+            early_exit_penalty,
+        } => execute_set_lock_tiers(deps, info, tiers, early_exit_penalty),
+        ExecuteMsg::LockPosition { token_id, duration } => {
+            execute_lock_position(deps, env, info, token_id, duration)
+        }
+        ExecuteMsg::FundBoostReserve {} => execute_fund_boost_reserve(deps, info),
+        ExecuteMsg::IncreaseLiquidity {
+            token_id,
+            amounts,
+            min_shares,
+        } => execute_increase_liquidity(deps, env, info, token_id, amounts, min_shares),
+        ExecuteMsg::DecreaseLiquidity {
+            token_id,
+            amount,
+            min_amounts_out,
+            deadline,
+        } => {
+            execute_decrease_liquidity(deps, env, info, token_id, amount, min_amounts_out, deadline)
+        }
+        ExecuteMsg::SyncPosition { token_id } => execute_sync_position(deps, env, info, token_id),
+        ExecuteMsg::Reconcile { limit } => execute_reconcile(deps, info, limit),
+        ExecuteMsg::SwapExactIn {
+            route,
+            min_amount_out,
+            deadline,
+        } => execute_swap_exact_in(deps, env, info, route, min_amount_out, deadline),
+        ExecuteMsg::SwapExactOut {
+            route,
+            amount_out,
+            max_amount_in,
+            deadline,
+        } => execute_swap_exact_out(deps, env, info, route, amount_out, max_amount_in, deadline),
+        ExecuteMsg::ZapIn {
+            pool_id,
+            chain_id,
+            input_denom,
+            min_shares,
+            max_slippage,
+            deadline,
+        } => execute_zap_in(
+            deps,
+            env,
+            info,
+            pool_id,
+            chain_id,
+            input_denom,
+            min_shares,
+            max_slippage,
+            deadline,
+        ),
+        ExecuteMsg::ZapOut {
+            token_id,
+            amount,
+            output_denom,
+            min_amount_out,
+            deadline,
+        } => execute_zap_out(
+            deps,
+            env,
+            info,
             token_id,
-            new_amount,
-        } => execute_update_position(deps, env, info, token_id, new_amount),
+            amount,
+            output_denom,
+            min_amount_out,
+            deadline,
+        ),
+        ExecuteMsg::SetMaxSlippage { max_slippage } => {
+            execute_set_max_slippage(deps, info, max_slippage)
+        }
+        ExecuteMsg::SetDepositRatioTolerance { tolerance } => {
+            execute_set_deposit_ratio_tolerance(deps, info, tolerance)
+        }
         ExecuteMsg::ClaimRewards { token_id } => execute_claim_rewards(deps, env, info, token_id),
+        ExecuteMsg::SetAutoCompound { token_id, enabled } => {
+            execute_set_auto_compound(deps, info, token_id, enabled)
+        }
+        ExecuteMsg::Compound { token_id } => execute_compound(deps, env, info, token_id),
+        ExecuteMsg::SetCompoundBounty { bounty } => execute_set_compound_bounty(deps, info, bounty),
+        ExecuteMsg::Receive(cw20_msg) => execute_receive(deps, env, info, cw20_msg),
+        ExecuteMsg::WithdrawCw20 { token, amount } => {
+            execute_withdraw_cw20(deps, info, token, amount)
+        }
+        ExecuteMsg::NftTransferred {
+            token_id,
+            previous_owner,
+            new_owner,
+        } => execute_nft_transferred(deps, info, token_id, previous_owner, new_owner),
+        ExecuteMsg::EuclidCallback { tx_id, response } => {
+            execute_euclid_callback(deps, env, info, tx_id, response)
+        }
+    }
+}
+
+// Authority over a position follows the UL-NFT: its owner, an approved
+// spender or an operator of the owner may act on it
+fn assert_nft_authority(deps: Deps, token_id: &str, sender: &Addr) -> Result<(), ContractError> {
+    let ul_nft_contract = CONFIG.load(deps.storage)?.ul_nft_contract;
+    if nft_owner(deps, token_id)? == *sender {
+        return Ok(());
+    }
+
+    // Covers both per-token approvals and operators of the owner
+    let approval: StdResult<ApprovalResponse> = deps.querier.query_wasm_smart(
+        &ul_nft_contract,
+        &NftQueryMsg::Approval {
+            token_id: token_id.to_string(),
+            spender: sender.to_string(),
+            include_expired: Some(false),
+        },
+    );
+    match approval {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ContractError::Unauthorized {}),
+    }
+}
+
+fn nft_owner(deps: Deps, token_id: &str) -> StdResult<Addr> {
+    let ul_nft_contract = CONFIG.load(deps.storage)?.ul_nft_contract;
+    let owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+        &ul_nft_contract,
+        &NftQueryMsg::OwnerOf {
+            token_id: token_id.to_string(),
+            include_expired: Some(false),
+        },
+    )?;
+    Ok(Addr::unchecked(owner.owner))
+}
+
+pub fn execute_nft_transferred(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_id: String,
+    previous_owner: String,
+    new_owner: String,
+) -> Result<Response, ContractError> {
+    // Only the UL-NFT contract reports transfers
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.ul_nft_contract {
+        return Err(ContractError::Unauthorized {});
+    }
+    // An operation in flight pays out to the owner that started it, so the
+    // token stays put until it resolves
+    assert_not_locked(deps.storage, &token_id)?;
+
+    let new_owner = deps.api.addr_validate(&new_owner)?;
+    if let Some(previous) = POSITIONS.may_load(deps.storage, &token_id)? {
+        let mut position = previous.clone();
+        position.owner = new_owner.clone();
+        save_position(deps.storage, Some(&previous), &position)?;
     }
+
+    Ok(Response::new()
+        .add_attribute("action", "nft_transferred")
+        .add_attribute("token_id", token_id)
+        .add_attribute("previous_owner", previous_owner)
+        .add_attribute("new_owner", new_owner))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_add_liquidity(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     pool_id: String,
     chain_id: String,
     token_pair: (String, String),
-    amount: Uint128,
+    amounts: (Uint128, Uint128),
+    min_shares: Uint128,
+    lock_duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    // The amounts follow their tokens into the pair's sorted order
+    let amounts = if token_pair.0 <= token_pair.1 {
+        amounts
+    } else {
+        (amounts.1, amounts.0)
+    };
+    let token_pair = canonical_pair(token_pair)?;
+    // The id is reserved now so the router tracks the position under it
+    let token_id = next_token_id(deps.storage)?;
+    // The lock starts once the deposit settles and the position exists
+    if let Some(duration) = lock_duration {
+        let tier = lock_tier(&CONFIG.load(deps.storage)?, duration)?;
+        env.block
+            .time
+            .seconds()
+            .checked_add(duration)
+            .ok_or(ContractError::InvalidLockDuration {})?;
+        PENDING_LOCKS.save(deps.storage, &token_id, &tier)?;
+    }
+    submit_deposit(
+        deps, env, info, token_id, pool_id, chain_id, token_pair, amounts, min_shares,
+    )
+}
+
+// Adds liquidity to an existing position, paid like `AddLiquidity`
+pub fn execute_increase_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    amounts: (Uint128, Uint128),
+    min_shares: Uint128,
+) -> Result<Response, ContractError> {
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+
+    let position = position.position;
+    submit_deposit(
+        deps,
+        env,
+        info,
+        token_id,
+        position.pool_id,
+        position.chain_id,
+        position.token_pair,
+        amounts,
+        min_shares,
+    )
+}
+
+// Sends a deposit for `token_id` to the router, the reply mints the UL-NFT
+// or grows the position when it already exists
+#[allow(clippy::too_many_arguments)]
+fn submit_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    pool_id: String,
+    chain_id: String,
+    token_pair: (String, String),
+    amounts: (Uint128, Uint128),
     min_shares: Uint128,
 ) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::AddLiquidity)?;
+    let pool = assert_pool_available(deps.storage, &pool_id, &chain_id)?;
+    if pool.token_pair != token_pair {
+        return Err(ContractError::InvalidTokenPair {});
+    }
+    let cw20_assets = collect_deposit(deps.storage, &info, &token_pair, amounts)?;
+    assert_deposit_ratio(deps.as_ref(), &pool_id, &token_pair, amounts)?;
+
     // Create Euclid message for adding liquidity
-    let euclid_msg = EuclidMsg {
+    let request = AddLiquidityRequest {
+        pool_id: pool_id.clone(),
+        chain_id: chain_id.clone(),
+        token_pair,
+        amounts,
+        min_shares,
+        cw20_assets: cw20_assets.clone(),
+        token_id: token_id.clone(),
+    };
+
+    // Add to pending operations
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
         action: EuclidAction::AddLiquidity,
-        data: to_json_binary(&json!({
-            "pool_id": pool_id,
-            "chain_id": chain_id,
-            "token_pair": token_pair,
-            "amount": amount,
-            "min_shares": min_shares,
-        }))?,
+        pool_id: pool_id.clone(),
+        chain_id,
+        token_id: Some(token_id.clone()),
+        funds: info.funds.clone(),
+        submitted_at: env.block.time.seconds(),
     };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    if POSITIONS.has(deps.storage, &token_id) {
+        LOCKED_POSITIONS.save(deps.storage, &token_id, &operation.id)?;
+    }
+    if !min_shares.is_zero() {
+        let limits = OperationLimits {
+            min_shares,
+            ..OperationLimits::default()
+        };
+        OPERATION_LIMITS.save(deps.storage, operation.id, &limits)?;
+    }
+
+    // Cw20 sides reach the router before the call that uses them
+    let router = euclid_router(deps.as_ref())?;
+    let mut transfers = vec![];
+    if !cw20_assets.is_empty() {
+        PENDING_CW20.save(deps.storage, operation.id, &cw20_assets)?;
+        for asset in cw20_assets {
+            transfers.push(WasmMsg::Execute {
+                contract_addr: asset.address,
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: router.addr().to_string(),
+                    amount: asset.amount,
+                })?,
+                funds: vec![],
+            });
+        }
+    }
 
     // Create submessage for Euclid Router
-    let msg = WasmMsg::Execute {
-        contract_addr: CONFIG.load(deps.storage)?.euclid_router.to_string(),
-        msg: to_json_binary(&euclid_msg)?,
-        funds: info.funds,
-    };
+    let msg = router.execute_tracked(&request, operation.id, info.funds)?;
 
     let submsg = SubMsg {
-        id: REPLY_ADD_LIQUIDITY,
+        id: operation.id,
         msg: msg.into(),
         gas_limit: None,
-        reply_on: ReplyOn::Success,
-        //payload: Binary::default(),
+        reply_on: ReplyOn::Always,
     };
 
-    // Add to pending operations
-    PENDING_OPERATIONS.save(deps.storage, &pool_id, &vec![info.sender.to_string()])?;
-
     Ok(Response::new()
+        .add_messages(transfers)
         .add_submessage(submsg)
         .add_attribute("action", "add_liquidity")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id)
         .add_attribute("pool_id", pool_id)
+        .add_attribute("amounts", format!("{},{}", amounts.0, amounts.1)))
+}
+
+// The router's reserves of a pool ordered like `token_pair`, None while the
+// pool is empty
+fn pool_reserves(
+    deps: Deps,
+    pool_id: &str,
+    token_pair: &(String, String),
+) -> Result<Option<(Uint128, Uint128)>, ContractError> {
+    let pool = euclid_router(deps)?.pool(&deps.querier, pool_id)?;
+    let reserves = if pool.token_pair.0 == token_pair.0 {
+        pool.reserves
+    } else {
+        (pool.reserves.1, pool.reserves.0)
+    };
+    if reserves.0.is_zero() || reserves.1.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(reserves))
+}
+
+// Deposits into a funded pool must match its ratio within the configured
+// tolerance, the first deposit sets the ratio
+fn assert_deposit_ratio(
+    deps: Deps,
+    pool_id: &str,
+    token_pair: &(String, String),
+    amounts: (Uint128, Uint128),
+) -> Result<(), ContractError> {
+    let reserves = match pool_reserves(deps, pool_id, token_pair)? {
+        Some(reserves) => reserves,
+        None => return Ok(()),
+    };
+    let expected = amounts
+        .0
+        .checked_multiply_ratio(reserves.1, reserves.0)
+        .map_err(|_| ContractError::InvalidDepositRatio {})?;
+    let difference = if amounts.1 > expected {
+        amounts.1 - expected
+    } else {
+        expected - amounts.1
+    };
+    if difference > expected * CONFIG.load(deps.storage)?.deposit_ratio_tolerance {
+        return Err(ContractError::InvalidDepositRatio {});
+    }
+    Ok(())
+}
+
+// The largest deposit out of `available`, ordered like `token_pair`, that
+// matches the pool's ratio
+fn balanced_deposit(
+    deps: Deps,
+    pool_id: &str,
+    token_pair: &(String, String),
+    available: (Uint128, Uint128),
+) -> Result<(Uint128, Uint128), ContractError> {
+    let reserves = match pool_reserves(deps, pool_id, token_pair)? {
+        Some(reserves) => reserves,
+        None => return Ok(available),
+    };
+    let second = available.0.multiply_ratio(reserves.1, reserves.0);
+    if second <= available.1 {
+        Ok((available.0, second))
+    } else {
+        Ok((
+            available.1.multiply_ratio(reserves.0, reserves.1),
+            available.1,
+        ))
+    }
+}
+
+// Either order of a pair names the same pool, positions store it sorted
+fn canonical_pair(token_pair: (String, String)) -> Result<(String, String), ContractError> {
+    let (first, second) = token_pair;
+    if first.is_empty() || second.is_empty() || first == second {
+        return Err(ContractError::InvalidTokenPair {});
+    }
+    if first < second {
+        Ok((first, second))
+    } else {
+        Ok((second, first))
+    }
+}
+
+// Checks a deposit holds exactly `amounts` of the sides of the pair. Native
+// sides must be sent along and nothing else may be; cw20 sides are taken from
+// the sender's cw20 deposits, which are debited and returned
+fn collect_deposit(
+    storage: &mut dyn Storage,
+    info: &MessageInfo,
+    token_pair: &(String, String),
+    amounts: (Uint128, Uint128),
+) -> Result<Vec<Cw20Coin>, ContractError> {
+    if amounts.0.is_zero() || amounts.1.is_zero() {
+        return Err(ContractError::InvalidPositionAmount {});
+    }
+    if info
+        .funds
+        .iter()
+        .any(|coin| coin.denom != token_pair.0 && coin.denom != token_pair.1)
+    {
+        return Err(ContractError::InvalidTokenPair {});
+    }
+
+    let mut cw20_assets = vec![];
+    for (denom, amount) in [(&token_pair.0, amounts.0), (&token_pair.1, amounts.1)] {
+        match info.funds.iter().find(|coin| &coin.denom == denom) {
+            Some(coin) if coin.amount == amount => {}
+            Some(_) => return Err(ContractError::InvalidPositionAmount {}),
+            None => {
+                let token = Addr::unchecked(denom);
+                let deposited = CW20_DEPOSITS
+                    .may_load(storage, (&info.sender, &token))?
+                    .unwrap_or_default();
+                if deposited.is_zero() {
+                    return Err(ContractError::InvalidPositionAmount {});
+                }
+                if deposited < amount {
+                    return Err(ContractError::InsufficientFunds {});
+                }
+                save_cw20_deposit(storage, &info.sender, &token, deposited - amount)?;
+                cw20_assets.push(Cw20Coin {
+                    address: denom.clone(),
+                    amount,
+                });
+            }
+        }
+    }
+    Ok(cw20_assets)
+}
+
+fn save_cw20_deposit(
+    storage: &mut dyn Storage,
+    owner: &Addr,
+    token: &Addr,
+    amount: Uint128,
+) -> StdResult<()> {
+    if amount.is_zero() {
+        CW20_DEPOSITS.remove(storage, (owner, token));
+        Ok(())
+    } else {
+        CW20_DEPOSITS.save(storage, (owner, token), &amount)
+    }
+}
+
+// Cw20 tokens arrive here, `info.sender` is the token contract
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    if cw20_msg.amount.is_zero() {
+        return Err(ContractError::InvalidPositionAmount {});
+    }
+    let owner = deps.api.addr_validate(&cw20_msg.sender)?;
+    CW20_DEPOSITS.update(
+        deps.storage,
+        (&owner, &info.sender),
+        |deposited| -> StdResult<_> {
+            Ok(deposited.unwrap_or_default().checked_add(cw20_msg.amount)?)
+        },
+    )?;
+
+    match from_json(&cw20_msg.msg)? {
+        ReceiveMsg::Deposit {} => Ok(Response::new()
+            .add_attribute("action", "cw20_deposit")
+            .add_attribute("owner", owner)
+            .add_attribute("token", info.sender)
+            .add_attribute("amount", cw20_msg.amount)),
+        ReceiveMsg::AddLiquidity {
+            pool_id,
+            chain_id,
+            token_pair,
+            amounts,
+            min_shares,
+            lock_duration,
+        } => {
+            let info = MessageInfo {
+                sender: owner,
+                funds: vec![],
+            };
+            execute_add_liquidity(
+                deps,
+                env,
+                info,
+                pool_id,
+                chain_id,
+                token_pair,
+                amounts,
+                min_shares,
+                lock_duration,
+            )
+        }
+    }
+}
+
+pub fn execute_withdraw_cw20(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+    amount: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let token = deps.api.addr_validate(&token)?;
+    let deposited = CW20_DEPOSITS
+        .may_load(deps.storage, (&info.sender, &token))?
+        .unwrap_or_default();
+    let amount = amount.unwrap_or(deposited);
+    if amount.is_zero() || amount > deposited {
+        return Err(ContractError::InsufficientFunds {});
+    }
+    save_cw20_deposit(deps.storage, &info.sender, &token, deposited - amount)?;
+
+    let transfer = WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+            recipient: info.sender.to_string(),
+            amount,
+        })?,
+        funds: vec![],
+    };
+
+    Ok(Response::new()
+        .add_message(transfer)
+        .add_attribute("action", "withdraw_cw20")
+        .add_attribute("token", token)
         .add_attribute("amount", amount))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_remove_liquidity(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     token_id: String,
     amount: Option<Uint128>,
+    min_amounts_out: Vec<Coin>,
+    deadline: Option<u64>,
 ) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::RemoveLiquidity)?;
     let position = POSITIONS.load(deps.storage, &token_id)?;
 
-    // Verify ownership
-    if position.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
+    // Verify the sender controls the UL-NFT
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+    assert_pool_available(
+        deps.storage,
+        &position.position.pool_id,
+        &position.position.chain_id,
+    )?;
+    check_deadline(&env, deadline)?;
+
+    // Leaving a lock early pays out through this contract, which keeps the
+    // penalty for the boost reserve
+    let early_exit = match active_lock(deps.storage, &env, &token_id)? {
+        None => None,
+        Some(lock) => match CONFIG.load(deps.storage)?.early_exit_penalty {
+            Some(penalty) => Some(EarlyExit {
+                recipient: position.owner.clone(),
+                penalty,
+            }),
+            None => {
+                return Err(ContractError::PositionTimeLocked {
+                    unlocks_at: lock.unlocks_at,
+                })
+            }
+        },
+    };
+    let recipient = match &early_exit {
+        Some(_) => env.contract.address.clone(),
+        None => position.owner.clone(),
+    };
 
     let remove_amount = amount.unwrap_or(position.position.amount);
     if remove_amount > position.position.amount {
         return Err(ContractError::InsufficientLiquidity {});
     }
+    let token_pair = &position.position.token_pair;
+    if min_amounts_out
+        .iter()
+        .any(|coin| coin.denom != token_pair.0 && coin.denom != token_pair.1)
+    {
+        return Err(ContractError::InvalidTokenPair {});
+    }
 
     // Create Euclid message for removing liquidity
-    let euclid_msg = EuclidMsg {
-        action: EuclidAction::RemoveLiquidity,
-        data: to_json_binary(&json!({
-            "pool_id": position.position.pool_id,
-            "amount": remove_amount,
-            "token_id": token_id,
-        }))?,
+    let request = RemoveLiquidityRequest {
+        token_id: token_id.clone(),
+        pool_id: position.position.pool_id.clone(),
+        amount: remove_amount,
+        recipient: recipient.to_string(),
+        deadline,
     };
 
-    // Create submessage for Euclid Router
-    let msg = WasmMsg::Execute {
-        contract_addr: CONFIG.load(deps.storage)?.euclid_router.to_string(),
-        msg: to_json_binary(&euclid_msg)?,
+    // Add to pending operations
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: EuclidAction::RemoveLiquidity,
+        pool_id: position.position.pool_id,
+        chain_id: position.position.chain_id,
+        token_id: Some(token_id.clone()),
         funds: vec![],
+        submitted_at: env.block.time.seconds(),
     };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    LOCKED_POSITIONS.save(deps.storage, &token_id, &operation.id)?;
+    let limits = OperationLimits {
+        min_amounts_out,
+        deadline,
+        ..OperationLimits::default()
+    };
+    OPERATION_LIMITS.save(deps.storage, operation.id, &limits)?;
+    if let Some(early_exit) = &early_exit {
+        PENDING_EXITS.save(deps.storage, operation.id, early_exit)?;
+    }
+
+    // Create submessage for Euclid Router
+    let msg = euclid_router(deps.as_ref())?.execute_tracked(&request, operation.id, vec![])?;
 
     let submsg = SubMsg {
-        id: REPLY_REMOVE_LIQUIDITY,
+        id: operation.id,
         msg: msg.into(),
         gas_limit: None,
-        reply_on: ReplyOn::Success,
-        //payload: Binary::default(),
+        reply_on: ReplyOn::Always,
     };
 
     Ok(Response::new()
         .add_submessage(submsg)
         .add_attribute("action", "remove_liquidity")
+        .add_attribute("operation_id", operation.id.to_string())
         .add_attribute("token_id", token_id)
         .add_attribute("amount", remove_amount))
 }
 
-// Query entry point
-#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
-        QueryMsg::GetPosition { token_id } => to_json_binary(&query_position(deps, token_id)?),
-        QueryMsg::GetPoolPositions { pool_id } => {
-            to_json_binary(&query_pool_positions(deps, pool_id)?)
-        }
-        QueryMsg::GetPendingOperations { token_id } => {
-            to_json_binary(&query_pending_operations(deps, token_id)?)
-        }
-        QueryMsg::GetPoolLiquidity { pool_id } => {
-            to_json_binary(&query_pool_liquidity(deps, pool_id)?)
-        }
-        QueryMsg::EstimateRewards { token_id } => {
-            to_json_binary(&query_estimate_rewards(deps, token_id)?)
-        }
-    }
+pub fn execute_swap_exact_in(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    route: Vec<String>,
+    min_amount_out: Uint128,
+    deadline: Option<u64>,
+) -> Result<Response, ContractError> {
+    validate_swap(&env, &route, deadline)?;
+    let amount_in = must_pay(&info, &route[0])?;
+
+    let request = SwapExactInRequest {
+        route: route.clone(),
+        amount_in,
+        min_amount_out,
+        deadline,
+    };
+    let order = SwapOrder {
+        route,
+        max_amount_in: amount_in,
+        min_amount_out,
+    };
+    submit_swap(deps, env, info, &request, order)
 }
 
-// Reply handler for submessages
-#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
-pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
-        REPLY_ADD_LIQUIDITY => handle_add_liquidity_reply(deps, env, msg),
-        REPLY_REMOVE_LIQUIDITY => handle_remove_liquidity_reply(deps, env, msg),
-        REPLY_TRANSFER_POSITION => handle_transfer_position_reply(deps, env, msg),
-        id => Err(ContractError::Std(StdError::generic_err(format!(
-            "Unknown reply id: {}",
-            id
-        )))),
+#[allow(clippy::too_many_arguments)]
+pub fn execute_swap_exact_out(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    route: Vec<String>,
+    amount_out: Uint128,
+    max_amount_in: Uint128,
+    deadline: Option<u64>,
+) -> Result<Response, ContractError> {
+    validate_swap(&env, &route, deadline)?;
+    if must_pay(&info, &route[0])? < max_amount_in {
+        return Err(ContractError::InsufficientFunds {});
     }
+
+    let request = SwapExactOutRequest {
+        route: route.clone(),
+        amount_out,
+        max_amount_in,
+        deadline,
+    };
+    let order = SwapOrder {
+        route,
+        max_amount_in,
+        min_amount_out: amount_out,
+    };
+    submit_swap(deps, env, info, &request, order)
 }
 
-// Helper function to safely extract data from Reply
-fn extract_reply_data(reply: Reply) -> StdResult<Binary> {
-    match reply.result {
-        cosmwasm_std::SubMsgResult::Ok(response) => {
-            // First try to get data from events
-            if !response.events.is_empty() {
-                if let Some(first_event) = response.events.get(0) {
-                    for attr in &first_event.attributes {
-                        // Process each attribute in the event
-                        // If you are looking for a specific key, you can compare it here
-                        if attr.key == "desired_key" {
-                            // If the desired key is found, return the value as Binary
-                            return Ok(Binary::from(attr.value.as_bytes()));
-                        }
-                    }                
-                }
-            }
+// A route needs at least two hops, each to a different denom
+fn validate_swap(env: &Env, route: &[String], deadline: Option<u64>) -> Result<(), ContractError> {
+    if route.len() < 2 || route.windows(2).any(|hop| hop[0] == hop[1]) {
+        return Err(ContractError::InvalidSwapRoute {});
+    }
+    check_deadline(env, deadline)
+}
 
-            // If no relevant event data is found, try the deprecated data field
-            #[allow(deprecated)]
-            match response.data {
-                Some(data) => Ok(data),
-                None => Err(StdError::generic_err("No data found in response")),
-            }
-        }
-        cosmwasm_std::SubMsgResult::Err(err) => {
-            Err(StdError::generic_err(format!("Reply error: {}", err)))
-        }
+// Deadlines are unix times in seconds, compared with the block time
+fn check_deadline(env: &Env, deadline: Option<u64>) -> Result<(), ContractError> {
+    if matches!(deadline, Some(deadline) if env.block.time.seconds() > deadline) {
+        return Err(ContractError::DeadlineExceeded {});
     }
+    Ok(())
 }
 
-fn handle_add_liquidity_reply(
+// Records the swap as a pending operation and sends it to the router
+fn submit_swap<T: EuclidRequest>(
     deps: DepsMut,
     env: Env,
-    msg: Reply,
+    info: MessageInfo,
+    request: &T,
+    order: SwapOrder,
 ) -> Result<Response, ContractError> {
-    let data = extract_reply_data(msg)?;
-    let result: EuclidResponse = from_json(&data)?;
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: T::ACTION,
+        // Swaps record their route in place of a pool
+        pool_id: order.route.join(">"),
+        chain_id: env.block.chain_id.clone(),
+        token_id: None,
+        funds: info.funds.clone(),
+        submitted_at: env.block.time.seconds(),
+    };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    PENDING_SWAPS.save(deps.storage, operation.id, &order)?;
+    let submsg = router_submsg(deps.as_ref(), operation.id, request, info.funds)?;
 
-    if !result.success {
-        return Err(ContractError::CrossChainOperationFailed {});
-    }
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("action", "swap")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("route", operation.pool_id))
+}
 
-    // Parse position data from successful response
-    let position_data: LiquidityPosition = from_json(&result.data)?;
+#[allow(clippy::too_many_arguments)]
+pub fn execute_zap_in(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: String,
+    chain_id: String,
+    input_denom: String,
+    min_shares: Uint128,
+    max_slippage: Option<Decimal>,
+    deadline: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::AddLiquidity)?;
+    check_deadline(&env, deadline)?;
+    let amount = must_pay(&info, &input_denom)?;
+    let pool = assert_pool_available(deps.storage, &pool_id, &chain_id)?;
+    let other_denom = match &pool.token_pair {
+        (first, second) if *first == input_denom => second.clone(),
+        (first, second) if *second == input_denom => first.clone(),
+        _ => return Err(ContractError::InvalidTokenPair {}),
+    };
 
-    // Get sender from PENDING_OPERATIONS
-    let pending_users = PENDING_OPERATIONS
-        .load(deps.storage, &position_data.pool_id)?
-        .pop()
-        .ok_or_else(|| ContractError::Unauthorized {})?;
+    // Half of the input is swapped, the deposit step refunds any excess
+    let swap_amount = Uint128::new(amount.u128() / 2);
+    if swap_amount.is_zero() {
+        return Err(ContractError::InvalidPositionAmount {});
+    }
+    let route = vec![input_denom.clone(), other_denom];
+    // Without an oracle the caller's `min_shares` is the only floor
+    let min_amount_out = swap_floor(
+        deps.as_ref(),
+        &route[0],
+        &route[1],
+        swap_amount,
+        max_slippage,
+    )?
+    .unwrap_or_default();
+    let request = SwapExactInRequest {
+        route,
+        amount_in: swap_amount,
+        min_amount_out,
+        deadline,
+    };
 
-    let sender = deps.api.addr_validate(&pending_users)?;
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: EuclidAction::SwapExactIn,
+        pool_id: pool_id.clone(),
+        chain_id,
+        token_id: None,
+        funds: info.funds,
+        submitted_at: env.block.time.seconds(),
+    };
+    let zap = Zap {
+        kind: ZapKind::In {
+            token_pair: pool.token_pair,
+            min_shares,
+        },
+        atomic: true,
+    };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    PENDING_ZAPS.save(deps.storage, operation.id, &zap)?;
+
+    let funds = vec![Coin::new(swap_amount.u128(), input_denom)];
+    let submsg = router_submsg(deps.as_ref(), operation.id, &request, funds)?;
+
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("action", "zap_in")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("pool_id", pool_id)
+        .add_attribute("amount", amount))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn execute_zap_out(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    amount: Option<Uint128>,
+    output_denom: String,
+    min_amount_out: Uint128,
+    deadline: Option<u64>,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::RemoveLiquidity)?;
+    check_deadline(&env, deadline)?;
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+    assert_not_time_locked(deps.storage, &env, &token_id)?;
+    assert_pool_available(
+        deps.storage,
+        &position.position.pool_id,
+        &position.position.chain_id,
+    )?;
+
+    let token_pair = &position.position.token_pair;
+    if token_pair.0 != output_denom && token_pair.1 != output_denom {
+        return Err(ContractError::InvalidTokenPair {});
+    }
+    let remove_amount = amount.unwrap_or(position.position.amount);
+    if remove_amount > position.position.amount {
+        return Err(ContractError::InsufficientLiquidity {});
+    }
+
+    // The withdrawn tokens come here to be swapped
+    let request = RemoveLiquidityRequest {
+        token_id: token_id.clone(),
+        pool_id: position.position.pool_id.clone(),
+        amount: remove_amount,
+        recipient: env.contract.address.to_string(),
+        deadline,
+    };
+
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: EuclidAction::RemoveLiquidity,
+        pool_id: position.position.pool_id,
+        chain_id: position.position.chain_id,
+        token_id: Some(token_id.clone()),
+        funds: vec![],
+        submitted_at: env.block.time.seconds(),
+    };
+    let zap = Zap {
+        kind: ZapKind::Out {
+            output_denom,
+            min_amount_out,
+            collected: Uint128::zero(),
+            deadline,
+        },
+        atomic: true,
+    };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    PENDING_ZAPS.save(deps.storage, operation.id, &zap)?;
+    LOCKED_POSITIONS.save(deps.storage, &token_id, &operation.id)?;
+
+    let submsg = router_submsg(deps.as_ref(), operation.id, &request, vec![])?;
+
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("action", "zap_out")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id)
+        .add_attribute("amount", remove_amount))
+}
+
+// Query entry point
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::GetPosition { token_id } => to_json_binary(&query_position(deps, token_id)?),
+        QueryMsg::GetPositionsByOwner {
+            owner,
+            start_after,
+            limit,
+        } => {
+            let owner = deps.api.addr_validate(&owner)?;
+            let positions = POSITIONS.idx.owner.prefix(owner);
+            to_json_binary(&query_positions(deps, positions, start_after, limit)?)
+        }
+        QueryMsg::GetPoolPositions {
+            pool_id,
+            start_after,
+            limit,
+        } => {
+            let positions = POSITIONS.idx.pool_id.prefix(pool_id);
+            to_json_binary(&query_positions(deps, positions, start_after, limit)?)
+        }
+        QueryMsg::GetChainPositions {
+            chain_id,
+            start_after,
+            limit,
+        } => {
+            let positions = POSITIONS.idx.chain_id.prefix(chain_id);
+            to_json_binary(&query_positions(deps, positions, start_after, limit)?)
+        }
+        QueryMsg::GetPendingOperation { id } => {
+            to_json_binary(&PENDING_OPERATIONS.may_load(deps.storage, id)?)
+        }
+        QueryMsg::GetPendingOperations {
+            user,
+            start_after,
+            limit,
+        } => to_json_binary(&query_pending_operations(deps, user, start_after, limit)?),
+        QueryMsg::GetFailedOperations {
+            user,
+            start_after,
+            limit,
+        } => to_json_binary(&query_failed_operations(deps, user, start_after, limit)?),
+        QueryMsg::GetCw20Deposits { owner } => to_json_binary(&query_cw20_deposits(deps, owner)?),
+        QueryMsg::GetPoolLiquidity { pool_id } => {
+            to_json_binary(&query_pool_liquidity(deps, pool_id)?)
+        }
+        QueryMsg::GetPoolTvl { pool_id } => to_json_binary(
+            &POOL_TVL
+                .may_load(deps.storage, &pool_id)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetChainTvl { chain_id } => to_json_binary(
+            &CHAIN_TVL
+                .may_load(deps.storage, &chain_id)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetTvl {} => to_json_binary(&query_tvl(deps)?),
+        QueryMsg::GetPauseState {} => {
+            to_json_binary(&PAUSE_STATE.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::GetPositionValue {
+            token_id,
+            quote_denom,
+        } => {
+            let position = POSITIONS.load(deps.storage, &token_id)?;
+            let oracle = price_oracle(deps)?;
+            to_json_binary(&value_position(deps, &*oracle, position, &quote_denom)?)
+        }
+        QueryMsg::GetPortfolioValue { owner } => {
+            to_json_binary(&query_portfolio_value(deps, owner)?)
+        }
+        QueryMsg::EstimateRewards { token_id } => {
+            to_json_binary(&query_estimate_rewards(deps, env, token_id)?)
+        }
+        QueryMsg::GetRewards { token_id } => to_json_binary(&query_rewards(deps, env, token_id)?),
+        QueryMsg::GetChain { chain_id } => {
+            to_json_binary(&CHAINS.may_load(deps.storage, &chain_id)?)
+        }
+        QueryMsg::GetChains { start_after, limit } => {
+            to_json_binary(&query_chains(deps, start_after, limit)?)
+        }
+        QueryMsg::GetRegisteredPool { pool_id } => {
+            to_json_binary(&POOLS.may_load(deps.storage, &pool_id)?)
+        }
+        QueryMsg::GetRegisteredPools { start_after, limit } => {
+            to_json_binary(&query_registered_pools(deps, start_after, limit)?)
+        }
+        QueryMsg::GetTransferStatus { token_id } => {
+            to_json_binary(&TRANSFERS.may_load(deps.storage, &token_id)?)
+        }
+        QueryMsg::GetAutoCompound { token_id } => {
+            to_json_binary(&query_auto_compound(deps, token_id)?)
+        }
+        QueryMsg::GetLock { token_id } => to_json_binary(&query_lock(deps, env, token_id)?),
+        QueryMsg::GetBoostReserve {} => to_json_binary(&query_boost_reserve(deps)?),
+        QueryMsg::GetRewardHistory {
+            token_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_reward_history(deps, token_id, start_after, limit)?),
+        QueryMsg::ListPools { start_after, limit } => {
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+            to_json_binary(&euclid_factory(deps)?.pools(&deps.querier, start_after, Some(limit))?)
+        }
+        QueryMsg::PoolInfo { pool_id } => {
+            to_json_binary(&euclid_factory(deps)?.pool_info(&deps.querier, pool_id)?)
+        }
+        QueryMsg::SimulateAddLiquidity { pool_id, amounts } => to_json_binary(
+            &euclid_router(deps)?.simulate_add_liquidity(&deps.querier, pool_id, amounts)?,
+        ),
+        QueryMsg::SimulateRemoveLiquidity { pool_id, amount } => to_json_binary(
+            &euclid_router(deps)?.simulate_remove_liquidity(&deps.querier, pool_id, amount)?,
+        ),
+    }
+}
+
+// Reply handler for submessages
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    // The reply id is the operation id
+    let operation = PENDING_OPERATIONS
+        .may_load(deps.storage, msg.id)?
+        .ok_or(ContractError::OperationNotFound { id: msg.id })?;
+
+    // A reverted submessage is recorded instead of reverting the whole transaction
+    let response = match msg.result {
+        SubMsgResult::Err(err) => {
+            // Cw20 sides were transferred to the router ahead of the call and
+            // stay there when it reverts, so they are not credited back
+            PENDING_CW20.remove(deps.storage, operation.id);
+            EuclidResponse::failure(err)
+        }
+        SubMsgResult::Ok(response) => {
+            let router = CONFIG.load(deps.storage)?.euclid_router;
+            parse_router_response(&router, &operation.action, &response)?
+        }
+    };
+
+    // Accepted calls stay pending until the router's callback
+    if response.is_accepted() {
+        if let Some(mut zap) = PENDING_ZAPS.may_load(deps.storage, operation.id)? {
+            zap.atomic = false;
+            PENDING_ZAPS.save(deps.storage, operation.id, &zap)?;
+        }
+        if let Some(mut compound) = PENDING_COMPOUNDS.may_load(deps.storage, operation.id)? {
+            compound.atomic = false;
+            PENDING_COMPOUNDS.save(deps.storage, operation.id, &compound)?;
+        }
+        if operation.action == EuclidAction::TransferLiquidity {
+            update_transfer(
+                deps.storage,
+                &env,
+                &operation,
+                TransferStatus::InFlight,
+                None,
+            )?;
+        }
+        return Ok(Response::new()
+            .add_attribute("action", "operation_accepted")
+            .add_attribute("operation_id", operation.id.to_string()));
+    }
+
+    resolve_operation(deps, env, operation, response, false)
+}
+
+// Completion of an operation the router accepted earlier
+pub fn execute_euclid_callback(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tx_id: u64,
+    response: EuclidResponse,
+) -> Result<Response, ContractError> {
+    if info.sender != CONFIG.load(deps.storage)?.euclid_router {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let operation = PENDING_OPERATIONS
+        .may_load(deps.storage, tx_id)?
+        .ok_or(ContractError::OperationNotFound { id: tx_id })?;
+
+    resolve_operation(deps, env, operation, response, true)
+}
+
+// Applies the router's final answer, each operation is resolved exactly once.
+// `settled_later` is set for answers arriving through the router's callback,
+// which can no longer be reverted.
+fn resolve_operation(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    response: EuclidResponse,
+    settled_later: bool,
+) -> Result<Response, ContractError> {
+    PENDING_OPERATIONS.remove(deps.storage, operation.id)?;
+
+    // Whatever the outcome, the position is free for the next operation
+    if let Some(token_id) = &operation.token_id {
+        LOCKED_POSITIONS.remove(deps.storage, token_id);
+    }
+
+    if let Some(zap) = PENDING_ZAPS.may_load(deps.storage, operation.id)? {
+        PENDING_ZAPS.remove(deps.storage, operation.id);
+        return resolve_zap_step(deps, env, operation, response, zap);
+    }
+
+    if let Some(compound) = PENDING_COMPOUNDS.may_load(deps.storage, operation.id)? {
+        PENDING_COMPOUNDS.remove(deps.storage, operation.id);
+        return resolve_compound_step(deps, env, operation, response, compound);
+    }
+
+    if !response.success {
+        let error = response
+            .error
+            .unwrap_or_else(|| "Unknown router error".to_string());
+        return handle_failed_operation(deps, env, operation, error);
+    }
+
+    match operation.action {
+        EuclidAction::AddLiquidity => {
+            handle_add_liquidity_reply(deps, env, operation, response, settled_later)
+        }
+        EuclidAction::RemoveLiquidity => {
+            handle_remove_liquidity_reply(deps, env, operation, response, settled_later)
+        }
+        EuclidAction::TransferLiquidity => {
+            handle_transfer_position_reply(deps, env, operation, response, settled_later)
+        }
+        EuclidAction::SwapExactIn | EuclidAction::SwapExactOut => {
+            handle_swap_reply(deps, operation, response)
+        }
+        EuclidAction::ClaimRewards => handle_claim_rewards_reply(deps, env, operation, response),
+    }
+}
+
+// Adds a confirmed deposit to an existing position
+fn handle_increase_liquidity_reply(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    position: &mut PositionInfo,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let previous = position.clone();
+    position.position.amount = position
+        .position
+        .amount
+        .checked_add(amount)
+        .map_err(StdError::from)?;
+    position.position.last_updated = env.block.time.seconds();
+    save_position(deps.storage, Some(&previous), position)?;
+
+    let nft_update = PositionExecuteMsg::UpdateLiquidityPosition {
+        token_id: position.token_id.clone(),
+        position_updates: vec![position.position.to_nft_position()],
+    };
+
+    Ok(Response::new()
+        .add_message(nft_msg(deps.as_ref(), nft_update)?)
+        .add_messages(expired_lock_msg(deps.as_ref(), &env, &previous)?)
+        .add_attribute("action", "increase_liquidity_complete")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", position.token_id.clone())
+        .add_attribute("amount", amount))
+}
+
+// Records a failed operation and refunds the funds escrowed for it. Native
+// funds of a reverted submessage never left this contract, and the router
+// returns what it escrowed along with a failed response. Cw20 sides are only
+// credited back in the latter case, `reply` drops them on a revert.
+fn handle_failed_operation(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    error: String,
+) -> Result<Response, ContractError> {
+    let refund = match operation.action {
+        EuclidAction::AddLiquidity | EuclidAction::SwapExactIn | EuclidAction::SwapExactOut => {
+            operation.funds.clone()
+        }
+        _ => vec![],
+    };
+    PENDING_SWAPS.remove(deps.storage, operation.id);
+    OPERATION_LIMITS.remove(deps.storage, operation.id);
+    PENDING_EXITS.remove(deps.storage, operation.id);
+    if let (EuclidAction::AddLiquidity, Some(token_id)) = (&operation.action, &operation.token_id) {
+        PENDING_LOCKS.remove(deps.storage, token_id);
+    }
+    if operation.action == EuclidAction::TransferLiquidity {
+        update_transfer(
+            deps.storage,
+            &env,
+            &operation,
+            TransferStatus::Failed,
+            Some(error.clone()),
+        )?;
+    }
+
+    // A failed response hands back the cw20 tokens too, they return to the
+    // deposits
+    if let Some(assets) = PENDING_CW20.may_load(deps.storage, operation.id)? {
+        PENDING_CW20.remove(deps.storage, operation.id);
+        for asset in assets {
+            let token = Addr::unchecked(asset.address);
+            CW20_DEPOSITS.update(
+                deps.storage,
+                (&operation.sender, &token),
+                |deposited| -> StdResult<_> {
+                    Ok(deposited.unwrap_or_default().checked_add(asset.amount)?)
+                },
+            )?;
+        }
+    }
+
+    Ok(record_failed_operation(
+        deps.storage,
+        &env,
+        operation,
+        error,
+        refund,
+    )?)
+}
+
+// Stores a failed operation and sends `refund` back to its sender
+fn record_failed_operation(
+    storage: &mut dyn Storage,
+    env: &Env,
+    operation: PendingOperation,
+    error: String,
+    refund: Vec<Coin>,
+) -> StdResult<Response> {
+    let mut response = Response::new()
+        .add_attribute("action", "operation_failed")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("error", error.clone());
+    if !refund.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: operation.sender.to_string(),
+            amount: refund.clone(),
+        });
+    }
+
+    let failed = FailedOperation {
+        operation,
+        error,
+        refunded: refund,
+        failed_at: env.block.time.seconds(),
+    };
+    FAILED_OPERATIONS.save(storage, failed.operation.id, &failed)?;
+
+    Ok(response)
+}
+
+// Moves a zap on to its next step, or pays it out after the last one
+fn resolve_zap_step(
+    mut deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    response: EuclidResponse,
+    zap: Zap,
+) -> Result<Response, ContractError> {
+    if !response.success {
+        let error = response
+            .error
+            .unwrap_or_else(|| "Unknown router error".to_string());
+        if zap.atomic {
+            return Err(ContractError::ZapFailed { error });
+        }
+        return handle_failed_operation(deps, env, operation, error);
+    }
+
+    match (zap.kind, &operation.action) {
+        (ZapKind::In { .. }, EuclidAction::AddLiquidity) => {
+            handle_add_liquidity_reply(deps, env, operation, response, !zap.atomic)
+        }
+        (
+            ZapKind::In {
+                token_pair,
+                min_shares,
+            },
+            EuclidAction::SwapExactIn,
+        ) => {
+            let swap: SwapResponse = response.parse()?;
+            let input = operation.funds[0].clone();
+            let input_left = input
+                .amount
+                .checked_sub(swap.amount_in)
+                .map_err(StdError::from)?;
+
+            // Both tokens are deposited at the pool's ratio, the rest is refunded
+            let available = if token_pair.0 == input.denom {
+                (input_left, swap.amount_out)
+            } else {
+                (swap.amount_out, input_left)
+            };
+            let amounts =
+                balanced_deposit(deps.as_ref(), &operation.pool_id, &token_pair, available)?;
+            let token_id = next_token_id(deps.storage)?;
+            let funds: Vec<Coin> = [
+                Coin::new(amounts.0.u128(), &token_pair.0),
+                Coin::new(amounts.1.u128(), &token_pair.1),
+            ]
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect();
+            let excess: Vec<Coin> = [
+                Coin::new((available.0 - amounts.0).u128(), &token_pair.0),
+                Coin::new((available.1 - amounts.1).u128(), &token_pair.1),
+            ]
+            .into_iter()
+            .filter(|coin| !coin.amount.is_zero())
+            .collect();
+
+            let request = AddLiquidityRequest {
+                pool_id: operation.pool_id.clone(),
+                chain_id: operation.chain_id.clone(),
+                token_pair: token_pair.clone(),
+                amounts,
+                min_shares,
+                cw20_assets: vec![],
+                token_id: token_id.clone(),
+            };
+            let deposit = PendingOperation {
+                id: next_operation_id(deps.storage)?,
+                action: EuclidAction::AddLiquidity,
+                token_id: Some(token_id),
+                funds: funds.clone(),
+                submitted_at: env.block.time.seconds(),
+                ..operation
+            };
+            let zap = Zap {
+                kind: ZapKind::In {
+                    token_pair,
+                    min_shares,
+                },
+                atomic: zap.atomic,
+            };
+            PENDING_OPERATIONS.save(deps.storage, deposit.id, &deposit)?;
+            PENDING_ZAPS.save(deps.storage, deposit.id, &zap)?;
+            let limits = OperationLimits {
+                min_shares,
+                ..OperationLimits::default()
+            };
+            OPERATION_LIMITS.save(deps.storage, deposit.id, &limits)?;
+
+            let mut res = Response::new()
+                .add_submessage(router_submsg(deps.as_ref(), deposit.id, &request, funds)?)
+                .add_attribute("action", "zap_in_swapped")
+                .add_attribute("operation_id", deposit.id.to_string())
+                .add_attribute("amounts", format!("{},{}", amounts.0, amounts.1));
+            if !excess.is_empty() {
+                res = res.add_message(BankMsg::Send {
+                    to_address: deposit.sender.to_string(),
+                    amount: excess,
+                });
+            }
+            Ok(res)
+        }
+        (
+            ZapKind::Out {
+                output_denom,
+                min_amount_out,
+                deadline,
+                ..
+            },
+            EuclidAction::RemoveLiquidity,
+        ) => {
+            let removed: RemoveLiquidityResponse = response.parse()?;
+            let res = handle_remove_liquidity_reply(
+                deps.branch(),
+                env.clone(),
+                operation.clone(),
+                response,
+                !zap.atomic,
+            )?;
+
+            let collected = removed
+                .assets
+                .iter()
+                .filter(|coin| coin.denom == output_denom)
+                .map(|coin| coin.amount)
+                .sum::<Uint128>();
+            let others: Vec<Coin> = removed
+                .assets
+                .into_iter()
+                .filter(|coin| coin.denom != output_denom && !coin.amount.is_zero())
+                .collect();
+
+            // Nothing to swap, the withdrawal already is in the output token
+            if others.is_empty() {
+                let payout = vec![Coin::new(collected.u128(), output_denom)];
+                if collected < min_amount_out {
+                    return fail_zap_out(deps, &env, operation, zap.atomic, payout);
+                }
+                return Ok(res
+                    .add_message(BankMsg::Send {
+                        to_address: operation.sender.to_string(),
+                        amount: payout,
+                    })
+                    .add_attribute("amount_out", collected));
+            }
+
+            let request = SwapExactInRequest {
+                route: vec![others[0].denom.clone(), output_denom.clone()],
+                amount_in: others[0].amount,
+                min_amount_out: min_amount_out.saturating_sub(collected),
+                deadline,
+            };
+            // The swap holds everything withdrawn so a failure refunds all of it
+            let mut held = others.clone();
+            if !collected.is_zero() {
+                held.push(Coin::new(collected.u128(), &output_denom));
+            }
+            let swap = PendingOperation {
+                id: next_operation_id(deps.storage)?,
+                action: EuclidAction::SwapExactIn,
+                token_id: None,
+                funds: held,
+                submitted_at: env.block.time.seconds(),
+                ..operation
+            };
+            let zap = Zap {
+                kind: ZapKind::Out {
+                    output_denom,
+                    min_amount_out,
+                    collected,
+                    deadline,
+                },
+                atomic: zap.atomic,
+            };
+            PENDING_OPERATIONS.save(deps.storage, swap.id, &swap)?;
+            PENDING_ZAPS.save(deps.storage, swap.id, &zap)?;
+
+            Ok(res
+                .add_submessage(router_submsg(deps.as_ref(), swap.id, &request, others)?)
+                .add_attribute("swap_operation_id", swap.id.to_string()))
+        }
+        (
+            ZapKind::Out {
+                output_denom,
+                min_amount_out,
+                collected,
+                ..
+            },
+            EuclidAction::SwapExactIn,
+        ) => {
+            let swap: SwapResponse = response.parse()?;
+            let amount_out = collected
+                .checked_add(swap.amount_out)
+                .map_err(StdError::from)?;
+            let payout = vec![Coin::new(amount_out.u128(), output_denom)];
+            if amount_out < min_amount_out {
+                return fail_zap_out(deps, &env, operation, zap.atomic, payout);
+            }
+            Ok(Response::new()
+                .add_message(BankMsg::Send {
+                    to_address: operation.sender.to_string(),
+                    amount: payout,
+                })
+                .add_attribute("action", "zap_out_complete")
+                .add_attribute("operation_id", operation.id.to_string())
+                .add_attribute("amount_out", amount_out))
+        }
+        (_, action) => Err(ContractError::Std(StdError::generic_err(format!(
+            "Unexpected zap step: {:?}",
+            action
+        )))),
+    }
+}
+
+// A zap out that came short of its minimum. Within the caller's transaction
+// it reverts; settled later, the withdrawn tokens are refunded as they are.
+fn fail_zap_out(
+    deps: DepsMut,
+    env: &Env,
+    operation: PendingOperation,
+    atomic: bool,
+    withdrawn: Vec<Coin>,
+) -> Result<Response, ContractError> {
+    if atomic {
+        return Err(ContractError::SlippageExceeded {});
+    }
+    Ok(record_failed_operation(
+        deps.storage,
+        env,
+        operation,
+        ContractError::SlippageExceeded {}.to_string(),
+        withdrawn,
+    )?)
+}
+
+// Least output a swap of `amount_in` from `offer` to `ask` must get: its
+// value at the price oracle less `max_slippage`, the configured one when
+// None. The router's own quote moves with the pool in the same transaction,
+// so without an oracle there is no floor to give and None is returned
+fn swap_floor(
+    deps: Deps,
+    offer: &str,
+    ask: &str,
+    amount_in: Uint128,
+    max_slippage: Option<Decimal>,
+) -> Result<Option<Uint128>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let max_slippage = max_slippage.unwrap_or(config.max_slippage);
+    if max_slippage > Decimal::one() {
+        return Err(ContractError::InvalidSlippage {});
+    }
+    let oracle = match config.price_oracle {
+        Some(oracle) => OracleContract(oracle),
+        None => return Ok(None),
+    };
 
-    // Generate token ID (using timestamp and pool ID)
-    let token_id = format!("ulp-{}-{}", env.block.time.seconds(), position_data.pool_id);
+    // Both sides are priced in the quote denom, which the oracle need not list
+    let quote_price = |denom: &str| -> StdResult<Decimal> {
+        if denom == config.quote_denom {
+            Ok(Decimal::one())
+        } else {
+            oracle.price(deps, denom, &config.quote_denom)
+        }
+    };
+    let ask_price = quote_price(ask)?;
+    if ask_price.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "No price for {ask}"
+        ))));
+    }
+    let amount_out = amount_in.multiply_ratio(quote_price(offer)?.atomics(), ask_price.atomics());
+    Ok(Some(amount_out * (Decimal::one() - max_slippage)))
+}
+
+// Removes the limits of an operation being resolved, failing once its
+// deadline has passed
+fn take_limits(
+    storage: &mut dyn Storage,
+    env: &Env,
+    operation_id: u64,
+) -> Result<OperationLimits, ContractError> {
+    let limits = OPERATION_LIMITS
+        .may_load(storage, operation_id)?
+        .unwrap_or_default();
+    OPERATION_LIMITS.remove(storage, operation_id);
+    check_deadline(env, limits.deadline)?;
+    Ok(limits)
+}
+
+// Refuses to start an operation on a position that has one in flight
+fn assert_not_locked(storage: &dyn Storage, token_id: &str) -> Result<(), ContractError> {
+    if LOCKED_POSITIONS.has(storage, token_id) {
+        return Err(ContractError::PositionLocked {});
+    }
+    Ok(())
+}
+
+fn assert_not_paused(storage: &dyn Storage, action: PausableAction) -> Result<(), ContractError> {
+    let pause = PAUSE_STATE.may_load(storage)?.unwrap_or_default();
+    if pause.paused.contains(&action) {
+        return Err(ContractError::Paused { action });
+    }
+    Ok(())
+}
+
+// The time lock of a position, while it has not run out
+fn active_lock(storage: &dyn Storage, env: &Env, token_id: &str) -> StdResult<Option<TimeLock>> {
+    Ok(TIME_LOCKS
+        .may_load(storage, token_id)?
+        .filter(|lock| lock.is_active(env.block.time.seconds())))
+}
+
+fn assert_not_time_locked(
+    storage: &dyn Storage,
+    env: &Env,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    if let Some(lock) = active_lock(storage, env, token_id)? {
+        return Err(ContractError::PositionTimeLocked {
+            unlocks_at: lock.unlocks_at,
+        });
+    }
+    Ok(())
+}
+
+// The longest tier `duration` qualifies for
+fn lock_tier(config: &Config, duration: u64) -> Result<LockTier, ContractError> {
+    config
+        .lock_tiers
+        .iter()
+        .filter(|tier| tier.duration <= duration)
+        .max_by_key(|tier| tier.duration)
+        .map(|tier| LockTier {
+            duration,
+            multiplier: tier.multiplier,
+        })
+        .ok_or(ContractError::InvalidLockDuration {})
+}
+
+// Shows the unlock time on the UL-NFT
+fn lock_attribute_msg(deps: Deps, token_id: &str, lock: &TimeLock) -> StdResult<WasmMsg> {
+    nft_msg(
+        deps,
+        PositionExecuteMsg::SetAttribute {
+            token_id: token_id.to_string(),
+            attribute: Attribute {
+                trait_type: LOCK_ATTRIBUTE.to_string(),
+                value: lock.unlocks_at.to_string(),
+                display_type: Some("date".to_string()),
+            },
+        },
+    )
+}
+
+// Drops the unlock time from the UL-NFT the first time the position changes
+// after its lock ran out
+fn expired_lock_msg(deps: Deps, env: &Env, previous: &PositionInfo) -> StdResult<Option<WasmMsg>> {
+    let lock = match TIME_LOCKS.may_load(deps.storage, &previous.token_id)? {
+        Some(lock) => lock,
+        None => return Ok(None),
+    };
+    if lock.is_active(env.block.time.seconds()) || previous.position.last_updated >= lock.unlocks_at
+    {
+        return Ok(None);
+    }
+    unlock_attribute_msg(deps, &previous.token_id).map(Some)
+}
+
+// Takes the unlock time off the UL-NFT
+fn unlock_attribute_msg(deps: Deps, token_id: &str) -> StdResult<WasmMsg> {
+    nft_msg(
+        deps,
+        PositionExecuteMsg::RemoveAttribute {
+            token_id: token_id.to_string(),
+            trait_type: LOCK_ATTRIBUTE.to_string(),
+        },
+    )
+}
+
+// Liquidity may only change in active, registered pools on enabled chains
+// the pool is available on
+fn assert_pool_available(
+    storage: &dyn Storage,
+    pool_id: &str,
+    chain_id: &str,
+) -> Result<RegisteredPool, ContractError> {
+    let pool = POOLS
+        .may_load(storage, pool_id)?
+        .ok_or(ContractError::PoolNotFound {})?;
+    if pool.status != PoolStatus::Active {
+        return Err(ContractError::PoolDisabled {});
+    }
+    let chain_enabled = CHAINS
+        .may_load(storage, chain_id)?
+        .is_some_and(|chain| chain.enabled);
+    if !chain_enabled || !pool.chains.iter().any(|chain| chain == chain_id) {
+        return Err(ContractError::ChainNotSupported {});
+    }
+    Ok(pool)
+}
+
+// Hands out the next position and UL-NFT id
+fn next_token_id(storage: &mut dyn Storage) -> StdResult<String> {
+    let count = TOKEN_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    TOKEN_COUNT.save(storage, &count)?;
+    Ok(format!("ulp-{}", count))
+}
+
+// Hands out the next operation id
+fn next_operation_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let id = OPERATION_COUNT.may_load(storage)?.unwrap_or_default() + 1;
+    OPERATION_COUNT.save(storage, &id)?;
+    Ok(id)
+}
+
+fn handle_add_liquidity_reply(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    result: EuclidResponse,
+    settled_later: bool,
+) -> Result<Response, ContractError> {
+    PENDING_CW20.remove(deps.storage, operation.id);
+    let limits = take_limits(deps.storage, &env, operation.id)?;
+
+    // Parse position data from successful response
+    let added: AddLiquidityResponse = result.parse()?;
+    // Reverts a deposit minting fewer shares than asked for. One the router
+    // already settled holds its shares there, so it is credited and the miss
+    // reported alongside.
+    let mut missed = None;
+    if added.amount < limits.min_shares {
+        if !settled_later {
+            return Err(ContractError::SlippageExceeded {});
+        }
+        missed = Some(ContractError::SlippageExceeded {}.to_string());
+    }
+    let position_data = LiquidityPosition {
+        pool_id: added.pool_id,
+        chain_id: added.chain_id,
+        token_pair: added.token_pair,
+        amount: added.amount,
+        last_updated: env.block.time.seconds(),
+    };
+
+    if position_data.pool_id != operation.pool_id {
+        return Err(ContractError::PoolNotFound {});
+    }
+
+    // Position and UL-NFT share the token id reserved on submission
+    let token_id = operation
+        .token_id
+        .clone()
+        .ok_or(ContractError::PositionNotFound {})?;
+    if let Some(mut position) = POSITIONS.may_load(deps.storage, &token_id)? {
+        let response =
+            handle_increase_liquidity_reply(deps, env, operation, &mut position, added.amount)?;
+        return Ok(report_limit_miss(response, missed));
+    }
+    let sender = operation.sender;
 
     // Create position info
     let position_info = PositionInfo {
-        owner: sender,
+        owner: sender.clone(),
         token_id: token_id.clone(),
         position: position_data.clone(),
     };
 
     // Save position
-    POSITIONS.save(deps.storage, &token_id, &position_info)?;
+    save_position(deps.storage, None, &position_info)?;
+    REWARD_LEDGERS.save(
+        deps.storage,
+        &token_id,
+        &RewardLedger::new(env.block.time.seconds()),
+    )?;
+
+    // Mint the UL-NFT representing the position to the depositor
+    let mint_msg = nft_msg(
+        deps.as_ref(),
+        PositionExecuteMsg::Mint {
+            token_id: token_id.clone(),
+            owner: sender.to_string(),
+            positions: vec![position_data.to_nft_position()],
+            token_uri: None,
+        },
+    )?;
+    let mut response = Response::new().add_message(mint_msg);
+
+    // A lock asked for with the deposit starts now
+    if let Some(tier) = PENDING_LOCKS.may_load(deps.storage, &token_id)? {
+        PENDING_LOCKS.remove(deps.storage, &token_id);
+        let now = env.block.time.seconds();
+        let lock = TimeLock {
+            locked_at: now,
+            unlocks_at: now.saturating_add(tier.duration),
+            multiplier: tier.multiplier,
+        };
+        TIME_LOCKS.save(deps.storage, &token_id, &lock)?;
+        response = response
+            .add_message(lock_attribute_msg(deps.as_ref(), &token_id, &lock)?)
+            .add_attribute("unlocks_at", lock.unlocks_at.to_string());
+    }
+
+    let response = response
+        .add_attribute("action", "add_liquidity_complete")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id)
+        .add_attribute("owner", sender)
+        .add_attribute("amount", position_data.amount);
+    Ok(report_limit_miss(response, missed))
+}
+
+// Reports the limit a settled operation missed, it could no longer be reverted
+fn report_limit_miss(response: Response, missed: Option<String>) -> Response {
+    match missed {
+        Some(missed) => response.add_attribute("limit_missed", missed),
+        None => response,
+    }
+}
+
+fn handle_remove_liquidity_reply(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    result: EuclidResponse,
+    settled_later: bool,
+) -> Result<Response, ContractError> {
+    let remove_data: RemoveLiquidityResponse = result.parse()?;
+    if operation.token_id.as_ref() != Some(&remove_data.token_id) {
+        return Err(ContractError::PositionNotFound {});
+    }
+
+    // Reverts a withdrawal the sender would not have accepted. One the
+    // router already settled has paid out, so it is applied and the miss
+    // reported alongside.
+    let missed = match check_remove_limits(deps.storage, &env, operation.id, &remove_data) {
+        Ok(()) => None,
+        Err(err) if settled_later => Some(err.to_string()),
+        Err(err) => return Err(err),
+    };
+    let previous = POSITIONS.load(deps.storage, &remove_data.token_id)?;
+
+    // A full withdrawal burns the UL-NFT, a partial one updates its positions
+    let closed = remove_data.amount == previous.position.amount;
+    let nft_update = if closed {
+        remove_position(deps.storage, &previous)?;
+        PositionExecuteMsg::Redeem {
+            token_id: remove_data.token_id.clone(),
+        }
+    } else {
+        let mut position = previous.clone();
+        position.position.amount = position
+            .position
+            .amount
+            .checked_sub(remove_data.amount)
+            .map_err(StdError::from)?;
+        position.position.last_updated = env.block.time.seconds();
+        save_position(deps.storage, Some(&previous), &position)?;
+        PositionExecuteMsg::UpdateLiquidityPosition {
+            token_id: remove_data.token_id.clone(),
+            position_updates: vec![position.position.to_nft_position()],
+        }
+    };
+    let mut response = Response::new().add_message(nft_msg(deps.as_ref(), nft_update)?);
+    if !closed {
+        response = response.add_messages(expired_lock_msg(deps.as_ref(), &env, &previous)?);
+    }
+
+    // An early exit was paid here. Paying it ends the lock on whatever is
+    // left of the position.
+    if let Some(early_exit) = PENDING_EXITS.may_load(deps.storage, operation.id)? {
+        PENDING_EXITS.remove(deps.storage, operation.id);
+        if let Some(mut lock) = TIME_LOCKS
+            .may_load(deps.storage, &remove_data.token_id)?
+            .filter(|_| !closed)
+        {
+            lock.unlocks_at = env.block.time.seconds();
+            TIME_LOCKS.save(deps.storage, &remove_data.token_id, &lock)?;
+            response =
+                response.add_message(unlock_attribute_msg(deps.as_ref(), &remove_data.token_id)?);
+        }
+        response = pay_early_exit(deps.storage, &early_exit, remove_data.assets, response)?;
+    }
+
+    Ok(report_limit_miss(response, missed)
+        .add_attribute("action", "remove_liquidity_complete")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", remove_data.token_id)
+        .add_attribute("amount", remove_data.amount))
+}
+
+// The minimum amounts and deadline of a withdrawal
+fn check_remove_limits(
+    storage: &mut dyn Storage,
+    env: &Env,
+    operation_id: u64,
+    remove_data: &RemoveLiquidityResponse,
+) -> Result<(), ContractError> {
+    let limits = take_limits(storage, env, operation_id)?;
+    for min in &limits.min_amounts_out {
+        let paid = remove_data
+            .assets
+            .iter()
+            .filter(|coin| coin.denom == min.denom)
+            .map(|coin| coin.amount)
+            .sum::<Uint128>();
+        if paid < min.amount {
+            return Err(ContractError::SlippageExceeded {});
+        }
+    }
+    Ok(())
+}
+
+// Pays an early exit's withdrawal on to its owner, all but the penalty that
+// goes to the boost reserve
+fn pay_early_exit(
+    storage: &mut dyn Storage,
+    early_exit: &EarlyExit,
+    assets: Vec<Coin>,
+    response: Response,
+) -> StdResult<Response> {
+    let mut payout = vec![];
+    let mut forfeited = vec![];
+    for asset in assets {
+        let penalty = asset.amount * early_exit.penalty;
+        if !penalty.is_zero() {
+            add_to_boost_reserve(storage, &asset.denom, penalty)?;
+            forfeited.push(Coin::new(penalty.u128(), &asset.denom));
+        }
+        let rest = asset.amount - penalty;
+        if !rest.is_zero() {
+            payout.push(Coin::new(rest.u128(), asset.denom));
+        }
+    }
+    let mut response = response.add_attribute("penalty", coins_attribute(&forfeited));
+    if !payout.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: early_exit.recipient.to_string(),
+            amount: payout,
+        });
+    }
+    Ok(response)
+}
+
+// Pays out the swap output and refunds whatever input the router did not use
+fn handle_swap_reply(
+    deps: DepsMut,
+    operation: PendingOperation,
+    result: EuclidResponse,
+) -> Result<Response, ContractError> {
+    let order = PENDING_SWAPS.load(deps.storage, operation.id)?;
+    PENDING_SWAPS.remove(deps.storage, operation.id);
+    let swap: SwapResponse = result.parse()?;
+
+    if swap.amount_in > order.max_amount_in || swap.amount_out < order.min_amount_out {
+        return Err(ContractError::SlippageExceeded {});
+    }
+
+    let denom_in = &order.route[0];
+    let denom_out = &order.route[order.route.len() - 1];
+    let paid = operation
+        .funds
+        .iter()
+        .find(|coin| &coin.denom == denom_in)
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+
+    let mut payout = vec![Coin::new(swap.amount_out.u128(), denom_out)];
+    let unused = paid.checked_sub(swap.amount_in).map_err(StdError::from)?;
+    if !unused.is_zero() {
+        payout.push(Coin::new(unused.u128(), denom_in));
+    }
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: operation.sender.to_string(),
+            amount: payout,
+        })
+        .add_attribute("action", "swap_completed")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("amount_in", swap.amount_in)
+        .add_attribute("amount_out", swap.amount_out))
+}
+
+// A tracked router call whose reply resolves operation `id`
+fn router_submsg<T: EuclidRequest>(
+    deps: Deps,
+    id: u64,
+    request: &T,
+    funds: Vec<Coin>,
+) -> StdResult<SubMsg> {
+    let msg = euclid_router(deps)?.execute_tracked(request, id, funds)?;
+    Ok(SubMsg {
+        id,
+        msg: msg.into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Always,
+    })
+}
+
+// The configured Euclid router
+fn euclid_router(deps: Deps) -> StdResult<EuclidRouter> {
+    Ok(EuclidRouter(CONFIG.load(deps.storage)?.euclid_router))
+}
+
+fn euclid_factory(deps: Deps) -> StdResult<EuclidFactory> {
+    Ok(EuclidFactory(CONFIG.load(deps.storage)?.euclid_factory))
+}
+
+// Saves a position, moving it from the TVL totals it was counted in to the
+// ones it belongs to now
+fn save_position(
+    storage: &mut dyn Storage,
+    previous: Option<&PositionInfo>,
+    position: &PositionInfo,
+) -> Result<(), ContractError> {
+    POSITIONS.save(storage, &position.token_id, position)?;
+    if let Some(previous) = previous {
+        count_position(storage, previous, false)?;
+    }
+    count_position(storage, position, true)
+}
+
+// Removes a closed position along with its share of the TVL totals
+fn remove_position(
+    storage: &mut dyn Storage,
+    position: &PositionInfo,
+) -> Result<(), ContractError> {
+    POSITIONS.remove(storage, &position.token_id)?;
+    TIME_LOCKS.remove(storage, &position.token_id);
+    count_position(storage, position, false)
+}
+
+// Adds a position to, or takes it out of, the totals of its pool, its chain
+// and the protocol. Positions a running `Reconcile` has not reached are left
+// for it to count.
+fn count_position(
+    storage: &mut dyn Storage,
+    info: &PositionInfo,
+    add: bool,
+) -> Result<(), ContractError> {
+    let reconciling = RECONCILING.may_load(storage)?;
+    let counted = |token_id: &str| match &reconciling {
+        None => true,
+        Some(last) => last.as_deref().is_some_and(|last| token_id <= last),
+    };
+    if !counted(&info.token_id) {
+        return Ok(());
+    }
+
+    // The owner only joins or leaves a total without other positions in it
+    let (mut in_pool, mut on_chain, mut anywhere) = (false, false, false);
+    for item in
+        POSITIONS
+            .idx
+            .owner
+            .prefix(info.owner.clone())
+            .range(storage, None, None, Order::Ascending)
+    {
+        let (token_id, other) = item?;
+        if token_id == info.token_id || !counted(&token_id) {
+            continue;
+        }
+        anywhere = true;
+        in_pool |= other.position.pool_id == info.position.pool_id;
+        on_chain |= other.position.chain_id == info.position.chain_id;
+    }
+
+    let amount = info.position.amount;
+    let pool_id = info.position.pool_id.as_str();
+    let mut tvl = POOL_TVL.may_load(storage, pool_id)?.unwrap_or_default();
+    update_tvl(&mut tvl, amount, add, !in_pool)?;
+    POOL_TVL.save(storage, pool_id, &tvl)?;
+
+    let chain_id = info.position.chain_id.as_str();
+    let mut tvl = CHAIN_TVL.may_load(storage, chain_id)?.unwrap_or_default();
+    update_tvl(&mut tvl, amount, add, !on_chain)?;
+    CHAIN_TVL.save(storage, chain_id, &tvl)?;
+
+    let mut tvl = TOTAL_TVL.may_load(storage)?.unwrap_or_default();
+    update_tvl(&mut tvl, amount, add, !anywhere)?;
+    TOTAL_TVL.save(storage, &tvl)?;
+    Ok(())
+}
+
+fn update_tvl(tvl: &mut Tvl, amount: Uint128, add: bool, depositor: bool) -> StdResult<()> {
+    let depositors = Uint64::from(u64::from(depositor));
+    let (liquidity, positions, depositors) = if add {
+        (
+            tvl.liquidity.checked_add(amount)?,
+            Uint64::new(tvl.positions).checked_add(Uint64::one())?,
+            Uint64::new(tvl.depositors).checked_add(depositors)?,
+        )
+    } else {
+        (
+            tvl.liquidity.checked_sub(amount)?,
+            Uint64::new(tvl.positions).checked_sub(Uint64::one())?,
+            Uint64::new(tvl.depositors).checked_sub(depositors)?,
+        )
+    };
+    tvl.liquidity = liquidity;
+    tvl.positions = positions.u64();
+    tvl.depositors = depositors.u64();
+    Ok(())
+}
+
+// Wraps a position message for the UL-NFT contract
+fn nft_msg(deps: Deps, msg: PositionExecuteMsg) -> StdResult<WasmMsg> {
+    Ok(WasmMsg::Execute {
+        contract_addr: CONFIG.load(deps.storage)?.ul_nft_contract.to_string(),
+        msg: to_json_binary(&NftExecuteMsg::Extension { msg })?,
+        funds: vec![],
+    })
+}
+
+fn handle_transfer_position_reply(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    result: EuclidResponse,
+    settled_later: bool,
+) -> Result<Response, ContractError> {
+    let transfer_data: TransferLiquidityResponse = result.parse()?;
+    if operation.token_id.as_ref() != Some(&transfer_data.token_id) {
+        return Err(ContractError::PositionNotFound {});
+    }
+    let token_id = transfer_data.token_id.clone();
+    let new_chain_id = transfer_data.new_chain_id.clone();
+
+    // Reverts a move the sender would not have accepted. One the router
+    // already settled has moved the liquidity, so it is applied and the miss
+    // kept on the transfer record.
+    let missed = match check_transfer_limits(deps.storage, &env, operation.id, &transfer_data) {
+        Ok(()) => None,
+        Err(err) if settled_later => Some(err.to_string()),
+        Err(err) => return Err(err),
+    };
+
+    // Update position with new chain ID and the liquidity that arrived
+    let previous = POSITIONS.load(deps.storage, &token_id)?;
+    let mut position = previous.clone();
+    position.position.chain_id = transfer_data.new_chain_id;
+    position.position.amount = transfer_data.amount;
+    position.position.last_updated = env.block.time.seconds();
+    save_position(deps.storage, Some(&previous), &position)?;
+
+    update_transfer(
+        deps.storage,
+        &env,
+        &operation,
+        TransferStatus::Completed,
+        missed.clone(),
+    )?;
+
+    let nft_update = PositionExecuteMsg::UpdateLiquidityPosition {
+        token_id: token_id.clone(),
+        position_updates: vec![position.position.to_nft_position()],
+    };
+
+    Ok(report_limit_miss(Response::new(), missed)
+        .add_message(nft_msg(deps.as_ref(), nft_update)?)
+        .add_messages(expired_lock_msg(deps.as_ref(), &env, &previous)?)
+        .add_attribute("action", "transfer_position_complete")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id)
+        .add_attribute("new_chain_id", new_chain_id))
+}
+
+// The minimum amount, fee cap and deadline of a cross-chain move
+fn check_transfer_limits(
+    storage: &mut dyn Storage,
+    env: &Env,
+    operation_id: u64,
+    transfer_data: &TransferLiquidityResponse,
+) -> Result<(), ContractError> {
+    let limits = take_limits(storage, env, operation_id)?;
+    if matches!(limits.min_amount_out, Some(min) if transfer_data.amount < min) {
+        return Err(ContractError::SlippageExceeded {});
+    }
+    if let Some(max_fee) = &limits.max_fee {
+        if transfer_data
+            .fee
+            .iter()
+            .any(|fee| fee.denom != max_fee.denom || fee.amount > max_fee.amount)
+        {
+            return Err(ContractError::FeeExceeded {});
+        }
+    }
+    Ok(())
+}
+
+// Moves the transfer record of the operation's position along
+fn update_transfer(
+    storage: &mut dyn Storage,
+    env: &Env,
+    operation: &PendingOperation,
+    status: TransferStatus,
+    error: Option<String>,
+) -> Result<(), ContractError> {
+    let token_id = operation
+        .token_id
+        .as_deref()
+        .ok_or(ContractError::PositionNotFound {})?;
+    // Moves submitted before transfers were tracked have no record
+    let mut transfer = match TRANSFERS.may_load(storage, token_id)? {
+        Some(transfer) if transfer.operation_id == operation.id => transfer,
+        _ => return Ok(()),
+    };
+    transfer.status = status;
+    transfer.updated_at = env.block.time.seconds();
+    transfer.error = error;
+    TRANSFERS.save(storage, token_id, &transfer)?;
+    Ok(())
+}
+
+pub fn execute_recover_timed_out_transfer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+
+    let timeout = CONFIG.load(deps.storage)?.transfer_timeout;
+    let transfer = TRANSFERS.load(deps.storage, &token_id)?;
+    let unfinished = matches!(
+        transfer.status,
+        TransferStatus::Initiated | TransferStatus::InFlight
+    );
+    if !unfinished || env.block.time.seconds() < transfer.initiated_at.saturating_add(timeout) {
+        return Err(ContractError::TransferNotTimedOut {});
+    }
+
+    // A callback arriving after this finds no operation and is rejected,
+    // SyncPosition reconciles the position if the router moved it after all
+    let operation = PENDING_OPERATIONS
+        .may_load(deps.storage, transfer.operation_id)?
+        .ok_or(ContractError::OperationNotFound {
+            id: transfer.operation_id,
+        })?;
+    PENDING_OPERATIONS.remove(deps.storage, operation.id)?;
+    OPERATION_LIMITS.remove(deps.storage, operation.id);
+    LOCKED_POSITIONS.remove(deps.storage, &token_id);
+    let error = "Transfer timed out".to_string();
+    update_transfer(
+        deps.storage,
+        &env,
+        &operation,
+        TransferStatus::TimedOut,
+        Some(error.clone()),
+    )?;
+    let failed = FailedOperation {
+        operation,
+        error,
+        refunded: vec![],
+        failed_at: env.block.time.seconds(),
+    };
+    FAILED_OPERATIONS.save(deps.storage, failed.operation.id, &failed)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "recover_timed_out_transfer")
+        .add_attribute("operation_id", failed.operation.id.to_string())
+        .add_attribute("token_id", token_id)
+        .add_attribute("chain_id", transfer.from_chain_id))
+}
+
+fn assert_admin(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+    if *sender != CONFIG.load(storage)?.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+pub fn execute_set_chain(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: String,
+    display_name: String,
+    enabled: bool,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.storage, &info.sender)?;
+    if chain_id.is_empty() {
+        return Err(ContractError::ChainNotSupported {});
+    }
+    let chain = RegisteredChain {
+        chain_id: chain_id.clone(),
+        display_name,
+        enabled,
+    };
+    CHAINS.save(deps.storage, &chain_id, &chain)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_chain")
+        .add_attribute("chain_id", chain_id)
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+pub fn execute_remove_chain(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: String,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.storage, &info.sender)?;
+    if !CHAINS.has(deps.storage, &chain_id) {
+        return Err(ContractError::ChainNotSupported {});
+    }
+    // Positions keep pointing at their chain, disable it instead
+    if POSITIONS
+        .idx
+        .chain_id
+        .prefix(chain_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+    {
+        return Err(ContractError::ChainInUse {});
+    }
+    CHAINS.remove(deps.storage, &chain_id);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_chain")
+        .add_attribute("chain_id", chain_id))
+}
+
+pub fn execute_set_pool(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: String,
+    token_pair: (String, String),
+    chains: Vec<String>,
+    status: PoolStatus,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.storage, &info.sender)?;
+    if pool_id.is_empty() {
+        return Err(ContractError::PoolNotFound {});
+    }
+    let token_pair = canonical_pair(token_pair)?;
+    if chains.is_empty() || chains.iter().any(|chain| !CHAINS.has(deps.storage, chain)) {
+        return Err(ContractError::ChainNotSupported {});
+    }
+    let pool = RegisteredPool {
+        pool_id: pool_id.clone(),
+        token_pair,
+        chains,
+        status,
+    };
+    POOLS.save(deps.storage, &pool_id, &pool)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_pool")
+        .add_attribute("pool_id", pool_id))
+}
+
+pub fn execute_remove_pool(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: String,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.storage, &info.sender)?;
+    if !POOLS.has(deps.storage, &pool_id) {
+        return Err(ContractError::PoolNotFound {});
+    }
+    // Positions keep pointing at their pool, disable it instead
+    if POSITIONS
+        .idx
+        .pool_id
+        .prefix(pool_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+    {
+        return Err(ContractError::PoolInUse {});
+    }
+    POOLS.remove(deps.storage, &pool_id);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_pool")
+        .add_attribute("pool_id", pool_id))
+}
+
+pub fn execute_set_guardian(
+    deps: DepsMut,
+    info: MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    config.guardian = guardian
+        .map(|guardian| deps.api.addr_validate(&guardian))
+        .transpose()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    let guardian = match &config.guardian {
+        Some(guardian) => guardian.to_string(),
+        None => "none".to_string(),
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_guardian")
+        .add_attribute("guardian", guardian))
+}
+
+// The admin or the guardian may pause, in an emergency either should be able
+// to stop new operations reaching the router
+pub fn execute_pause(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    actions: Vec<PausableAction>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin && Some(&info.sender) != config.guardian.as_ref() {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut pause = PAUSE_STATE.may_load(deps.storage)?.unwrap_or_default();
+    for action in &actions {
+        if !pause.paused.contains(action) {
+            pause.paused.push(*action);
+        }
+    }
+    let event = save_pause_state(deps, &env, &info, pause, "paused", &actions)?;
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "pause"))
+}
+
+// Only the admin resumes paused actions
+pub fn execute_unpause(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    actions: Vec<PausableAction>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut pause = PAUSE_STATE.may_load(deps.storage)?.unwrap_or_default();
+    pause.paused.retain(|action| !actions.contains(action));
+    let event = save_pause_state(deps, &env, &info, pause, "unpaused", &actions)?;
+
+    Ok(Response::new()
+        .add_event(event)
+        .add_attribute("action", "unpause"))
+}
+
+// Records who changed the pause state and describes the change in an event
+fn save_pause_state(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    mut pause: PauseState,
+    kind: &str,
+    actions: &[PausableAction],
+) -> StdResult<Event> {
+    pause.updated_by = Some(info.sender.clone());
+    pause.updated_at = Some(env.block.time.seconds());
+    PAUSE_STATE.save(deps.storage, &pause)?;
+
+    Ok(Event::new(kind)
+        .add_attribute("actions", pause_actions_attribute(actions))
+        .add_attribute("paused", pause_actions_attribute(&pause.paused))
+        .add_attribute("sender", info.sender.to_string()))
+}
+
+fn pause_actions_attribute(actions: &[PausableAction]) -> String {
+    actions
+        .iter()
+        .map(|action| match action {
+            PausableAction::AddLiquidity => "add_liquidity",
+            PausableAction::RemoveLiquidity => "remove_liquidity",
+            PausableAction::Transfer => "transfer",
+            PausableAction::ClaimRewards => "claim_rewards",
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Withdraws all of a position while RemoveLiquidity is paused. Only the
+// router is needed, the pool registry is skipped so owners can always get
+// out, but leaving an active time lock still costs the early exit penalty.
+pub fn execute_emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    // Only open while regular withdrawals are paused
+    let pause = PAUSE_STATE.may_load(deps.storage)?.unwrap_or_default();
+    if !pause.paused.contains(&PausableAction::RemoveLiquidity) {
+        return Err(ContractError::WithdrawalsNotPaused {});
+    }
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+
+    // Leaving an active lock still costs the penalty, paid out through this
+    // contract like any early exit
+    let penalty = CONFIG.load(deps.storage)?.early_exit_penalty;
+    let early_exit = match (active_lock(deps.storage, &env, &token_id)?, penalty) {
+        (Some(_), Some(penalty)) => Some(EarlyExit {
+            recipient: position.owner.clone(),
+            penalty,
+        }),
+        _ => None,
+    };
+    let recipient = match &early_exit {
+        Some(_) => env.contract.address.clone(),
+        None => position.owner.clone(),
+    };
+
+    let request = RemoveLiquidityRequest {
+        token_id: token_id.clone(),
+        pool_id: position.position.pool_id.clone(),
+        amount: position.position.amount,
+        recipient: recipient.to_string(),
+        deadline: None,
+    };
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: EuclidAction::RemoveLiquidity,
+        pool_id: position.position.pool_id,
+        chain_id: position.position.chain_id,
+        token_id: Some(token_id.clone()),
+        funds: vec![],
+        submitted_at: env.block.time.seconds(),
+    };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    LOCKED_POSITIONS.save(deps.storage, &token_id, &operation.id)?;
+    if let Some(early_exit) = &early_exit {
+        PENDING_EXITS.save(deps.storage, operation.id, early_exit)?;
+    }
+
+    let submsg = router_submsg(deps.as_ref(), operation.id, &request, vec![])?;
+
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("action", "emergency_withdraw")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id)
+        .add_attribute("amount", position.position.amount))
+}
+
+pub fn execute_set_transfer_timeout(
+    deps: DepsMut,
+    info: MessageInfo,
+    timeout: u64,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    config.transfer_timeout = timeout;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_transfer_timeout")
+        .add_attribute("timeout", timeout.to_string()))
+}
+
+pub fn execute_set_price_oracle(
+    deps: DepsMut,
+    info: MessageInfo,
+    oracle: Option<String>,
+    quote_denom: String,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    config.price_oracle = oracle
+        .map(|oracle| deps.api.addr_validate(&oracle))
+        .transpose()?;
+    config.quote_denom = quote_denom;
+    CONFIG.save(deps.storage, &config)?;
+
+    let source = match &config.price_oracle {
+        Some(oracle) => oracle.to_string(),
+        None => "pool_reserves".to_string(),
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_price_oracle")
+        .add_attribute("oracle", source)
+        .add_attribute("quote_denom", config.quote_denom))
+}
+
+pub fn execute_set_lock_tiers(
+    deps: DepsMut,
+    info: MessageInfo,
+    tiers: Vec<LockTier>,
+    early_exit_penalty: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if tiers
+        .iter()
+        .any(|tier| tier.duration == 0 || tier.multiplier < Decimal::one())
+        || early_exit_penalty.is_some_and(|penalty| penalty > Decimal::one())
+    {
+        return Err(ContractError::InvalidLockTiers {});
+    }
+    config.lock_tiers = tiers;
+    config.early_exit_penalty = early_exit_penalty;
+    CONFIG.save(deps.storage, &config)?;
+
+    let penalty = match early_exit_penalty {
+        Some(penalty) => penalty.to_string(),
+        None => "none".to_string(),
+    };
+    Ok(Response::new()
+        .add_attribute("action", "set_lock_tiers")
+        .add_attribute("tiers", config.lock_tiers.len().to_string())
+        .add_attribute("early_exit_penalty", penalty))
+}
+
+// Locks a position from now, or extends its lock. An active lock keeps its
+// start and may only be pushed later.
+pub fn execute_lock_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    duration: u64,
+) -> Result<Response, ContractError> {
+    POSITIONS.load(deps.storage, &token_id)?;
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+
+    let tier = lock_tier(&CONFIG.load(deps.storage)?, duration)?;
+    let now = env.block.time.seconds();
+    let unlocks_at = now
+        .checked_add(duration)
+        .ok_or(ContractError::InvalidLockDuration {})?;
+    // Extending at the same multiplier keeps the lock's start, a new
+    // multiplier only applies from now on
+    let locked_at = match active_lock(deps.storage, &env, &token_id)? {
+        Some(lock) if unlocks_at < lock.unlocks_at => {
+            return Err(ContractError::InvalidLockDuration {})
+        }
+        Some(lock) if lock.multiplier == tier.multiplier => lock.locked_at,
+        _ => now,
+    };
+    let lock = TimeLock {
+        locked_at,
+        unlocks_at,
+        multiplier: tier.multiplier,
+    };
+    TIME_LOCKS.save(deps.storage, &token_id, &lock)?;
+
+    Ok(Response::new()
+        .add_message(lock_attribute_msg(deps.as_ref(), &token_id, &lock)?)
+        .add_attribute("action", "lock_position")
+        .add_attribute("token_id", token_id)
+        .add_attribute("unlocks_at", unlocks_at.to_string())
+        .add_attribute("multiplier", lock.multiplier.to_string()))
+}
+
+pub fn execute_fund_boost_reserve(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    if info.funds.iter().all(|coin| coin.amount.is_zero()) {
+        return Err(ContractError::InsufficientFunds {});
+    }
+    for coin in &info.funds {
+        add_to_boost_reserve(deps.storage, &coin.denom, coin.amount)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "fund_boost_reserve")
+        .add_attribute("funds", coins_attribute(&info.funds)))
+}
+
+// Query implementation
+fn query_config(deps: Deps) -> StdResult<Config> {
+    CONFIG.load(deps.storage)
+}
+
+fn query_position(deps: Deps, token_id: String) -> StdResult<Option<PositionInfo>> {
+    POSITIONS.may_load(deps.storage, &token_id)
+}
+
+// Pages through the positions under one index value
+fn query_positions(
+    deps: Deps,
+    positions: Prefix<String, PositionInfo, String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PositionInfo>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    positions
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, position)| position))
+        .collect()
+}
+
+fn query_pending_operations(
+    deps: Deps,
+    user: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PendingOperation>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let operations = match user {
+        Some(user) => {
+            let user = deps.api.addr_validate(&user)?;
+            PENDING_OPERATIONS
+                .idx
+                .sender
+                .prefix(user)
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, op)| op))
+                .collect::<StdResult<Vec<_>>>()?
+        }
+        None => PENDING_OPERATIONS
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, op)| op))
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+
+    Ok(operations)
+}
+
+fn query_failed_operations(
+    deps: Deps,
+    user: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<FailedOperation>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let operations = match user {
+        Some(user) => {
+            let user = deps.api.addr_validate(&user)?;
+            FAILED_OPERATIONS
+                .idx
+                .sender
+                .prefix(user)
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, op)| op))
+                .collect::<StdResult<Vec<_>>>()?
+        }
+        None => FAILED_OPERATIONS
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, op)| op))
+            .collect::<StdResult<Vec<_>>>()?,
+    };
+
+    Ok(operations)
+}
+
+fn query_cw20_deposits(deps: Deps, owner: String) -> StdResult<Vec<Cw20Coin>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    CW20_DEPOSITS
+        .prefix(&owner)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (address, amount) = item?;
+            Ok(Cw20Coin {
+                address: address.to_string(),
+                amount,
+            })
+        })
+        .collect()
+}
+
+fn query_pool_liquidity(deps: Deps, pool_id: String) -> StdResult<Uint128> {
+    Ok(POOL_TVL
+        .may_load(deps.storage, &pool_id)?
+        .unwrap_or_default()
+        .liquidity)
+}
+
+fn query_tvl(deps: Deps) -> StdResult<TvlResponse> {
+    Ok(TvlResponse {
+        total: TOTAL_TVL.may_load(deps.storage)?.unwrap_or_default(),
+        reconciling: RECONCILING.may_load(deps.storage)?.is_some(),
+    })
+}
+
+// The configured price source
+fn price_oracle(deps: Deps) -> StdResult<Box<dyn PriceOracle>> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(match config.price_oracle {
+        Some(oracle) => Box::new(OracleContract(oracle)),
+        None => Box::new(PoolReserveOracle(EuclidRouter(config.euclid_router))),
+    })
+}
+
+// Values a position at its pro rata share of the pool reserves
+fn value_position(
+    deps: Deps,
+    oracle: &dyn PriceOracle,
+    info: PositionInfo,
+    quote_denom: &str,
+) -> StdResult<PositionValueResponse> {
+    let position = info.position;
+    let pool = euclid_router(deps)?.pool(&deps.querier, &position.pool_id)?;
+    if pool.total_shares.is_zero() {
+        return Err(StdError::generic_err("Pool has no liquidity"));
+    }
+
+    let mut assets = vec![];
+    let mut value = Uint128::zero();
+    for (denom, reserve) in [
+        (pool.token_pair.0, pool.reserves.0),
+        (pool.token_pair.1, pool.reserves.1),
+    ] {
+        let amount = reserve.multiply_ratio(position.amount, pool.total_shares);
+        let price = if denom == quote_denom {
+            Decimal::one()
+        } else {
+            oracle.price(deps, &denom, quote_denom)?
+        };
+        value = value.checked_add(amount * price)?;
+        assets.push(Coin::new(amount.u128(), denom));
+    }
+
+    Ok(PositionValueResponse {
+        token_id: info.token_id,
+        quote_denom: quote_denom.to_string(),
+        assets,
+        value,
+    })
+}
+
+fn query_portfolio_value(deps: Deps, owner: String) -> StdResult<PortfolioValueResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let quote_denom = CONFIG.load(deps.storage)?.quote_denom;
+    let oracle = price_oracle(deps)?;
+
+    let mut positions = vec![];
+    let mut value = Uint128::zero();
+    for item in
+        POSITIONS
+            .idx
+            .owner
+            .prefix(owner.clone())
+            .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (_, info) = item?;
+        let position = value_position(deps, &*oracle, info, &quote_denom)?;
+        value = value.checked_add(position.value)?;
+        positions.push(position);
+    }
+
+    Ok(PortfolioValueResponse {
+        owner: owner.to_string(),
+        quote_denom,
+        positions,
+        value,
+    })
+}
+
+fn query_estimate_rewards(deps: Deps, env: Env, token_id: String) -> StdResult<Uint128> {
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+    let ledger = load_reward_ledger(deps.storage, &position)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    // Query Euclid router for rewards accrued since the last claim
+    EuclidRouter(config.euclid_router).estimate_rewards(
+        &deps.querier,
+        position.position.pool_id,
+        position.position.amount,
+        env.block
+            .time
+            .seconds()
+            .saturating_sub(ledger.accrued_since),
+    )
+}
+
+fn query_rewards(deps: Deps, env: Env, token_id: String) -> StdResult<RewardsResponse> {
+    // Closed positions keep their ledger but accrue nothing
+    let (ledger, accrued) = match POSITIONS.may_load(deps.storage, &token_id)? {
+        Some(position) => (
+            load_reward_ledger(deps.storage, &position)?,
+            query_estimate_rewards(deps, env, token_id.clone())?,
+        ),
+        None => (
+            REWARD_LEDGERS.load(deps.storage, &token_id)?,
+            Uint128::zero(),
+        ),
+    };
+
+    Ok(RewardsResponse {
+        token_id,
+        denoms: ledger.denoms,
+        accrued,
+        claimed: ledger.claimed,
+        last_claimed: ledger.last_claimed,
+    })
+}
+
+fn query_chains(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RegisteredChain>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    CHAINS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, chain)| chain))
+        .collect()
+}
+
+fn query_registered_pools(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RegisteredPool>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    POOLS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, pool)| pool))
+        .collect()
+}
+
+fn query_auto_compound(deps: Deps, token_id: String) -> StdResult<bool> {
+    Ok(match AUTO_COMPOUND.may_load(deps.storage, &token_id)? {
+        Some(opted_in) => nft_owner(deps, &token_id)? == opted_in,
+        None => false,
+    })
+}
+
+fn query_lock(deps: Deps, env: Env, token_id: String) -> StdResult<LockStatusResponse> {
+    let lock = TIME_LOCKS.may_load(deps.storage, &token_id)?;
+    let locked = lock
+        .as_ref()
+        .is_some_and(|lock| lock.is_active(env.block.time.seconds()));
+    Ok(LockStatusResponse {
+        token_id,
+        lock,
+        locked,
+    })
+}
+
+fn query_boost_reserve(deps: Deps) -> StdResult<BoostReserveResponse> {
+    let funds = BOOST_RESERVE
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(denom, amount)| Coin { denom, amount }))
+        .collect::<StdResult<_>>()?;
+    Ok(BoostReserveResponse { funds })
+}
+
+fn query_reward_history(
+    deps: Deps,
+    token_id: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RewardClaim>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    REWARD_HISTORY
+        .prefix(&token_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, claim)| claim))
+        .collect()
+}
+
+// Positions opened before the ledger existed accrue from their last update
+fn load_reward_ledger(storage: &dyn Storage, position: &PositionInfo) -> StdResult<RewardLedger> {
+    Ok(REWARD_LEDGERS
+        .may_load(storage, &position.token_id)?
+        .unwrap_or_else(|| RewardLedger::new(position.position.last_updated)))
+}
+
+pub fn execute_transfer_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    to_chain_id: String,
+    limits: OperationLimits,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::Transfer)?;
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+
+    // Verify the sender controls the UL-NFT
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+    assert_not_time_locked(deps.storage, &env, &token_id)?;
+    assert_pool_available(
+        deps.storage,
+        &position.position.pool_id,
+        &position.position.chain_id,
+    )?;
+    assert_pool_available(deps.storage, &position.position.pool_id, &to_chain_id)?;
+    check_deadline(&env, limits.deadline)?;
+
+    // Create Euclid message for transferring position
+    let request = TransferLiquidityRequest {
+        token_id: token_id.clone(),
+        from_chain_id: position.position.chain_id.clone(),
+        to_chain_id: to_chain_id.clone(),
+        amount: position.position.amount,
+        deadline: limits.deadline,
+    };
+
+    // Add to pending operations
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: EuclidAction::TransferLiquidity,
+        pool_id: position.position.pool_id,
+        chain_id: to_chain_id,
+        token_id: Some(token_id.clone()),
+        funds: vec![],
+        submitted_at: env.block.time.seconds(),
+    };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    LOCKED_POSITIONS.save(deps.storage, &token_id, &operation.id)?;
+    OPERATION_LIMITS.save(deps.storage, operation.id, &limits)?;
+    let transfer = Transfer {
+        operation_id: operation.id,
+        from_chain_id: position.position.chain_id.clone(),
+        to_chain_id: operation.chain_id.clone(),
+        amount: position.position.amount,
+        status: TransferStatus::Initiated,
+        initiated_at: env.block.time.seconds(),
+        updated_at: env.block.time.seconds(),
+        error: None,
+    };
+    TRANSFERS.save(deps.storage, &token_id, &transfer)?;
+
+    let msg = euclid_router(deps.as_ref())?.execute_tracked(&request, operation.id, vec![])?;
+
+    let submsg = SubMsg {
+        id: operation.id,
+        msg: msg.into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Always,
+    };
+
+    Ok(Response::new()
+        .add_submessage(submsg)
+        .add_attribute("action", "transfer_position")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id))
+}
+
+// Withdraws part of a position, the UL-NFT stays with the rest
+pub fn execute_decrease_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+    amount: Uint128,
+    min_amounts_out: Vec<Coin>,
+    deadline: Option<u64>,
+) -> Result<Response, ContractError> {
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+    if amount.is_zero() || amount >= position.position.amount {
+        return Err(ContractError::InvalidPositionAmount {});
+    }
+    execute_remove_liquidity(
+        deps,
+        env,
+        info,
+        token_id,
+        Some(amount),
+        min_amounts_out,
+        deadline,
+    )
+}
+
+// Overwrites a position with the router's record of it (admin only)
+pub fn execute_sync_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    if info.sender != CONFIG.load(deps.storage)?.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    let previous = POSITIONS.load(deps.storage, &token_id)?;
+    assert_not_locked(deps.storage, &token_id)?;
+
+    // The router forgets positions it paid out in full
+    let record = euclid_router(deps.as_ref())?
+        .position(&deps.querier, &token_id)?
+        .filter(|record| !record.amount.is_zero());
+    let previous_amount = previous.position.amount;
+    let amount = record
+        .as_ref()
+        .map(|record| record.amount)
+        .unwrap_or_default();
+
+    // A position the router no longer holds is burned
+    let (nft_update, lock_msg) = match record {
+        None => {
+            remove_position(deps.storage, &previous)?;
+            let redeem = PositionExecuteMsg::Redeem {
+                token_id: token_id.clone(),
+            };
+            (redeem, None)
+        }
+        Some(record) => {
+            let lock_msg = expired_lock_msg(deps.as_ref(), &env, &previous)?;
+            let mut position = previous.clone();
+            position.position.chain_id = record.chain_id;
+            position.position.amount = record.amount;
+            position.position.last_updated = env.block.time.seconds();
+            save_position(deps.storage, Some(&previous), &position)?;
+            let update = PositionExecuteMsg::UpdateLiquidityPosition {
+                token_id: token_id.clone(),
+                position_updates: vec![position.position.to_nft_position()],
+            };
+            (update, lock_msg)
+        }
+    };
+
+    Ok(Response::new()
+        .add_message(nft_msg(deps.as_ref(), nft_update)?)
+        .add_messages(lock_msg)
+        .add_attribute("action", "sync_position")
+        .add_attribute("token_id", token_id)
+        .add_attribute("previous_amount", previous_amount)
+        .add_attribute("amount", amount))
+}
+
+// Rebuilds the TVL totals from the positions, `limit` positions per call
+// until the response reports `done` (admin only)
+pub fn execute_reconcile(
+    deps: DepsMut,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    assert_admin(deps.storage, &info.sender)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // A new run starts over from empty totals
+    let start_after = match RECONCILING.may_load(deps.storage)? {
+        Some(last) => last,
+        None => {
+            POOL_TVL.clear(deps.storage);
+            CHAIN_TVL.clear(deps.storage);
+            TOTAL_TVL.remove(deps.storage);
+            RECONCILING.save(deps.storage, &None)?;
+            None
+        }
+    };
+    let positions = POSITIONS
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (token_id, position) in &positions {
+        RECONCILING.save(deps.storage, &Some(token_id.clone()))?;
+        count_position(deps.storage, position, true)?;
+    }
+    let done = positions.len() < limit;
+    if done {
+        RECONCILING.remove(deps.storage);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "reconcile")
+        .add_attribute("positions", positions.len().to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+pub fn execute_claim_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::ClaimRewards)?;
+    let position = POSITIONS.load(deps.storage, &token_id)?;
+
+    // Verify the sender controls the UL-NFT
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
+    assert_not_locked(deps.storage, &token_id)?;
+
+    // Create Euclid message for claiming rewards
+    let request = ClaimRewardsRequest {
+        token_id: token_id.clone(),
+        pool_id: position.position.pool_id.clone(),
+        amount: position.position.amount,
+    };
+
+    // Add to pending operations, the rewards are paid out on the reply
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender,
+        action: EuclidAction::ClaimRewards,
+        pool_id: position.position.pool_id,
+        chain_id: position.position.chain_id,
+        token_id: Some(token_id.clone()),
+        funds: vec![],
+        submitted_at: env.block.time.seconds(),
+    };
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    LOCKED_POSITIONS.save(deps.storage, &token_id, &operation.id)?;
+
+    Ok(Response::new()
+        .add_submessage(router_submsg(
+            deps.as_ref(),
+            operation.id,
+            &request,
+            vec![],
+        )?)
+        .add_attribute("action", "claim_rewards")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("token_id", token_id))
+}
 
-    // Update pool liquidity
-    POOL_LIQUIDITY.update(
+// Pays claimed rewards to whoever owns the UL-NFT now and records the claim
+fn handle_claim_rewards_reply(
+    deps: DepsMut,
+    env: Env,
+    operation: PendingOperation,
+    result: EuclidResponse,
+) -> Result<Response, ContractError> {
+    let (token_id, rewards) = parse_claim(&operation, result)?;
+    let recipient = nft_owner(deps.as_ref(), &token_id)?;
+    let rewards = record_claim(
         deps.storage,
-        &position_data.pool_id,
-        |liquid| -> StdResult<_> { Ok(liquid.unwrap_or_default() + position_data.amount) },
+        &env,
+        operation.id,
+        &token_id,
+        &recipient,
+        &rewards,
+        false,
     )?;
 
-    // Clear pending operation
-    PENDING_OPERATIONS.remove(deps.storage, &position_data.pool_id);
-
-    Ok(Response::new()
-        .add_attribute("action", "add_liquidity_complete")
+    let mut response = Response::new()
+        .add_attribute("action", "claim_rewards_complete")
+        .add_attribute("operation_id", operation.id.to_string())
         .add_attribute("token_id", token_id)
-        .add_attribute("amount", position_data.amount))
+        .add_attribute("recipient", recipient.clone())
+        .add_attribute("rewards", coins_attribute(&rewards));
+    if !rewards.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: rewards,
+        });
+    }
+    Ok(response)
 }
 
-fn handle_remove_liquidity_reply(
-    deps: DepsMut,
-    _env: Env,
-    msg: Reply,
-) -> Result<Response, ContractError> {
-    let data = extract_reply_data(msg)?;
-    let result: EuclidResponse = from_json(&data)?;
+// The non-zero rewards a claim paid for the operation's position
+fn parse_claim(
+    operation: &PendingOperation,
+    result: EuclidResponse,
+) -> Result<(String, Vec<Coin>), ContractError> {
+    let claimed: ClaimRewardsResponse = result.parse()?;
+    if operation.token_id.as_ref() != Some(&claimed.token_id) {
+        return Err(ContractError::PositionNotFound {});
+    }
+    let rewards = claimed
+        .rewards
+        .into_iter()
+        .filter(|coin| !coin.amount.is_zero())
+        .collect();
+    Ok((claimed.token_id, rewards))
+}
 
-    if !result.success {
-        return Err(ContractError::CrossChainOperationFailed {});
+// Adds a settled claim to the position's ledger and history, returning the
+// rewards with the boost a time lock earned on top
+fn record_claim(
+    storage: &mut dyn Storage,
+    env: &Env,
+    operation_id: u64,
+    token_id: &str,
+    recipient: &Addr,
+    rewards: &[Coin],
+    compounded: bool,
+) -> StdResult<Vec<Coin>> {
+    let now = env.block.time.seconds();
+    let position = POSITIONS.load(storage, token_id)?;
+    let mut ledger = load_reward_ledger(storage, &position)?;
+    let boost = lock_boost(storage, token_id, rewards, ledger.accrued_since, now)?;
+    let mut rewards = rewards.to_vec();
+    for extra in &boost {
+        match rewards.iter_mut().find(|coin| coin.denom == extra.denom) {
+            Some(coin) => coin.amount += extra.amount,
+            None => rewards.push(extra.clone()),
+        }
+    }
+    for reward in &rewards {
+        match ledger
+            .claimed
+            .iter_mut()
+            .find(|coin| coin.denom == reward.denom)
+        {
+            Some(coin) => {
+                coin.amount = coin
+                    .amount
+                    .checked_add(reward.amount)
+                    .map_err(StdError::from)?
+            }
+            None => {
+                ledger.denoms.push(reward.denom.clone());
+                ledger.claimed.push(reward.clone());
+            }
+        }
     }
+    ledger.accrued_since = now;
+    ledger.last_claimed = Some(now);
+    REWARD_LEDGERS.save(storage, token_id, &ledger)?;
+
+    let claim = RewardClaim {
+        operation_id,
+        recipient: recipient.clone(),
+        rewards,
+        claimed_at: now,
+        compounded,
+        boost,
+    };
+    REWARD_HISTORY.save(storage, (token_id, operation_id), &claim)?;
+    Ok(claim.rewards)
+}
 
-    let remove_data: RemoveLiquidityResponse = from_json(&result.data)?;
-    let position = POSITIONS.load(deps.storage, &remove_data.token_id)?;
+// The part of `(multiplier - 1) * rewards` earned while the position was
+// locked since `accrued_since`, as far as the boost reserve covers it
+fn lock_boost(
+    storage: &mut dyn Storage,
+    token_id: &str,
+    rewards: &[Coin],
+    accrued_since: u64,
+    now: u64,
+) -> StdResult<Vec<Coin>> {
+    let lock = match TIME_LOCKS.may_load(storage, token_id)? {
+        Some(lock) if lock.multiplier > Decimal::one() => lock,
+        _ => return Ok(vec![]),
+    };
+    let locked_from = accrued_since.max(lock.locked_at);
+    let locked_until = now.min(lock.unlocks_at);
+    if locked_until <= locked_from {
+        return Ok(vec![]);
+    }
+    let share = Decimal::from_ratio(locked_until - locked_from, now - accrued_since);
+    let rate = (lock.multiplier - Decimal::one()) * share;
 
-    //  ========= NEED TO FIX THIS =========
-    let temp_fix = position.position.pool_id.clone();
-    // If all liquidity removed, delete position
-    if remove_data.amount == position.position.amount {
-        POSITIONS.remove(deps.storage, &remove_data.token_id);
-    } else {
-        // Update position with remaining amount
-        let mut updated_position = position;
-        updated_position.position.amount -= remove_data.amount;
-        POSITIONS.save(deps.storage, &remove_data.token_id, &updated_position)?;
+    let mut boost = vec![];
+    for reward in rewards {
+        let reserve = BOOST_RESERVE
+            .may_load(storage, &reward.denom)?
+            .unwrap_or_default();
+        let extra = (reward.amount * rate).min(reserve);
+        if extra.is_zero() {
+            continue;
+        }
+        if extra == reserve {
+            BOOST_RESERVE.remove(storage, &reward.denom);
+        } else {
+            BOOST_RESERVE.save(storage, &reward.denom, &(reserve - extra))?;
+        }
+        boost.push(Coin::new(extra.u128(), &reward.denom));
     }
+    Ok(boost)
+}
 
-    // Update pool liquidity
-    POOL_LIQUIDITY.update(deps.storage, &temp_fix, |liquid| -> StdResult<_> {
-        Ok(liquid.unwrap_or_default() - remove_data.amount)
+fn add_to_boost_reserve(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    BOOST_RESERVE.update(storage, denom, |reserve| -> StdResult<_> {
+        reserve
+            .unwrap_or_default()
+            .checked_add(amount)
+            .map_err(StdError::from)
     })?;
+    Ok(())
+}
 
-    Ok(Response::new()
-        .add_attribute("action", "remove_liquidity_complete")
-        .add_attribute("token_id", remove_data.token_id)
-        .add_attribute("amount", remove_data.amount))
+fn coins_attribute(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(Coin::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
 }
 
-fn handle_transfer_position_reply(
+pub fn execute_set_auto_compound(
     deps: DepsMut,
-    _env: Env,
-    msg: Reply,
+    info: MessageInfo,
+    token_id: String,
+    enabled: bool,
 ) -> Result<Response, ContractError> {
-    let data = extract_reply_data(msg)?;
-    let result: EuclidResponse = from_json(&data)?;
+    POSITIONS.load(deps.storage, &token_id)?;
+    assert_nft_authority(deps.as_ref(), &token_id, &info.sender)?;
 
-    if !result.success {
-        return Err(ContractError::CrossChainOperationFailed {});
+    // The opt-in belongs to the current owner and lapses when the NFT moves
+    if enabled {
+        let owner = nft_owner(deps.as_ref(), &token_id)?;
+        AUTO_COMPOUND.save(deps.storage, &token_id, &owner)?;
+    } else {
+        AUTO_COMPOUND.remove(deps.storage, &token_id);
     }
 
-    let transfer_data: TransferPositionResponse = from_json(&result.data)?;
-    let token_id = transfer_data.token_id.clone();
-    let new_chain_id = transfer_data.new_chain_id.clone();
-
-    // Update position with new chain ID
-    POSITIONS.update(
-        deps.storage,
-        &transfer_data.token_id,
-        |pos_opt| -> StdResult<_> {
-            let mut position = pos_opt.ok_or_else(|| StdError::not_found("Position"))?;
-            position.position.chain_id = transfer_data.new_chain_id;
-            Ok(position)
-        },
-    )?;
-
     Ok(Response::new()
-        .add_attribute("action", "transfer_position_complete")
+        .add_attribute("action", "set_auto_compound")
         .add_attribute("token_id", token_id)
-        .add_attribute("new_chain_id", new_chain_id))
+        .add_attribute("enabled", enabled.to_string()))
 }
 
-// Query implementation
-fn query_config(deps: Deps) -> StdResult<Config> {
-    CONFIG.load(deps.storage)
-}
+pub fn execute_set_compound_bounty(
+    deps: DepsMut,
+    info: MessageInfo,
+    bounty: Decimal,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if bounty > MAX_COMPOUND_BOUNTY {
+        return Err(ContractError::InvalidCompoundBounty {
+            max: MAX_COMPOUND_BOUNTY,
+        });
+    }
+    config.compound_bounty = bounty;
+    CONFIG.save(deps.storage, &config)?;
 
-fn query_position(deps: Deps, token_id: String) -> StdResult<Option<PositionInfo>> {
-    POSITIONS.may_load(deps.storage, &token_id)
+    Ok(Response::new()
+        .add_attribute("action", "set_compound_bounty")
+        .add_attribute("bounty", bounty.to_string()))
 }
 
-fn query_pool_positions(deps: Deps, pool_id: String) -> StdResult<Vec<PositionInfo>> {
-    let positions: Vec<PositionInfo> = POSITIONS
-        .range(deps.storage, None, None, Order::Ascending)
-        .filter_map(|item| match item {
-            Ok((_, pos)) if pos.position.pool_id == pool_id => Some(Ok(pos)),
-            Ok(_) => None,
-            Err(e) => Some(Err(e)),
-        })
-        .collect::<StdResult<Vec<_>>>()?;
+pub fn execute_set_max_slippage(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_slippage: Decimal,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if max_slippage > Decimal::one() {
+        return Err(ContractError::InvalidSlippage {});
+    }
+    config.max_slippage = max_slippage;
+    CONFIG.save(deps.storage, &config)?;
 
-    Ok(positions)
+    Ok(Response::new()
+        .add_attribute("action", "set_max_slippage")
+        .add_attribute("max_slippage", max_slippage.to_string()))
 }
 
-fn query_pending_operations(deps: Deps, token_id: String) -> StdResult<Vec<String>> {
-    PENDING_OPERATIONS
-        .may_load(deps.storage, &token_id)
-        .map(|ops| ops.unwrap_or_default())
-}
+pub fn execute_set_deposit_ratio_tolerance(
+    deps: DepsMut,
+    info: MessageInfo,
+    tolerance: Decimal,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if tolerance > Decimal::one() {
+        return Err(ContractError::InvalidDepositRatioTolerance {});
+    }
+    config.deposit_ratio_tolerance = tolerance;
+    CONFIG.save(deps.storage, &config)?;
 
-fn query_pool_liquidity(deps: Deps, pool_id: String) -> StdResult<Uint128> {
-    Ok(POOL_LIQUIDITY
-        .may_load(deps.storage, &pool_id)?
-        .unwrap_or_default())
+    Ok(Response::new()
+        .add_attribute("action", "set_deposit_ratio_tolerance")
+        .add_attribute("tolerance", tolerance.to_string()))
 }
 
-fn query_estimate_rewards(deps: Deps, token_id: String) -> StdResult<Uint128> {
+pub fn execute_compound(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableAction::ClaimRewards)?;
     let position = POSITIONS.load(deps.storage, &token_id)?;
-    let config = CONFIG.load(deps.storage)?;
+    let owner = nft_owner(deps.as_ref(), &token_id)?;
+    if AUTO_COMPOUND.may_load(deps.storage, &token_id)? != Some(owner) {
+        return Err(ContractError::AutoCompoundDisabled {});
+    }
+    assert_not_locked(deps.storage, &token_id)?;
+    assert_pool_available(
+        deps.storage,
+        &position.position.pool_id,
+        &position.position.chain_id,
+    )?;
+    // Keepers are not trusted to bound the swaps, only the oracle can
+    if CONFIG.load(deps.storage)?.price_oracle.is_none() {
+        return Err(ContractError::NoPriceOracle {});
+    }
 
-    // Create Euclid query message for rewards estimation
-    let euclid_query = EuclidMsg {
+    let request = ClaimRewardsRequest {
+        token_id: token_id.clone(),
+        pool_id: position.position.pool_id.clone(),
+        amount: position.position.amount,
+    };
+    let operation = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        sender: info.sender.clone(),
         action: EuclidAction::ClaimRewards,
-        data: to_json_binary(&json!({
-            "pool_id": position.position.pool_id,
-            "amount": position.position.amount,
-            "duration": position.position.last_updated,
-        }))?,
+        pool_id: position.position.pool_id,
+        chain_id: position.position.chain_id,
+        token_id: Some(token_id.clone()),
+        funds: vec![],
+        submitted_at: env.block.time.seconds(),
     };
-
-    // Query Euclid router for rewards
-    let rewards: Uint128 = deps
-        .querier
-        .query_wasm_smart(config.euclid_router, &euclid_query)?;
-
-    Ok(rewards)
+    let compound = Compound {
+        keeper: info.sender,
+        token_pair: position.position.token_pair,
+        pending: vec![],
+        held: (Uint128::zero(), Uint128::zero()),
+        atomic: true,
+    };
+    let res = Response::new()
+        .add_attribute("action", "compound")
+        .add_attribute("token_id", token_id);
+    submit_compound_step(deps, operation, compound, &request, res)
 }
 
-pub fn execute_transfer_position(
+// Moves a compound on to its next step
+fn resolve_compound_step(
     deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    token_id: String,
-    to_chain_id: String,
+    env: Env,
+    operation: PendingOperation,
+    response: EuclidResponse,
+    mut compound: Compound,
 ) -> Result<Response, ContractError> {
-    let position = POSITIONS.load(deps.storage, &token_id)?;
+    if !response.success {
+        let error = response
+            .error
+            .unwrap_or_else(|| "Unknown router error".to_string());
+        if compound.atomic {
+            return Err(ContractError::CompoundFailed { error });
+        }
+        return handle_failed_compound(deps, env, operation, compound, error);
+    }
 
-    // Verify ownership
-    if position.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let pair = compound.token_pair.clone();
+    let mut res = Response::new();
+    match operation.action {
+        EuclidAction::ClaimRewards => {
+            let (token_id, rewards) = parse_claim(&operation, response)?;
+            let owner = nft_owner(deps.as_ref(), &token_id)?;
+            let rewards = record_claim(
+                deps.storage,
+                &env,
+                operation.id,
+                &token_id,
+                &owner,
+                &rewards,
+                true,
+            )?;
+
+            // The keeper's bounty comes off every reward token
+            let bounty_rate = CONFIG.load(deps.storage)?.compound_bounty;
+            let mut bounty = vec![];
+            for mut reward in rewards {
+                let cut = reward.amount * bounty_rate;
+                if !cut.is_zero() {
+                    bounty.push(Coin::new(cut.u128(), &reward.denom));
+                    reward.amount -= cut;
+                }
+                if reward.denom == pair.0 {
+                    compound.held.0 += reward.amount;
+                } else if reward.denom == pair.1 {
+                    compound.held.1 += reward.amount;
+                } else if !reward.amount.is_zero() {
+                    compound.pending.push(reward);
+                }
+            }
+            res = res
+                .add_attribute("action", "compound_claimed")
+                .add_attribute("bounty", coins_attribute(&bounty));
+            if !bounty.is_empty() {
+                res = res.add_message(BankMsg::Send {
+                    to_address: compound.keeper.to_string(),
+                    amount: bounty,
+                });
+            }
+        }
+        EuclidAction::SwapExactIn => {
+            let swap: SwapResponse = response.parse()?;
+            let offer = operation.funds[0].denom.clone();
+            res = res
+                .add_attribute("action", "compound_swapped")
+                .add_attribute("amount_out", swap.amount_out);
+            // A swap from one side of the pair evened the sides out
+            if offer == pair.0 || offer == pair.1 {
+                if offer == pair.0 {
+                    compound.held.1 += swap.amount_out;
+                } else {
+                    compound.held.0 += swap.amount_out;
+                }
+                return submit_compound_deposit(deps, env, operation, compound, res);
+            }
+            compound.held.0 += swap.amount_out;
+        }
+        EuclidAction::AddLiquidity => {
+            let res = handle_add_liquidity_reply(deps, env, operation, response, false)?;
+            return Ok(res.add_attribute("action", "compound_complete"));
+        }
+        action => {
+            return Err(ContractError::Std(StdError::generic_err(format!(
+                "Unexpected compound step: {:?}",
+                action
+            ))))
+        }
     }
 
-    // Create Euclid message for transferring position
-    let euclid_msg = EuclidMsg {
-        action: EuclidAction::TransferLiquidity,
-        data: to_json_binary(&json!({
-            "token_id": token_id,
-            "from_chain_id": position.position.chain_id,
-            "to_chain_id": to_chain_id,
-            "amount": position.position.amount,
-        }))?,
-    };
+    // Rewards outside the pair are swapped into its first token one by one,
+    // each swap bounded by the oracle price less the configured slippage
+    if let Some(reward) = compound.pending.pop() {
+        let route = vec![reward.denom.clone(), pair.0.clone()];
+        let request = SwapExactInRequest {
+            min_amount_out: compound_swap_floor(deps.as_ref(), &route, reward.amount)?,
+            route,
+            amount_in: reward.amount,
+            deadline: None,
+        };
+        let swap = PendingOperation {
+            id: next_operation_id(deps.storage)?,
+            action: EuclidAction::SwapExactIn,
+            funds: vec![reward],
+            submitted_at: env.block.time.seconds(),
+            ..operation
+        };
+        return submit_compound_step(deps, swap, compound, &request, res);
+    }
 
-    let msg = WasmMsg::Execute {
-        contract_addr: CONFIG.load(deps.storage)?.euclid_router.to_string(),
-        msg: to_json_binary(&euclid_msg)?,
-        funds: vec![],
+    // Then half the difference between the sides is swapped across
+    let (offer, ask, difference) = if compound.held.0 >= compound.held.1 {
+        (&pair.0, &pair.1, compound.held.0 - compound.held.1)
+    } else {
+        (&pair.1, &pair.0, compound.held.1 - compound.held.0)
     };
-
-    let submsg = SubMsg {
-        id: REPLY_TRANSFER_POSITION,
-        msg: msg.into(),
-        gas_limit: None,
-        reply_on: ReplyOn::Success,
-        //payload: Binary::default(), // Add this
+    let swap_amount = Uint128::new(difference.u128() / 2);
+    if swap_amount.is_zero() {
+        return submit_compound_deposit(deps, env, operation, compound, res);
+    }
+    if *offer == pair.0 {
+        compound.held.0 -= swap_amount;
+    } else {
+        compound.held.1 -= swap_amount;
+    }
+    let route = vec![offer.clone(), ask.clone()];
+    let request = SwapExactInRequest {
+        min_amount_out: compound_swap_floor(deps.as_ref(), &route, swap_amount)?,
+        route,
+        amount_in: swap_amount,
+        deadline: None,
     };
+    let swap = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        action: EuclidAction::SwapExactIn,
+        funds: vec![Coin::new(swap_amount.u128(), offer)],
+        submitted_at: env.block.time.seconds(),
+        ..operation
+    };
+    submit_compound_step(deps, swap, compound, &request, res)
+}
 
-    Ok(Response::new()
-        .add_submessage(submsg)
-        .add_attribute("action", "transfer_position")
-        .add_attribute("token_id", token_id))
+// Floor of a compound swap, which keepers trigger and so always needs the
+// oracle
+fn compound_swap_floor(
+    deps: Deps,
+    route: &[String],
+    amount_in: Uint128,
+) -> Result<Uint128, ContractError> {
+    swap_floor(deps, &route[0], &route[1], amount_in, None)?.ok_or(ContractError::NoPriceOracle {})
 }
 
-pub fn execute_update_position(
+// Adds both tokens back to the position at the pool's ratio, the excess goes
+// to its owner
+fn submit_compound_deposit(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
-    token_id: String,
-    new_amount: Uint128,
+    operation: PendingOperation,
+    mut compound: Compound,
+    mut res: Response,
 ) -> Result<Response, ContractError> {
-    let mut position = POSITIONS.load(deps.storage, &token_id)?;
+    let token_id = operation
+        .token_id
+        .clone()
+        .ok_or(ContractError::PositionNotFound {})?;
+    let pair = compound.token_pair.clone();
+    let amounts = balanced_deposit(deps.as_ref(), &operation.pool_id, &pair, compound.held)?;
+    let excess: Vec<Coin> = [
+        Coin::new((compound.held.0 - amounts.0).u128(), &pair.0),
+        Coin::new((compound.held.1 - amounts.1).u128(), &pair.1),
+    ]
+    .into_iter()
+    .filter(|coin| !coin.amount.is_zero())
+    .collect();
+    compound.held = (Uint128::zero(), Uint128::zero());
+    if !excess.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: nft_owner(deps.as_ref(), &token_id)?.to_string(),
+            amount: excess,
+        });
+    }
 
-    // Verify ownership
-    if position.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    if amounts.0.is_zero() || amounts.1.is_zero() {
+        return Ok(res
+            .add_attribute("action", "compound_complete")
+            .add_attribute("token_id", token_id)
+            .add_attribute("amount", Uint128::zero()));
     }
 
-    // Update position amount
-    position.position.amount = new_amount;
-    position.position.last_updated = env.block.time.seconds();
+    let request = AddLiquidityRequest {
+        pool_id: operation.pool_id.clone(),
+        chain_id: operation.chain_id.clone(),
+        token_pair: pair.clone(),
+        amounts,
+        min_shares: Uint128::zero(),
+        cw20_assets: vec![],
+        token_id,
+    };
+    let deposit = PendingOperation {
+        id: next_operation_id(deps.storage)?,
+        action: EuclidAction::AddLiquidity,
+        funds: vec![
+            Coin::new(amounts.0.u128(), &pair.0),
+            Coin::new(amounts.1.u128(), &pair.1),
+        ],
+        submitted_at: env.block.time.seconds(),
+        ..operation
+    };
+    submit_compound_step(deps, deposit, compound, &request, res)
+}
 
-    // Save updated position
-    POSITIONS.save(deps.storage, &token_id, &position)?;
+// Sends one step of a compound to the router, the position stays locked
+// until the last step resolves
+fn submit_compound_step<T: EuclidRequest>(
+    deps: DepsMut,
+    operation: PendingOperation,
+    compound: Compound,
+    request: &T,
+    res: Response,
+) -> Result<Response, ContractError> {
+    PENDING_OPERATIONS.save(deps.storage, operation.id, &operation)?;
+    PENDING_COMPOUNDS.save(deps.storage, operation.id, &compound)?;
+    if let Some(token_id) = &operation.token_id {
+        LOCKED_POSITIONS.save(deps.storage, token_id, &operation.id)?;
+    }
 
-    Ok(Response::new()
-        .add_attribute("action", "update_position")
-        .add_attribute("token_id", token_id)
-        .add_attribute("new_amount", new_amount))
+    let submsg = router_submsg(
+        deps.as_ref(),
+        operation.id,
+        request,
+        operation.funds.clone(),
+    )?;
+    Ok(res
+        .add_submessage(submsg)
+        .add_attribute("operation_id", operation.id.to_string()))
 }
 
-pub fn execute_claim_rewards(
+// Records a failed compound step and pays everything the compound held,
+// rewards already claimed included, to the owner of the position
+fn handle_failed_compound(
     deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    token_id: String,
+    env: Env,
+    operation: PendingOperation,
+    compound: Compound,
+    error: String,
 ) -> Result<Response, ContractError> {
-    let position = POSITIONS.load(deps.storage, &token_id)?;
+    let pair = &compound.token_pair;
+    let held = [
+        Coin::new(compound.held.0.u128(), &pair.0),
+        Coin::new(compound.held.1.u128(), &pair.1),
+    ];
+    let mut refund: Vec<Coin> = vec![];
+    for coin in held
+        .into_iter()
+        .chain(compound.pending)
+        .chain(operation.funds.clone())
+    {
+        if coin.amount.is_zero() {
+            continue;
+        }
+        match refund.iter_mut().find(|c| c.denom == coin.denom) {
+            Some(c) => c.amount += coin.amount,
+            None => refund.push(coin),
+        }
+    }
 
-    // Verify ownership
-    if position.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let mut response = Response::new()
+        .add_attribute("action", "operation_failed")
+        .add_attribute("operation_id", operation.id.to_string())
+        .add_attribute("error", error.clone());
+    if !refund.is_empty() {
+        let token_id = operation
+            .token_id
+            .as_deref()
+            .ok_or(ContractError::PositionNotFound {})?;
+        response = response.add_message(BankMsg::Send {
+            to_address: nft_owner(deps.as_ref(), token_id)?.to_string(),
+            amount: refund.clone(),
+        });
     }
 
-    // Create Euclid message for claiming rewards
-    let euclid_msg = EuclidMsg {
-        action: EuclidAction::ClaimRewards,
-        data: to_json_binary(&json!({
-            "token_id": token_id,
-            "pool_id": position.position.pool_id,
-            "amount": position.position.amount,
-        }))?,
+    let failed = FailedOperation {
+        operation,
+        error,
+        refunded: refund,
+        failed_at: env.block.time.seconds(),
     };
+    FAILED_OPERATIONS.save(deps.storage, failed.operation.id, &failed)?;
 
-    let msg = WasmMsg::Execute {
-        contract_addr: CONFIG.load(deps.storage)?.euclid_router.to_string(),
-        msg: to_json_binary(&euclid_msg)?,
-        funds: vec![],
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::euclid::{EuclidQueryMsg, PoolResponse};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        coin, coins, from_json, ContractResult, CosmosMsg, OwnedDeps, SubMsgResponse, SubMsgResult,
+        SystemError, SystemResult, WasmQuery,
     };
+    use cw721::Expiration;
 
-    Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "claim_rewards")
-        .add_attribute("token_id", token_id))
-}
+    fn router_reply(id: u64, data: Binary) -> Reply {
+        let response = EuclidResponse {
+            success: true,
+            data,
+            error: None,
+        };
+        #[allow(deprecated)]
+        Reply {
+            id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(execute_response_data(&response)),
+            }),
+        }
+    }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-struct RemoveLiquidityResponse {
-    token_id: String,
-    amount: Uint128,
-}
+    // Router data as the chain delivers it, wrapped in a MsgExecuteContractResponse
+    fn execute_response_data(response: &EuclidResponse) -> Binary {
+        let data = to_json_binary(response).unwrap();
+        let mut encoded = vec![0x0a];
+        let mut len = data.len();
+        while len >= 0x80 {
+            encoded.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        encoded.push(len as u8);
+        encoded.extend_from_slice(&data);
+        Binary::from(encoded)
+    }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-struct TransferPositionResponse {
-    token_id: String,
-    new_chain_id: String,
+    // Registers archway and pool1 trading `token_pair` on it
+    fn register_pool(mut deps: DepsMut, token_pair: (&str, &str)) {
+        let admin = mock_info("creator", &[]);
+        execute_set_chain(
+            deps.branch(),
+            admin.clone(),
+            "archway".to_string(),
+            "Archway".to_string(),
+            true,
+        )
+        .unwrap();
+        execute_set_pool(
+            deps,
+            admin,
+            "pool1".to_string(),
+            (token_pair.0.to_string(), token_pair.1.to_string()),
+            vec!["archway".to_string()],
+            PoolStatus::Active,
+        )
+        .unwrap();
+    }
+
+    // A router that knows every pool, all of them still empty
+    fn mock_empty_pools(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => match from_json(msg).unwrap() {
+                EuclidQueryMsg::Pool { pool_id } => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&PoolResponse {
+                        pool_id,
+                        token_pair: ("uarch".to_string(), "uusdc".to_string()),
+                        reserves: (Uint128::zero(), Uint128::zero()),
+                        total_shares: Uint128::zero(),
+                    })
+                    .unwrap(),
+                )),
+                _ => SystemResult::Err(SystemError::Unknown {}),
+            },
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+    }
+
+    fn save_pending(
+        deps: DepsMut,
+        sender: &str,
+        action: EuclidAction,
+        token_id: Option<&str>,
+    ) -> u64 {
+        let operation = PendingOperation {
+            id: next_operation_id(deps.storage).unwrap(),
+            sender: Addr::unchecked(sender),
+            action,
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_id: token_id.map(|id| id.to_string()),
+            funds: vec![],
+            submitted_at: 0,
+        };
+        PENDING_OPERATIONS
+            .save(deps.storage, operation.id, &operation)
+            .unwrap();
+        operation.id
+    }
+
+    fn nft_call(msg: &CosmosMsg) -> PositionExecuteMsg {
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr, "ul_nft");
+                match from_json(msg).unwrap() {
+                    NftExecuteMsg::Extension { msg } => msg,
+                    other => panic!("unexpected nft message: {:?}", other),
+                }
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deposits_short_of_min_shares() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+        let limits = OperationLimits {
+            min_shares: Uint128::new(1001),
+            ..OperationLimits::default()
+        };
+        let position = AddLiquidityResponse {
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amount: Uint128::new(1000),
+        };
+
+        // A reply short of the minimum reverts the deposit
+        let op_id = save_pending(
+            deps.as_mut(),
+            "alice",
+            EuclidAction::AddLiquidity,
+            Some("ulp-1"),
+        );
+        OPERATION_LIMITS
+            .save(deps.as_mut().storage, op_id, &limits)
+            .unwrap();
+        let reply_msg = router_reply(op_id, to_json_binary(&position).unwrap());
+        let err = reply(deps.as_mut(), mock_env(), reply_msg).unwrap_err();
+        assert!(matches!(err, ContractError::SlippageExceeded {}));
+
+        // A callback can no longer revert, the shares are credited and the
+        // miss reported
+        let op_id = save_pending(
+            deps.as_mut(),
+            "alice",
+            EuclidAction::AddLiquidity,
+            Some("ulp-2"),
+        );
+        OPERATION_LIMITS
+            .save(deps.as_mut().storage, op_id, &limits)
+            .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("router", &[]),
+            ExecuteMsg::EuclidCallback {
+                tx_id: op_id,
+                response: EuclidResponse::ok(&position).unwrap(),
+            },
+        )
+        .unwrap();
+        assert!(res.attributes.contains(&cosmwasm_std::attr(
+            "limit_missed",
+            "Slippage limit exceeded"
+        )));
+        let position = POSITIONS.load(deps.as_ref().storage, "ulp-2").unwrap();
+        assert_eq!(position.position.amount, Uint128::new(1000));
+    }
+
+    #[test]
+    fn replies_mint_update_and_burn_the_nft() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+        let op_id = save_pending(
+            deps.as_mut(),
+            "alice",
+            EuclidAction::AddLiquidity,
+            Some("ulp-1"),
+        );
+
+        // A confirmed deposit mints the UL-NFT to the depositor
+        let position = AddLiquidityResponse {
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amount: Uint128::new(1000),
+        };
+        let reply_msg = router_reply(op_id, to_json_binary(&position).unwrap());
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        match nft_call(&res.messages[0].msg) {
+            PositionExecuteMsg::Mint {
+                token_id,
+                owner,
+                positions,
+                ..
+            } => {
+                assert_eq!(token_id, "ulp-1");
+                assert_eq!(owner, "alice");
+                assert_eq!(positions[0].pool_id, position.pool_id);
+                assert_eq!(positions[0].amount, position.amount);
+            }
+            other => panic!("expected mint, got {:?}", other),
+        }
+        assert!(POSITIONS.has(deps.as_ref().storage, "ulp-1"));
+
+        // A partial withdrawal updates the NFT
+        let removed = RemoveLiquidityResponse {
+            token_id: "ulp-1".to_string(),
+            amount: Uint128::new(400),
+            assets: vec![],
+        };
+        let op_id = save_pending(
+            deps.as_mut(),
+            "alice",
+            EuclidAction::RemoveLiquidity,
+            Some("ulp-1"),
+        );
+        let reply_msg = router_reply(op_id, to_json_binary(&removed).unwrap());
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        match nft_call(&res.messages[0].msg) {
+            PositionExecuteMsg::UpdateLiquidityPosition {
+                position_updates, ..
+            } => assert_eq!(position_updates[0].amount, Uint128::new(600)),
+            other => panic!("expected update, got {:?}", other),
+        }
+
+        // Withdrawing the rest burns it
+        let removed = RemoveLiquidityResponse {
+            token_id: "ulp-1".to_string(),
+            amount: Uint128::new(600),
+            assets: vec![],
+        };
+        let op_id = save_pending(
+            deps.as_mut(),
+            "alice",
+            EuclidAction::RemoveLiquidity,
+            Some("ulp-1"),
+        );
+        let reply_msg = router_reply(op_id, to_json_binary(&removed).unwrap());
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(
+            nft_call(&res.messages[0].msg),
+            PositionExecuteMsg::Redeem {
+                token_id: "ulp-1".to_string()
+            }
+        );
+        assert!(!POSITIONS.has(deps.as_ref().storage, "ulp-1"));
+    }
+
+    #[test]
+    fn concurrent_deposits_credit_the_right_users() {
+        let mut deps = mock_dependencies();
+        mock_empty_pools(&mut deps);
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+
+        // Two deposits into the same pool in the same block
+        let deposit = ExecuteMsg::AddLiquidity {
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amounts: (Uint128::new(1000), Uint128::new(1000)),
+            min_shares: Uint128::zero(),
+            lock_duration: None,
+        };
+        let mut ids = vec![];
+        for user in ["alice", "bob"] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(user, &[coin(1000, "uarch"), coin(1000, "uusdc")]),
+                deposit.clone(),
+            )
+            .unwrap();
+            ids.push(res.messages[0].id);
+        }
+        assert_eq!(ids, vec![1, 2]);
+
+        let pending: Vec<PendingOperation> = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPendingOperations {
+                    user: Some("bob".to_string()),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, 2);
+
+        // Replies resolve in reverse order and still mint to the right owner
+        let position = AddLiquidityResponse {
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amount: Uint128::new(1000),
+        };
+        for (id, user) in [(2, "bob"), (1, "alice")] {
+            let reply_msg = router_reply(id, to_json_binary(&position).unwrap());
+            let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+            match nft_call(&res.messages[0].msg) {
+                PositionExecuteMsg::Mint { owner, .. } => assert_eq!(owner, user),
+                other => panic!("expected mint, got {:?}", other),
+            }
+        }
+
+        // Each operation is resolved only once
+        let reply_msg = router_reply(1, to_json_binary(&position).unwrap());
+        let err = reply(deps.as_mut(), mock_env(), reply_msg).unwrap_err();
+        assert!(matches!(err, ContractError::OperationNotFound { id: 1 }));
+    }
+
+    #[test]
+    fn failed_operations_are_recorded_and_refunded() {
+        let mut deps = mock_dependencies();
+        mock_empty_pools(&mut deps);
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+
+        // A reverted deposit refunds the escrowed funds
+        let deposit = ExecuteMsg::AddLiquidity {
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amounts: (Uint128::new(1000), Uint128::new(1000)),
+            min_shares: Uint128::zero(),
+            lock_duration: None,
+        };
+        let funds = vec![coin(1000, "uarch"), coin(1000, "uusdc")];
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &funds),
+            deposit,
+        )
+        .unwrap();
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Always);
+        #[allow(deprecated)]
+        let reply_msg = Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Err("pool is paused".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: funds.clone(),
+            })
+        );
+
+        // A router reporting failure is recorded with its error
+        let op_id = save_pending(
+            deps.as_mut(),
+            "bob",
+            EuclidAction::RemoveLiquidity,
+            Some("ulp-7"),
+        );
+        LOCKED_POSITIONS
+            .save(deps.as_mut().storage, "ulp-7", &op_id)
+            .unwrap();
+        let response = EuclidResponse::failure("timeout on destination chain");
+        #[allow(deprecated)]
+        let reply_msg = Reply {
+            id: op_id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(execute_response_data(&response)),
+            }),
+        };
+        let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert!(!LOCKED_POSITIONS.has(deps.as_ref().storage, "ulp-7"));
+
+        let failed: Vec<FailedOperation> = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetFailedOperations {
+                    user: None,
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].operation.sender, Addr::unchecked("alice"));
+        assert_eq!(failed[0].error, "pool is paused");
+        assert_eq!(failed[0].refunded, funds);
+        assert_eq!(failed[1].operation.id, op_id);
+        assert_eq!(failed[1].error, "timeout on destination chain");
+        assert!(failed[1].refunded.is_empty());
+    }
+
+    #[test]
+    fn position_authority_follows_the_nft() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+        let position = PositionInfo {
+            owner: Addr::unchecked("alice"),
+            token_id: "ulp-1".to_string(),
+            position: LiquidityPosition {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                token_pair: ("uarch".to_string(), "uusdc".to_string()),
+                amount: Uint128::new(1000),
+                last_updated: 0,
+            },
+        };
+        save_position(deps.as_mut().storage, None, &position).unwrap();
+
+        // Alice sold the NFT to bob, who made vault an operator
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } => match from_json(msg).unwrap() {
+                NftQueryMsg::OwnerOf { .. } => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&OwnerOfResponse {
+                        owner: "bob".to_string(),
+                        approvals: vec![],
+                    })
+                    .unwrap(),
+                )),
+                NftQueryMsg::Approval { spender, .. } if spender == "vault" => {
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_json_binary(&ApprovalResponse {
+                            approval: cw721::Approval {
+                                spender,
+                                expires: Expiration::Never {},
+                            },
+                        })
+                        .unwrap(),
+                    ))
+                }
+                _ => SystemResult::Ok(ContractResult::Err("Approval not found".to_string())),
+            },
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+
+        let remove = ExecuteMsg::RemoveLiquidity {
+            token_id: "ulp-1".to_string(),
+            amount: None,
+            min_amounts_out: vec![],
+            deadline: None,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            remove.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            remove.clone(),
+        )
+        .unwrap();
+
+        // The position stays locked until the router replies
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("vault", &[]),
+            remove.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::PositionLocked {}));
+        LOCKED_POSITIONS.remove(deps.as_mut().storage, "ulp-1");
+        execute(deps.as_mut(), mock_env(), mock_info("vault", &[]), remove).unwrap();
+
+        // Only the UL-NFT contract can report transfers
+        let transferred = ExecuteMsg::NftTransferred {
+            token_id: "ulp-1".to_string(),
+            previous_owner: "alice".to_string(),
+            new_owner: "bob".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            transferred.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // Nor can the token change hands while the withdrawal is in flight
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("ul_nft", &[]),
+            transferred.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::PositionLocked {}));
+        LOCKED_POSITIONS.remove(deps.as_mut().storage, "ulp-1");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("ul_nft", &[]),
+            transferred,
+        )
+        .unwrap();
+        let position = POSITIONS.load(deps.as_ref().storage, "ulp-1").unwrap();
+        assert_eq!(position.owner, Addr::unchecked("bob"));
+    }
+
+    #[test]
+    fn swaps_validate_and_settle() {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+
+        let swap = |route: &[&str], deadline| ExecuteMsg::SwapExactOut {
+            route: route.iter().map(|denom| denom.to_string()).collect(),
+            amount_out: Uint128::new(400),
+            max_amount_in: Uint128::new(500),
+            deadline,
+        };
+        let funds = coins(600, "uarch");
+        let info = mock_info("alice", &funds);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            swap(&["uarch"], None),
+        );
+        assert!(matches!(err, Err(ContractError::InvalidSwapRoute {})));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            swap(&["uarch", "uarch"], None),
+        );
+        assert!(matches!(err, Err(ContractError::InvalidSwapRoute {})));
+        let expired = mock_env().block.time.seconds() - 1;
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            swap(&["uarch", "uusdc"], Some(expired)),
+        );
+        assert!(matches!(err, Err(ContractError::DeadlineExceeded {})));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &coins(499, "uarch")),
+            swap(&["uarch", "uusdc"], None),
+        );
+        assert!(matches!(err, Err(ContractError::InsufficientFunds {})));
+
+        // The output and the unused input go back to the sender
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            swap(&["uarch", "uatom", "uusdc"], None),
+        )
+        .unwrap();
+        let op_id = res.messages[0].id;
+        let operation = PENDING_OPERATIONS.load(&deps.storage, op_id).unwrap();
+        assert_eq!(operation.action, EuclidAction::SwapExactOut);
+        assert_eq!(operation.pool_id, "uarch>uatom>uusdc");
+
+        let data = to_json_binary(&SwapResponse {
+            amount_in: Uint128::new(450),
+            amount_out: Uint128::new(400),
+        })
+        .unwrap();
+        let res = reply(deps.as_mut(), mock_env(), router_reply(op_id, data)).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: vec![Coin::new(400, "uusdc"), Coin::new(150, "uarch")],
+            })
+        );
+        assert!(!PENDING_SWAPS.has(&deps.storage, op_id));
+
+        // A router fill outside the limits is rejected
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            swap(&["uarch", "uusdc"], None),
+        )
+        .unwrap();
+        let data = to_json_binary(&SwapResponse {
+            amount_in: Uint128::new(501),
+            amount_out: Uint128::new(400),
+        })
+        .unwrap();
+        let err = reply(
+            deps.as_mut(),
+            mock_env(),
+            router_reply(res.messages[0].id, data),
+        );
+        assert!(matches!(err, Err(ContractError::SlippageExceeded {})));
+    }
+
+    #[test]
+    fn deposits_are_validated_against_the_pair() {
+        let mut deps = mock_dependencies();
+        mock_empty_pools(&mut deps);
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+
+        let deposit = |pair: (&str, &str), amounts: (u128, u128)| ExecuteMsg::AddLiquidity {
+            pool_id: "pool1".to_string(),
+            chain_id: "archway".to_string(),
+            token_pair: (pair.0.to_string(), pair.1.to_string()),
+            amounts: (Uint128::new(amounts.0), Uint128::new(amounts.1)),
+            min_shares: Uint128::zero(),
+            lock_duration: None,
+        };
+        let both = [coin(1000, "uarch"), coin(1000, "uusdc")];
+        let cases = [
+            (
+                deposit(("uarch", "uarch"), (1000, 1000)),
+                both.to_vec(),
+                "pair",
+            ),
+            (deposit(("uarch", "uusdc"), (0, 0)), both.to_vec(), "amount"),
+            (
+                deposit(("uarch", "uusdc"), (1000, 0)),
+                coins(1000, "uarch"),
+                "amount",
+            ),
+            (
+                deposit(("uarch", "uusdc"), (1000, 1000)),
+                vec![
+                    coin(1000, "uarch"),
+                    coin(1000, "uatom"),
+                    coin(1000, "uusdc"),
+                ],
+                "pair",
+            ),
+            (
+                deposit(("uarch", "uusdc"), (1000, 1000)),
+                coins(1000, "uarch"),
+                "amount",
+            ),
+            (
+                deposit(("uarch", "uusdc"), (1000, 1000)),
+                vec![coin(1000, "uarch"), coin(900, "uusdc")],
+                "amount",
+            ),
+        ];
+        for (msg, funds, expected) in cases {
+            let err =
+                execute(deps.as_mut(), mock_env(), mock_info("alice", &funds), msg).unwrap_err();
+            match expected {
+                "pair" => assert!(matches!(err, ContractError::InvalidTokenPair {})),
+                _ => assert!(matches!(err, ContractError::InvalidPositionAmount {})),
+            }
+        }
+
+        // Either order of the pair is accepted and sent sorted, the amounts
+        // follow their tokens
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[coin(500, "uarch"), coin(1000, "uusdc")]),
+            deposit(("uusdc", "uarch"), (1000, 500)),
+        )
+        .unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) = &res.messages[0].msg else {
+            panic!("expected a router call");
+        };
+        let request: AddLiquidityRequest = from_json::<crate::euclid::EuclidMsg>(msg)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            request.token_pair,
+            ("uarch".to_string(), "uusdc".to_string())
+        );
+        assert_eq!(request.amounts, (Uint128::new(500), Uint128::new(1000)));
+    }
+
+    #[test]
+    fn registry_gates_deposits() {
+        let mut deps = mock_dependencies();
+        mock_empty_pools(&mut deps);
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("uarch", "uusdc"));
+
+        // Only the admin manages the registry, and pools need known chains
+        let set_chain = |chain_id: &str, enabled| ExecuteMsg::SetChain {
+            chain_id: chain_id.to_string(),
+            display_name: chain_id.to_uppercase(),
+            enabled,
+        };
+        let set_pool = |chains: &[&str], status| ExecuteMsg::SetPool {
+            pool_id: "pool1".to_string(),
+            token_pair: ("uusdc".to_string(), "uarch".to_string()),
+            chains: chains.iter().map(|chain| chain.to_string()).collect(),
+            status,
+        };
+        let admin = mock_info("creator", &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            set_chain("osmosis", true),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            set_pool(&["archway", "neutron"], PoolStatus::Active),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::ChainNotSupported {}));
+
+        let deposit = |pool_id: &str, chain_id: &str| ExecuteMsg::AddLiquidity {
+            pool_id: pool_id.to_string(),
+            chain_id: chain_id.to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amounts: (Uint128::new(1000), Uint128::new(1000)),
+            min_shares: Uint128::zero(),
+            lock_duration: None,
+        };
+        let funds = [coin(1000, "uarch"), coin(1000, "uusdc")];
+        let try_deposit = |deps: DepsMut, pool_id: &str, chain_id: &str| {
+            execute(
+                deps,
+                mock_env(),
+                mock_info("alice", &funds),
+                deposit(pool_id, chain_id),
+            )
+        };
+        let err = try_deposit(deps.as_mut(), "pool9", "archway").unwrap_err();
+        assert!(matches!(err, ContractError::PoolNotFound {}));
+        let err = try_deposit(deps.as_mut(), "pool1", "osmosis").unwrap_err();
+        assert!(matches!(err, ContractError::ChainNotSupported {}));
+
+        // Disabling the chain or the pool closes it to deposits
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            set_chain("archway", false),
+        )
+        .unwrap();
+        let err = try_deposit(deps.as_mut(), "pool1", "archway").unwrap_err();
+        assert!(matches!(err, ContractError::ChainNotSupported {}));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            set_chain("archway", true),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            set_pool(&["archway"], PoolStatus::Disabled),
+        )
+        .unwrap();
+        let err = try_deposit(deps.as_mut(), "pool1", "archway").unwrap_err();
+        assert!(matches!(err, ContractError::PoolDisabled {}));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            admin.clone(),
+            set_pool(&["archway"], PoolStatus::Active),
+        )
+        .unwrap();
+        try_deposit(deps.as_mut(), "pool1", "archway").unwrap();
+
+        // The registry pages by id
+        for chain_id in ["neutron", "osmosis"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                admin.clone(),
+                set_chain(chain_id, true),
+            )
+            .unwrap();
+        }
+        let page: Vec<RegisteredChain> = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetChains {
+                    start_after: Some("archway".to_string()),
+                    limit: Some(1),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].chain_id, "neutron");
+        let pool: Option<RegisteredPool> = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetRegisteredPool {
+                    pool_id: "pool1".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            pool.unwrap().token_pair,
+            ("uarch".to_string(), "uusdc".to_string())
+        );
+    }
+
+    #[test]
+    fn cw20_deposits_fund_liquidity() {
+        let mut deps = mock_dependencies();
+        mock_empty_pools(&mut deps);
+        let msg = InstantiateMsg {
+            ul_nft_contract: "ul_nft".to_string(),
+            euclid_router: "router".to_string(),
+            euclid_factory: "factory".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        register_pool(deps.as_mut(), ("cw20token", "uarch"));
+
+        let receive = |msg: &ReceiveMsg| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "alice".to_string(),
+                amount: Uint128::new(1500),
+                msg: to_json_binary(msg).unwrap(),
+            })
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("cw20token", &[]),
+            receive(&ReceiveMsg::Deposit {}),
+        )
+        .unwrap();
+
+        // The cw20 side comes from the deposit, the native side is sent along
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &coins(1000, "uarch")),
+            ExecuteMsg::AddLiquidity {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                token_pair: ("cw20token".to_string(), "uarch".to_string()),
+                amounts: (Uint128::new(1000), Uint128::new(1000)),
+                min_shares: Uint128::zero(),
+                lock_duration: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "cw20token".to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "router".to_string(),
+                    amount: Uint128::new(1000),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+        let deposits = |deps: Deps| -> Vec<Cw20Coin> {
+            from_json(
+                query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::GetCw20Deposits {
+                        owner: "alice".to_string(),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(deposits(deps.as_ref())[0].amount, Uint128::new(500));
+
+        // A reverted call leaves the transferred side with the router, it is
+        // not credited back to be withdrawn a second time
+        #[allow(deprecated)]
+        let reply_msg = Reply {
+            id: res.messages[1].id,
+            result: SubMsgResult::Err("out of gas".to_string()),
+        };
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(deposits(deps.as_ref())[0].amount, Uint128::new(500));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::WithdrawCw20 {
+                token: "cw20token".to_string(),
+                amount: Some(Uint128::new(501)),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InsufficientFunds {}));
+
+        // A failed response returns the tokens, and with them the deposit
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &coins(500, "uarch")),
+            ExecuteMsg::AddLiquidity {
+                pool_id: "pool1".to_string(),
+                chain_id: "archway".to_string(),
+                token_pair: ("cw20token".to_string(), "uarch".to_string()),
+                amounts: (Uint128::new(500), Uint128::new(500)),
+                min_shares: Uint128::zero(),
+                lock_duration: None,
+            },
+        )
+        .unwrap();
+        assert!(deposits(deps.as_ref()).is_empty());
+        let response = EuclidResponse::failure("pool is paused");
+        #[allow(deprecated)]
+        let reply_msg = Reply {
+            id: res.messages[1].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(execute_response_data(&response)),
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), reply_msg).unwrap();
+        assert_eq!(deposits(deps.as_ref())[0].amount, Uint128::new(500));
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::WithdrawCw20 {
+                token: "cw20token".to_string(),
+                amount: Some(Uint128::new(501)),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InsufficientFunds {}));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::WithdrawCw20 {
+                token: "cw20token".to_string(),
+                amount: None,
+            },
+        )
+        .unwrap();
+        assert!(deposits(deps.as_ref()).is_empty());
+    }
 }
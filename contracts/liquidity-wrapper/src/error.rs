@@ -1,11 +1,18 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError};
+use cw_utils::PaymentError;
 use thiserror::Error;
 
+use crate::euclid::EuclidReplyError;
+use crate::state::PausableAction;
+
 #[derive(Error, Debug)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Reply(#[from] EuclidReplyError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -27,12 +34,30 @@ pub enum ContractError {
     #[error("Invalid token pair")]
     InvalidTokenPair {},
 
+    #[error("Deposit does not match the pool's ratio")]
+    InvalidDepositRatio {},
+
+    #[error("Deposit ratio tolerance must be at most 1")]
+    InvalidDepositRatioTolerance {},
+
     #[error("Chain not supported")]
     ChainNotSupported {},
 
+    #[error("Pool disabled")]
+    PoolDisabled {},
+
+    #[error("Pool still backs positions")]
+    PoolInUse {},
+
+    #[error("Chain still hosts positions")]
+    ChainInUse {},
+
     #[error("Operation pending")]
     OperationPending {},
 
+    #[error("Pending operation {id} not found")]
+    OperationNotFound { id: u64 },
+
     #[error("Cross chain operation failed")]
     CrossChainOperationFailed {},
 
@@ -41,4 +66,58 @@ pub enum ContractError {
 
     #[error("Position locked")]
     PositionLocked {},
+
+    #[error("{action:?} is paused")]
+    Paused { action: PausableAction },
+
+    #[error("RemoveLiquidity is not paused")]
+    WithdrawalsNotPaused {},
+
+    #[error("Position is time-locked until {unlocks_at}")]
+    PositionTimeLocked { unlocks_at: u64 },
+
+    #[error("No lock tier offers this duration")]
+    InvalidLockDuration {},
+
+    #[error("Lock multipliers must be at least 1 and the penalty at most 1")]
+    InvalidLockTiers {},
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("Invalid swap route")]
+    InvalidSwapRoute {},
+
+    #[error("Deadline exceeded")]
+    DeadlineExceeded {},
+
+    #[error("Slippage limit exceeded")]
+    SlippageExceeded {},
+
+    #[error("Max slippage must be at most 1")]
+    InvalidSlippage {},
+
+    #[error("No price oracle configured")]
+    NoPriceOracle {},
+
+    #[error("Fee limit exceeded")]
+    FeeExceeded {},
+
+    #[error("Insufficient funds")]
+    InsufficientFunds {},
+
+    #[error("Transfer has not timed out")]
+    TransferNotTimedOut {},
+
+    #[error("Auto-compounding is not enabled for this position")]
+    AutoCompoundDisabled {},
+
+    #[error("Compound bounty above the maximum of {max}")]
+    InvalidCompoundBounty { max: Decimal },
+
+    #[error("Compound failed: {error}")]
+    CompoundFailed { error: String },
+
+    #[error("Zap failed: {error}")]
+    ZapFailed { error: String },
 }
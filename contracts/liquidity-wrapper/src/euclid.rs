@@ -0,0 +1,852 @@
+//! Typed client for the Euclid router and factory.
+//!
+//! Every router call is an `EuclidMsg` whose `data` is one of the request
+//! structs below, and every reply carries an `EuclidResponse` whose `data` is
+//! the matching response struct. Build messages through `EuclidRouter` rather
+//! than by hand so field names are checked at compile time.
+//!
+//! The router may also accept a call and complete it later: it then replies
+//! with `EuclidResponse::accepted()` and eventually sends the caller an
+//! `EuclidCallbackMsg` carrying the `tx_id` of the original message.
+//!
+//! The router reports its `EuclidResponse` in its `wasm` event, using the
+//! attributes named by the `ATTR_*` constants (see `to_attributes`), and may
+//! also set it as the execute data. `parse_router_response` reads the event
+//! emitted by the router's own address first and only falls back to the data.
+//!
+//! The factory only answers queries, see `EuclidFactory`.
+
+use cosmwasm_std::{
+    attr, from_json, to_json_binary, Addr, Attribute, Binary, Coin, Decimal, QuerierWrapper,
+    StdError, StdResult, SubMsgResponse, Uint128, WasmMsg,
+};
+use cw20::Cw20Coin;
+use cw_utils::parse_execute_response_data;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// Attributes of the router's `wasm` event carrying its response
+pub const ATTR_ACTION: &str = "euclid_action";
+pub const ATTR_SUCCESS: &str = "euclid_success";
+// Base64 encoded response payload, absent for failed and accepted calls
+pub const ATTR_DATA: &str = "euclid_data";
+pub const ATTR_ERROR: &str = "euclid_error";
+
+// Set by the chain on every wasm event
+const ATTR_CONTRACT_ADDRESS: &str = "_contract_address";
+
+// Message for communication with Euclid Protocol
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EuclidMsg {
+    pub action: EuclidAction,
+    pub data: Binary,
+    // Caller reference, echoed back when the call completes asynchronously
+    #[serde(default)]
+    pub tx_id: Option<u64>,
+}
+
+impl EuclidMsg {
+    pub fn new<T: EuclidRequest>(request: &T) -> StdResult<Self> {
+        Ok(EuclidMsg {
+            action: T::ACTION,
+            data: to_json_binary(request)?,
+            tx_id: None,
+        })
+    }
+
+    pub fn with_tx_id(mut self, tx_id: u64) -> Self {
+        self.tx_id = Some(tx_id);
+        self
+    }
+
+    // Decodes the payload, checking it was sent for the expected action
+    pub fn parse<T: EuclidRequest>(&self) -> StdResult<T> {
+        if self.action != T::ACTION {
+            return Err(StdError::generic_err(format!(
+                "Expected {:?} payload, got {:?}",
+                T::ACTION,
+                self.action
+            )));
+        }
+        from_json(&self.data)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EuclidAction {
+    AddLiquidity,
+    RemoveLiquidity,
+    SwapExactIn,
+    SwapExactOut,
+    TransferLiquidity,
+    ClaimRewards,
+}
+
+impl EuclidAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EuclidAction::AddLiquidity => "add_liquidity",
+            EuclidAction::RemoveLiquidity => "remove_liquidity",
+            EuclidAction::SwapExactIn => "swap_exact_in",
+            EuclidAction::SwapExactOut => "swap_exact_out",
+            EuclidAction::TransferLiquidity => "transfer_liquidity",
+            EuclidAction::ClaimRewards => "claim_rewards",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EuclidResponse {
+    pub success: bool,
+    pub data: Binary,
+    pub error: Option<String>,
+}
+
+impl EuclidResponse {
+    pub fn ok<T: Serialize>(data: &T) -> StdResult<Self> {
+        Ok(EuclidResponse {
+            success: true,
+            data: to_json_binary(data)?,
+            error: None,
+        })
+    }
+
+    pub fn failure(error: impl Into<String>) -> Self {
+        EuclidResponse {
+            success: false,
+            data: Binary::default(),
+            error: Some(error.into()),
+        }
+    }
+
+    // The call was accepted and will complete through a callback
+    pub fn accepted() -> Self {
+        EuclidResponse {
+            success: true,
+            data: Binary::default(),
+            error: None,
+        }
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.success && self.data.is_empty()
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self) -> StdResult<T> {
+        from_json(&self.data)
+    }
+
+    // Attributes the router adds to its event to report this response
+    pub fn to_attributes(&self, action: &EuclidAction) -> Vec<Attribute> {
+        let mut attributes = vec![
+            attr(ATTR_ACTION, action.as_str()),
+            attr(ATTR_SUCCESS, self.success.to_string()),
+        ];
+        if !self.data.is_empty() {
+            attributes.push(attr(ATTR_DATA, self.data.to_base64()));
+        }
+        if let Some(error) = &self.error {
+            attributes.push(attr(ATTR_ERROR, error));
+        }
+        attributes
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EuclidReplyError {
+    #[error("Router reply carries neither a router event nor data")]
+    MissingResponse {},
+
+    #[error("Router event is missing the {key} attribute")]
+    MissingAttribute { key: String },
+
+    #[error("Router event has an invalid {key} attribute: {value}")]
+    InvalidAttribute { key: String, value: String },
+
+    #[error("Router replied for {actual} instead of {expected}")]
+    UnexpectedAction { expected: String, actual: String },
+
+    #[error("Invalid router reply data: {msg}")]
+    InvalidData { msg: String },
+}
+
+/// Reads the router's answer to a call for `action` out of a successful
+/// submessage response
+pub fn parse_router_response(
+    router: &Addr,
+    action: &EuclidAction,
+    response: &SubMsgResponse,
+) -> Result<EuclidResponse, EuclidReplyError> {
+    // Only the router's own event counts, whatever other contracts emitted
+    let router_event = response.events.iter().find(|event| {
+        event.ty.starts_with("wasm")
+            && event
+                .attributes
+                .iter()
+                .any(|a| a.key == ATTR_CONTRACT_ADDRESS && a.value == router.as_str())
+            && event.attributes.iter().any(|a| a.key == ATTR_SUCCESS)
+    });
+
+    match router_event {
+        Some(event) => {
+            let find = |key: &str| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|a| a.key == key)
+                    .map(|a| a.value.clone())
+            };
+            let required = |key: &str| {
+                find(key).ok_or_else(|| EuclidReplyError::MissingAttribute {
+                    key: key.to_string(),
+                })
+            };
+
+            let actual = required(ATTR_ACTION)?;
+            if actual != action.as_str() {
+                return Err(EuclidReplyError::UnexpectedAction {
+                    expected: action.as_str().to_string(),
+                    actual,
+                });
+            }
+
+            let success = required(ATTR_SUCCESS)?;
+            let success =
+                success
+                    .parse::<bool>()
+                    .map_err(|_| EuclidReplyError::InvalidAttribute {
+                        key: ATTR_SUCCESS.to_string(),
+                        value: success,
+                    })?;
+
+            let data = match find(ATTR_DATA) {
+                Some(value) => {
+                    Binary::from_base64(&value).map_err(|_| EuclidReplyError::InvalidAttribute {
+                        key: ATTR_DATA.to_string(),
+                        value,
+                    })?
+                }
+                None => Binary::default(),
+            };
+
+            let error = find(ATTR_ERROR);
+            if !success && error.is_none() {
+                return Err(EuclidReplyError::MissingAttribute {
+                    key: ATTR_ERROR.to_string(),
+                });
+            }
+
+            Ok(EuclidResponse {
+                success,
+                data,
+                error,
+            })
+        }
+        None => {
+            // The chain wraps the router's data in a MsgExecuteContractResponse
+            #[allow(deprecated)]
+            let data = response
+                .data
+                .as_ref()
+                .ok_or(EuclidReplyError::MissingResponse {})?;
+            let data = parse_execute_response_data(data)
+                .map_err(|err| EuclidReplyError::InvalidData {
+                    msg: err.to_string(),
+                })?
+                .data
+                .ok_or(EuclidReplyError::MissingResponse {})?;
+            from_json(&data).map_err(|err| EuclidReplyError::InvalidData {
+                msg: err.to_string(),
+            })
+        }
+    }
+}
+
+/// Sent by the router to the caller when an accepted call completes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EuclidCallbackMsg {
+    EuclidCallback {
+        tx_id: u64,
+        response: EuclidResponse,
+    },
+}
+
+/// Implemented by every router request payload
+pub trait EuclidRequest: Serialize + DeserializeOwned {
+    const ACTION: EuclidAction;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AddLiquidityRequest {
+    pub pool_id: String,
+    pub chain_id: String,
+    pub token_pair: (String, String),
+    // Tokens of each side of `token_pair` deposited
+    pub amounts: (Uint128, Uint128),
+    pub min_shares: Uint128,
+    // Cw20 sides of the pair, transferred to the router ahead of this call
+    #[serde(default)]
+    pub cw20_assets: Vec<Cw20Coin>,
+    // Position the liquidity is credited to, new or existing
+    pub token_id: String,
+}
+
+impl EuclidRequest for AddLiquidityRequest {
+    const ACTION: EuclidAction = EuclidAction::AddLiquidity;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AddLiquidityResponse {
+    pub pool_id: String,
+    pub chain_id: String,
+    pub token_pair: (String, String),
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RemoveLiquidityRequest {
+    pub token_id: String,
+    pub pool_id: String,
+    pub amount: Uint128,
+    // Receives the withdrawn tokens
+    pub recipient: String,
+    // Unix time in seconds after which the withdrawal must not execute
+    pub deadline: Option<u64>,
+}
+
+impl EuclidRequest for RemoveLiquidityRequest {
+    const ACTION: EuclidAction = EuclidAction::RemoveLiquidity;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RemoveLiquidityResponse {
+    pub token_id: String,
+    pub amount: Uint128,
+    // Tokens paid out to the recipient
+    pub assets: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapExactInRequest {
+    // Denoms from the offered token to the asked token, at least two hops
+    pub route: Vec<String>,
+    pub amount_in: Uint128,
+    pub min_amount_out: Uint128,
+    // Unix time in seconds after which the swap must not execute
+    pub deadline: Option<u64>,
+}
+
+impl EuclidRequest for SwapExactInRequest {
+    const ACTION: EuclidAction = EuclidAction::SwapExactIn;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapExactOutRequest {
+    // Denoms from the offered token to the asked token, at least two hops
+    pub route: Vec<String>,
+    pub amount_out: Uint128,
+    pub max_amount_in: Uint128,
+    // Unix time in seconds after which the swap must not execute
+    pub deadline: Option<u64>,
+}
+
+impl EuclidRequest for SwapExactOutRequest {
+    const ACTION: EuclidAction = EuclidAction::SwapExactOut;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapResponse {
+    pub amount_in: Uint128,
+    pub amount_out: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferLiquidityRequest {
+    pub token_id: String,
+    pub from_chain_id: String,
+    pub to_chain_id: String,
+    pub amount: Uint128,
+    // Unix time in seconds after which the move must not execute
+    pub deadline: Option<u64>,
+}
+
+impl EuclidRequest for TransferLiquidityRequest {
+    const ACTION: EuclidAction = EuclidAction::TransferLiquidity;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferLiquidityResponse {
+    pub token_id: String,
+    pub new_chain_id: String,
+    // Liquidity that arrived on the destination chain
+    pub amount: Uint128,
+    // Charged by the router for the move
+    pub fee: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimRewardsRequest {
+    pub token_id: String,
+    pub pool_id: String,
+    pub amount: Uint128,
+}
+
+impl EuclidRequest for ClaimRewardsRequest {
+    const ACTION: EuclidAction = EuclidAction::ClaimRewards;
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimRewardsResponse {
+    pub token_id: String,
+    pub rewards: Vec<Coin>,
+}
+
+/// Queries answered by the router
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EuclidQueryMsg {
+    // Rewards earned by `amount` of liquidity in a pool over `duration` seconds
+    EstimateRewards {
+        pool_id: String,
+        amount: Uint128,
+        duration: u64,
+    },
+    // Tokens and reserves of a pool
+    Pool {
+        pool_id: String,
+    },
+    // Liquidity the router holds for a position, null when it holds none
+    Position {
+        token_id: String,
+    },
+    // Shares an `AddLiquidityRequest` of `amounts`, ordered like the pool's
+    // pair, would mint
+    SimulateAddLiquidity {
+        pool_id: String,
+        amounts: (Uint128, Uint128),
+    },
+    // Tokens a `RemoveLiquidityRequest` of `amount` shares would pay out
+    SimulateRemoveLiquidity {
+        pool_id: String,
+        amount: Uint128,
+    },
+    // Output a `SwapExactInRequest` of `amount_in` along `route` would get
+    SimulateSwap {
+        route: Vec<String>,
+        amount_in: Uint128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EstimateRewardsResponse {
+    pub rewards: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PositionResponse {
+    pub token_id: String,
+    pub pool_id: String,
+    pub chain_id: String,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolResponse {
+    pub pool_id: String,
+    pub token_pair: (String, String),
+    pub reserves: (Uint128, Uint128),
+    pub total_shares: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateAddLiquidityResponse {
+    pub shares: Uint128,
+    // Share of the deposit left unbalanced by the pool's current price
+    pub price_impact: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateRemoveLiquidityResponse {
+    pub assets: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SimulateSwapResponse {
+    pub amount_out: Uint128,
+}
+
+/// Queries answered by the factory
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EuclidFactoryQueryMsg {
+    // Pools ordered by id
+    Pools {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    PoolInfo {
+        pool_id: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolInfoResponse {
+    pub pool_id: String,
+    pub token_pair: (String, String),
+    pub reserves: (Uint128, Uint128),
+    pub total_shares: Uint128,
+    // Fee charged on swaps through the pool
+    pub fee_tier: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolsResponse {
+    pub pools: Vec<PoolInfoResponse>,
+}
+
+/// Handle on a deployed router, builds its messages and runs its queries
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EuclidRouter(pub Addr);
+
+impl EuclidRouter {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn execute<T: EuclidRequest>(&self, request: &T, funds: Vec<Coin>) -> StdResult<WasmMsg> {
+        self.execute_msg(EuclidMsg::new(request)?, funds)
+    }
+
+    // Like `execute`, tagging the call so an asynchronous completion can be
+    // matched back to it
+    pub fn execute_tracked<T: EuclidRequest>(
+        &self,
+        request: &T,
+        tx_id: u64,
+        funds: Vec<Coin>,
+    ) -> StdResult<WasmMsg> {
+        self.execute_msg(EuclidMsg::new(request)?.with_tx_id(tx_id), funds)
+    }
+
+    fn execute_msg(&self, msg: EuclidMsg, funds: Vec<Coin>) -> StdResult<WasmMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds,
+        })
+    }
+
+    pub fn estimate_rewards(
+        &self,
+        querier: &QuerierWrapper,
+        pool_id: impl Into<String>,
+        amount: Uint128,
+        duration: u64,
+    ) -> StdResult<Uint128> {
+        let response: EstimateRewardsResponse = querier.query_wasm_smart(
+            &self.0,
+            &EuclidQueryMsg::EstimateRewards {
+                pool_id: pool_id.into(),
+                amount,
+                duration,
+            },
+        )?;
+        Ok(response.rewards)
+    }
+
+    pub fn position(
+        &self,
+        querier: &QuerierWrapper,
+        token_id: impl Into<String>,
+    ) -> StdResult<Option<PositionResponse>> {
+        querier.query_wasm_smart(
+            &self.0,
+            &EuclidQueryMsg::Position {
+                token_id: token_id.into(),
+            },
+        )
+    }
+
+    pub fn pool(
+        &self,
+        querier: &QuerierWrapper,
+        pool_id: impl Into<String>,
+    ) -> StdResult<PoolResponse> {
+        querier.query_wasm_smart(
+            &self.0,
+            &EuclidQueryMsg::Pool {
+                pool_id: pool_id.into(),
+            },
+        )
+    }
+
+    pub fn simulate_add_liquidity(
+        &self,
+        querier: &QuerierWrapper,
+        pool_id: impl Into<String>,
+        amounts: (Uint128, Uint128),
+    ) -> StdResult<SimulateAddLiquidityResponse> {
+        querier.query_wasm_smart(
+            &self.0,
+            &EuclidQueryMsg::SimulateAddLiquidity {
+                pool_id: pool_id.into(),
+                amounts,
+            },
+        )
+    }
+
+    pub fn simulate_swap(
+        &self,
+        querier: &QuerierWrapper,
+        route: Vec<String>,
+        amount_in: Uint128,
+    ) -> StdResult<SimulateSwapResponse> {
+        querier.query_wasm_smart(&self.0, &EuclidQueryMsg::SimulateSwap { route, amount_in })
+    }
+
+    pub fn simulate_remove_liquidity(
+        &self,
+        querier: &QuerierWrapper,
+        pool_id: impl Into<String>,
+        amount: Uint128,
+    ) -> StdResult<SimulateRemoveLiquidityResponse> {
+        querier.query_wasm_smart(
+            &self.0,
+            &EuclidQueryMsg::SimulateRemoveLiquidity {
+                pool_id: pool_id.into(),
+                amount,
+            },
+        )
+    }
+}
+
+/// Handle on a deployed factory, runs its queries
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EuclidFactory(pub Addr);
+
+impl EuclidFactory {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn pools(
+        &self,
+        querier: &QuerierWrapper,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<PoolsResponse> {
+        querier.query_wasm_smart(
+            &self.0,
+            &EuclidFactoryQueryMsg::Pools { start_after, limit },
+        )
+    }
+
+    pub fn pool_info(
+        &self,
+        querier: &QuerierWrapper,
+        pool_id: impl Into<String>,
+    ) -> StdResult<PoolInfoResponse> {
+        querier.query_wasm_smart(
+            &self.0,
+            &EuclidFactoryQueryMsg::PoolInfo {
+                pool_id: pool_id.into(),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::Event;
+
+    const ROUTER: &str = "router";
+
+    fn router_event(response: &EuclidResponse, action: &EuclidAction) -> Event {
+        Event::new("wasm")
+            .add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER)
+            .add_attributes(response.to_attributes(action))
+    }
+
+    fn events_only(events: Vec<Event>) -> SubMsgResponse {
+        #[allow(deprecated)]
+        SubMsgResponse { events, data: None }
+    }
+
+    fn parse(
+        action: EuclidAction,
+        response: &SubMsgResponse,
+    ) -> Result<EuclidResponse, EuclidReplyError> {
+        parse_router_response(&Addr::unchecked(ROUTER), &action, response)
+    }
+
+    #[test]
+    fn parses_router_events_for_each_action() {
+        let cases = vec![
+            (
+                EuclidAction::AddLiquidity,
+                EuclidResponse::ok(&AddLiquidityResponse {
+                    pool_id: "pool1".to_string(),
+                    chain_id: "archway".to_string(),
+                    token_pair: ("uarch".to_string(), "uusdc".to_string()),
+                    amount: Uint128::new(1000),
+                })
+                .unwrap(),
+            ),
+            (
+                EuclidAction::RemoveLiquidity,
+                EuclidResponse::ok(&RemoveLiquidityResponse {
+                    token_id: "ulp-1".to_string(),
+                    amount: Uint128::new(400),
+                    assets: vec![],
+                })
+                .unwrap(),
+            ),
+            (
+                EuclidAction::TransferLiquidity,
+                EuclidResponse::ok(&TransferLiquidityResponse {
+                    token_id: "ulp-1".to_string(),
+                    new_chain_id: "osmosis".to_string(),
+                    amount: Uint128::new(1000),
+                    fee: vec![],
+                })
+                .unwrap(),
+            ),
+            (
+                EuclidAction::ClaimRewards,
+                EuclidResponse::ok(&ClaimRewardsResponse {
+                    token_id: "ulp-1".to_string(),
+                    rewards: vec![Coin::new(10, "uarch")],
+                })
+                .unwrap(),
+            ),
+            (
+                EuclidAction::SwapExactIn,
+                EuclidResponse::ok(&SwapResponse {
+                    amount_in: Uint128::new(100),
+                    amount_out: Uint128::new(95),
+                })
+                .unwrap(),
+            ),
+            (EuclidAction::TransferLiquidity, EuclidResponse::accepted()),
+            (
+                EuclidAction::AddLiquidity,
+                EuclidResponse::failure("pool is paused"),
+            ),
+        ];
+
+        for (action, expected) in cases {
+            // Other contracts' events around the router's are ignored
+            let foreign = Event::new("wasm")
+                .add_attribute(ATTR_CONTRACT_ADDRESS, "other")
+                .add_attributes(EuclidResponse::failure("spoofed").to_attributes(&action));
+            let response = events_only(vec![
+                Event::new("execute").add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER),
+                foreign,
+                router_event(&expected, &action),
+            ]);
+            assert_eq!(parse(action, &response).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_execute_data() {
+        let expected = EuclidResponse::ok(&RemoveLiquidityResponse {
+            token_id: "ulp-1".to_string(),
+            amount: Uint128::new(400),
+            assets: vec![],
+        })
+        .unwrap();
+        let json = to_json_binary(&expected).unwrap();
+        // MsgExecuteContractResponse { data }
+        let mut data = vec![0x0a, json.len() as u8];
+        data.extend_from_slice(&json);
+
+        #[allow(deprecated)]
+        let response = SubMsgResponse {
+            events: vec![Event::new("execute").add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER)],
+            data: Some(Binary::from(data)),
+        };
+        assert_eq!(
+            parse(EuclidAction::RemoveLiquidity, &response).unwrap(),
+            expected
+        );
+
+        #[allow(deprecated)]
+        let garbage = SubMsgResponse {
+            events: vec![],
+            data: Some(Binary::from(b"not protobuf".to_vec())),
+        };
+        assert!(matches!(
+            parse(EuclidAction::RemoveLiquidity, &garbage),
+            Err(EuclidReplyError::InvalidData { .. })
+        ));
+    }
+
+    #[test]
+    fn reports_missing_and_invalid_fields() {
+        assert_eq!(
+            parse(EuclidAction::AddLiquidity, &events_only(vec![])),
+            Err(EuclidReplyError::MissingResponse {})
+        );
+
+        let no_action = Event::new("wasm")
+            .add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER)
+            .add_attribute(ATTR_SUCCESS, "true");
+        assert_eq!(
+            parse(EuclidAction::AddLiquidity, &events_only(vec![no_action])),
+            Err(EuclidReplyError::MissingAttribute {
+                key: ATTR_ACTION.to_string()
+            })
+        );
+
+        let wrong_action = router_event(&EuclidResponse::accepted(), &EuclidAction::ClaimRewards);
+        assert_eq!(
+            parse(EuclidAction::AddLiquidity, &events_only(vec![wrong_action])),
+            Err(EuclidReplyError::UnexpectedAction {
+                expected: "add_liquidity".to_string(),
+                actual: "claim_rewards".to_string(),
+            })
+        );
+
+        let bad_success = Event::new("wasm")
+            .add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER)
+            .add_attribute(ATTR_ACTION, "add_liquidity")
+            .add_attribute(ATTR_SUCCESS, "yes");
+        assert_eq!(
+            parse(EuclidAction::AddLiquidity, &events_only(vec![bad_success])),
+            Err(EuclidReplyError::InvalidAttribute {
+                key: ATTR_SUCCESS.to_string(),
+                value: "yes".to_string(),
+            })
+        );
+
+        let bad_data = Event::new("wasm")
+            .add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER)
+            .add_attribute(ATTR_ACTION, "add_liquidity")
+            .add_attribute(ATTR_SUCCESS, "true")
+            .add_attribute(ATTR_DATA, "%%%");
+        assert!(matches!(
+            parse(EuclidAction::AddLiquidity, &events_only(vec![bad_data])),
+            Err(EuclidReplyError::InvalidAttribute { .. })
+        ));
+
+        let failure_without_error = Event::new("wasm")
+            .add_attribute(ATTR_CONTRACT_ADDRESS, ROUTER)
+            .add_attribute(ATTR_ACTION, "add_liquidity")
+            .add_attribute(ATTR_SUCCESS, "false");
+        assert_eq!(
+            parse(
+                EuclidAction::AddLiquidity,
+                &events_only(vec![failure_without_error])
+            ),
+            Err(EuclidReplyError::MissingAttribute {
+                key: ATTR_ERROR.to_string()
+            })
+        );
+    }
+}
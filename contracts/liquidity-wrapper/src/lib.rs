@@ -1,6 +1,8 @@
 pub mod contract;
 pub mod error;
+pub mod euclid;
 pub mod msg;
+pub mod oracle;
 pub mod state;
 
 pub use crate::error::ContractError;
@@ -1,7 +1,12 @@
-use cosmwasm_std::{Binary, Uint128};
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::state::{LockTier, PausableAction, PoolStatus, TimeLock, Tvl};
+
+pub use crate::euclid::{EuclidAction, EuclidMsg, EuclidResponse};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub ul_nft_contract: String,
@@ -12,33 +17,230 @@ pub struct InstantiateMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    // Add liquidity to Euclid pool
+    // Add liquidity to Euclid pool. Native sides of the pair are sent along,
+    // cw20 sides are taken from the sender's cw20 deposits. `amounts` follow
+    // the order of `token_pair` and must match the pool's ratio.
     AddLiquidity {
         pool_id: String,
         chain_id: String,
         token_pair: (String, String),
-        amount: Uint128,
+        amounts: (Uint128, Uint128),
         min_shares: Uint128,
+        // Lock the minted position for this many seconds
+        lock_duration: Option<u64>,
     },
     // Remove liquidity from Euclid pool
     RemoveLiquidity {
         token_id: String,
         amount: Option<Uint128>, // If None, removes all liquidity
+        // Least of each token the withdrawal must pay out
+        #[serde(default)]
+        min_amounts_out: Vec<Coin>,
+        // Unix time in seconds after which the withdrawal fails
+        deadline: Option<u64>,
     },
     // Transfer position between chains
     TransferPosition {
         token_id: String,
         to_chain_id: String,
+        // Least liquidity that must arrive on the destination chain
+        min_amount_out: Option<Uint128>,
+        // Most the router may charge for the move
+        max_fee: Option<Coin>,
+        deadline: Option<u64>,
+    },
+    // Give up on a cross-chain move the router did not finish within the
+    // transfer timeout, unlocking the position on its original chain
+    RecoverTimedOutTransfer {
+        token_id: String,
+    },
+    // Register or update a chain (admin only)
+    SetChain {
+        chain_id: String,
+        display_name: String,
+        enabled: bool,
+    },
+    // Unregister a chain (admin only)
+    RemoveChain {
+        chain_id: String,
+    },
+    // Register or update a pool, its chains must be registered (admin only)
+    SetPool {
+        pool_id: String,
+        token_pair: (String, String),
+        chains: Vec<String>,
+        status: PoolStatus,
+    },
+    // Unregister a pool (admin only)
+    RemovePool {
+        pool_id: String,
+    },
+    // Set or clear the guardian that may pause actions (admin only)
+    SetGuardian {
+        guardian: Option<String>,
+    },
+    // Refuse new `actions` until unpaused (admin or guardian)
+    Pause {
+        actions: Vec<PausableAction>,
+    },
+    // Accept `actions` again (admin only)
+    Unpause {
+        actions: Vec<PausableAction>,
+    },
+    // Withdraw all of a position while RemoveLiquidity is paused. Skips the
+    // pool registry, a time-locked position still pays the early exit penalty
+    EmergencyWithdraw {
+        token_id: String,
+    },
+    // Set the transfer timeout in seconds (admin only)
+    SetTransferTimeout {
+        timeout: u64,
+    },
+    // Set the price oracle contract, or value from pool reserves when none,
+    // and the denom portfolios are valued in (admin only)
+    SetPriceOracle {
+        oracle: Option<String>,
+        quote_denom: String,
+    },
+    // Set the lock durations on offer with their reward multipliers, and the
+    // share forfeited by early exits, refused when None (admin only). Paying
+    // the penalty ends the lock.
+    SetLockTiers {
+        tiers: Vec<LockTier>,
+        early_exit_penalty: Option<Decimal>,
+    },
+    // Lock a position for `duration` seconds from now, or extend its lock
+    LockPosition {
+        token_id: String,
+        duration: u64,
+    },
+    // Add to the funds paying reward boosts of locked positions
+    FundBoostReserve {},
+    // Add liquidity to an existing position, funded like AddLiquidity
+    IncreaseLiquidity {
+        token_id: String,
+        // Ordered like the position's token pair
+        amounts: (Uint128, Uint128),
+        min_shares: Uint128,
+    },
+    // Withdraw part of a position, keeping the UL-NFT
+    DecreaseLiquidity {
+        token_id: String,
+        amount: Uint128,
+        #[serde(default)]
+        min_amounts_out: Vec<Coin>,
+        deadline: Option<u64>,
     },
-    // Update position (rebalance or adjust)
-    UpdatePosition {
+    // Reconcile a position with the router's record of it (admin only)
+    SyncPosition {
         token_id: String,
-        new_amount: Uint128,
+    },
+    // Rebuild the TVL totals from the positions, `limit` at a time, call
+    // again until the response reports `done` (admin only)
+    Reconcile {
+        limit: Option<u32>,
+    },
+    // Swap all of the sent tokens along `route`, from route[0] to the last denom
+    SwapExactIn {
+        route: Vec<String>,
+        min_amount_out: Uint128,
+        // Unix time in seconds after which the swap fails
+        deadline: Option<u64>,
+    },
+    // Swap for exactly `amount_out` of the last denom of `route`, spending at
+    // most `max_amount_in` of the sent tokens, the rest is refunded
+    SwapExactOut {
+        route: Vec<String>,
+        amount_out: Uint128,
+        max_amount_in: Uint128,
+        deadline: Option<u64>,
+    },
+    // Deposit a single token: about half of it is swapped to the other token
+    // of the pool, then both are added and a UL-NFT is minted
+    ZapIn {
+        pool_id: String,
+        chain_id: String,
+        input_denom: String,
+        min_shares: Uint128,
+        // Shortfall from the oracle price the swap accepts, the configured
+        // `max_slippage` when None. Without an oracle only `min_shares`
+        // bounds the zap
+        max_slippage: Option<Decimal>,
+        deadline: Option<u64>,
+    },
+    // Withdraw liquidity and swap all of it to `output_denom`
+    ZapOut {
+        token_id: String,
+        amount: Option<Uint128>, // If None, removes all liquidity
+        output_denom: String,
+        min_amount_out: Uint128,
+        deadline: Option<u64>,
     },
     // Claim rewards from liquidity provision
     ClaimRewards {
         token_id: String,
     },
+    // Let keepers reinvest the position's rewards into it
+    SetAutoCompound {
+        token_id: String,
+        enabled: bool,
+    },
+    // Claim the rewards of an auto-compounding position and add them back to
+    // it, anyone may call this and earns the compound bounty. Needs a price
+    // oracle to bound the swaps
+    Compound {
+        token_id: String,
+    },
+    // Set the share of compounded rewards paid to keepers (admin only)
+    SetCompoundBounty {
+        bounty: Decimal,
+    },
+    // Set the default shortfall from the oracle price that swaps made on a
+    // user's behalf accept (admin only)
+    SetMaxSlippage {
+        max_slippage: Decimal,
+    },
+    // Set how far a deposit may stray from the pool's ratio (admin only)
+    SetDepositRatioTolerance {
+        tolerance: Decimal,
+    },
+    // Cw20 tokens sent to this contract with a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+    // Withdraw unused cw20 deposits, all of them if amount is None
+    WithdrawCw20 {
+        token: String,
+        amount: Option<Uint128>,
+    },
+    // Sync position ownership after a UL-NFT transfer (UL-NFT transfer hook),
+    // refused while the position has an operation in flight
+    NftTransferred {
+        token_id: String,
+        previous_owner: String,
+        new_owner: String,
+    },
+    // Completion of an operation the router accepted earlier (router only)
+    EuclidCallback {
+        tx_id: u64,
+        response: EuclidResponse,
+    },
+}
+
+// Messages carried by a cw20 `Send` to this contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    // Credit the tokens to the sender's cw20 deposits
+    Deposit {},
+    // Credit the tokens, then add liquidity as `ExecuteMsg::AddLiquidity`
+    AddLiquidity {
+        pool_id: String,
+        chain_id: String,
+        token_pair: (String, String),
+        amounts: (Uint128, Uint128),
+        min_shares: Uint128,
+        // Lock the minted position for this many seconds
+        lock_duration: Option<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -47,38 +249,183 @@ pub enum QueryMsg {
     // Get config information
     GetConfig {},
     // Get position information by token ID
-    GetPosition { token_id: String },
-    // Get all positions for a pool
-    GetPoolPositions { pool_id: String },
-    // Get pending operations
-    GetPendingOperations { token_id: String },
+    GetPosition {
+        token_id: String,
+    },
+    // Get the positions of an owner, ordered by token ID
+    GetPositionsByOwner {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Get the positions in a pool, ordered by token ID
+    GetPoolPositions {
+        pool_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Get the positions living on a chain, ordered by token ID
+    GetChainPositions {
+        chain_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Get a pending operation by id
+    GetPendingOperation {
+        id: u64,
+    },
+    // Get pending operations, optionally only those of one user
+    GetPendingOperations {
+        user: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // Get failed operations, optionally only those of one user
+    GetFailedOperations {
+        user: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // Get the unused cw20 deposits of a user
+    GetCw20Deposits {
+        owner: String,
+    },
     // Get total liquidity in a pool
-    GetPoolLiquidity { pool_id: String },
-    // Estimate rewards for a position
-    EstimateRewards { token_id: String },
+    GetPoolLiquidity {
+        pool_id: String,
+    },
+    // Get the liquidity, positions and depositors of a pool
+    GetPoolTvl {
+        pool_id: String,
+    },
+    // Get the liquidity, positions and depositors on a chain
+    GetChainTvl {
+        chain_id: String,
+    },
+    // Get the protocol-wide liquidity, positions and depositors
+    GetTvl {},
+    // Get the paused actions
+    GetPauseState {},
+    // Value a position's share of its pool in `quote_denom`
+    GetPositionValue {
+        token_id: String,
+        quote_denom: String,
+    },
+    // Value every position of an owner in the configured quote denom
+    GetPortfolioValue {
+        owner: String,
+    },
+    // Estimate rewards for a position since its last claim
+    EstimateRewards {
+        token_id: String,
+    },
+    // Get the rewards ledger of a position
+    GetRewards {
+        token_id: String,
+    },
+    // Get a registered chain
+    GetChain {
+        chain_id: String,
+    },
+    // Get registered chains, ordered by id
+    GetChains {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Get a registered pool
+    GetRegisteredPool {
+        pool_id: String,
+    },
+    // Get registered pools, ordered by id
+    GetRegisteredPools {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Get the latest cross-chain move of a position
+    GetTransferStatus {
+        token_id: String,
+    },
+    // Whether keepers may compound a position
+    GetAutoCompound {
+        token_id: String,
+    },
+    // Get the time lock of a position
+    GetLock {
+        token_id: String,
+    },
+    // Get the funds left to pay reward boosts
+    GetBoostReserve {},
+    // Get the claims paid out for a position, oldest first
+    GetRewardHistory {
+        token_id: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // List the factory's pools, ordered by id
+    ListPools {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Get a pool's reserves and fee tier from the factory
+    PoolInfo {
+        pool_id: String,
+    },
+    // Shares a deposit of `amounts`, ordered like the pool's pair, would mint
+    SimulateAddLiquidity {
+        pool_id: String,
+        amounts: (Uint128, Uint128),
+    },
+    // Tokens a withdrawal of `amount` shares would pay out
+    SimulateRemoveLiquidity {
+        pool_id: String,
+        amount: Uint128,
+    },
 }
 
-// Message for communication with Euclid Protocol
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct EuclidMsg {
-    pub action: EuclidAction,
-    pub data: Binary,
+pub struct PositionValueResponse {
+    pub token_id: String,
+    pub quote_denom: String,
+    // The position's share of the pool reserves
+    pub assets: Vec<Coin>,
+    pub value: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-pub enum EuclidAction {
-    AddLiquidity,
-    RemoveLiquidity,
-    SwapExactIn,
-    SwapExactOut,
-    TransferLiquidity,
-    ClaimRewards,
+pub struct PortfolioValueResponse {
+    pub owner: String,
+    pub quote_denom: String,
+    pub positions: Vec<PositionValueResponse>,
+    pub value: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockStatusResponse {
+    pub token_id: String,
+    pub lock: Option<TimeLock>,
+    // Whether the lock still blocks withdrawals and cross-chain moves
+    pub locked: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BoostReserveResponse {
+    pub funds: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TvlResponse {
+    pub total: Tvl,
+    // Set while `Reconcile` is rebuilding the totals, which then undercount
+    pub reconciling: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct EuclidResponse {
-    pub success: bool,
-    pub data: Binary,
-    pub error: Option<String>,
+pub struct RewardsResponse {
+    pub token_id: String,
+    pub denoms: Vec<String>,
+    // Accrued on the router and not claimed yet, zero once the position is closed
+    pub accrued: Uint128,
+    // Lifetime total claimed, per denom
+    pub claimed: Vec<Coin>,
+    pub last_claimed: Option<u64>,
 }
@@ -0,0 +1,83 @@
+//! Price sources used to value positions.
+//!
+//! `PriceOracle` quotes one unit of a denom in another. `OracleContract`
+//! asks a price oracle contract speaking `OracleQueryMsg`, `PoolReserveOracle`
+//! reads the reserves of a registered pool pairing both denoms on the router.
+
+use cosmwasm_std::{Addr, Decimal, Deps, Order, StdError, StdResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::euclid::EuclidRouter;
+use crate::state::{PoolStatus, POOLS};
+
+/// Implemented by every price source
+pub trait PriceOracle {
+    // Price of one unit of `denom` in units of `quote_denom`
+    fn price(&self, deps: Deps, denom: &str, quote_denom: &str) -> StdResult<Decimal>;
+}
+
+/// Queries answered by a price oracle contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    Price { denom: String, quote_denom: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub price: Decimal,
+}
+
+/// Handle on a deployed price oracle contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleContract(pub Addr);
+
+impl PriceOracle for OracleContract {
+    fn price(&self, deps: Deps, denom: &str, quote_denom: &str) -> StdResult<Decimal> {
+        let response: PriceResponse = deps.querier.query_wasm_smart(
+            &self.0,
+            &OracleQueryMsg::Price {
+                denom: denom.to_string(),
+                quote_denom: quote_denom.to_string(),
+            },
+        )?;
+        Ok(response.price)
+    }
+}
+
+/// Spot price from the reserves of the first active registered pool that
+/// pairs both denoms
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PoolReserveOracle(pub EuclidRouter);
+
+impl PriceOracle for PoolReserveOracle {
+    fn price(&self, deps: Deps, denom: &str, quote_denom: &str) -> StdResult<Decimal> {
+        let pool = POOLS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, pool)| pool))
+            .collect::<StdResult<Vec<_>>>()?
+            .into_iter()
+            .find(|pool| {
+                pool.status == PoolStatus::Active
+                    && ((pool.token_pair.0 == denom && pool.token_pair.1 == quote_denom)
+                        || (pool.token_pair.0 == quote_denom && pool.token_pair.1 == denom))
+            })
+            .ok_or_else(|| no_price(denom, quote_denom))?;
+
+        let reserves = self.0.pool(&deps.querier, pool.pool_id)?.reserves;
+        let (reserve, quote_reserve) = if pool.token_pair.0 == denom {
+            reserves
+        } else {
+            (reserves.1, reserves.0)
+        };
+        if reserve.is_zero() {
+            return Err(no_price(denom, quote_denom));
+        }
+        Ok(Decimal::from_ratio(quote_reserve, reserve))
+    }
+}
+
+fn no_price(denom: &str, quote_denom: &str) -> StdError {
+    StdError::generic_err(format!("No price for {denom} in {quote_denom}"))
+}
@@ -1,7 +1,11 @@
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cw20::Cw20Coin;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use ul_nft_core::state::LiquidityPosition as NftLiquidityPosition;
+
+use crate::msg::EuclidAction;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -9,6 +13,56 @@ pub struct Config {
     pub ul_nft_contract: Addr,
     pub euclid_router: Addr,
     pub euclid_factory: Addr,
+    // Share of compounded rewards paid to the keeper that triggered it
+    #[serde(default)]
+    pub compound_bounty: Decimal,
+    // Seconds after which an unfinished cross-chain move may be recovered
+    #[serde(default = "default_transfer_timeout")]
+    pub transfer_timeout: u64,
+    // Price oracle contract valuing positions, pool reserves when unset
+    #[serde(default)]
+    pub price_oracle: Option<Addr>,
+    // Denom portfolios are valued in
+    #[serde(default = "default_quote_denom")]
+    pub quote_denom: String,
+    // Lock durations on offer and the reward multiplier each earns
+    #[serde(default)]
+    pub lock_tiers: Vec<LockTier>,
+    // Share of a locked position forfeited to leave before it unlocks,
+    // early exits are refused when unset
+    #[serde(default)]
+    pub early_exit_penalty: Option<Decimal>,
+    // May pause actions alongside the admin, only the admin unpauses
+    #[serde(default)]
+    pub guardian: Option<Addr>,
+    // Largest shortfall from the oracle price the swaps of zaps and compounds
+    // accept unless the caller sets their own
+    #[serde(default = "default_max_slippage")]
+    pub max_slippage: Decimal,
+    // Largest deviation from the pool's ratio a deposit may have
+    #[serde(default = "default_deposit_ratio_tolerance")]
+    pub deposit_ratio_tolerance: Decimal,
+}
+
+pub const DEFAULT_TRANSFER_TIMEOUT: u64 = 24 * 60 * 60;
+pub const DEFAULT_QUOTE_DENOM: &str = "uusdc";
+pub const DEFAULT_MAX_SLIPPAGE: Decimal = Decimal::percent(1);
+pub const DEFAULT_DEPOSIT_RATIO_TOLERANCE: Decimal = Decimal::percent(1);
+
+fn default_transfer_timeout() -> u64 {
+    DEFAULT_TRANSFER_TIMEOUT
+}
+
+fn default_quote_denom() -> String {
+    DEFAULT_QUOTE_DENOM.to_string()
+}
+
+fn default_max_slippage() -> Decimal {
+    DEFAULT_MAX_SLIPPAGE
+}
+
+fn default_deposit_ratio_tolerance() -> Decimal {
+    DEFAULT_DEPOSIT_RATIO_TOLERANCE
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -20,6 +74,18 @@ pub struct LiquidityPosition {
     pub last_updated: u64,
 }
 
+impl LiquidityPosition {
+    // The position as recorded on the UL-NFT
+    pub fn to_nft_position(&self) -> NftLiquidityPosition {
+        NftLiquidityPosition {
+            pool_id: self.pool_id.clone(),
+            token_pair: self.token_pair.clone(),
+            amount: self.amount,
+            chain_id: self.chain_id.clone(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct PositionInfo {
     pub owner: Addr,
@@ -27,14 +93,408 @@ pub struct PositionInfo {
     pub position: LiquidityPosition,
 }
 
+pub struct PositionIndexes<'a> {
+    pub owner: MultiIndex<'a, Addr, PositionInfo, String>,
+    pub pool_id: MultiIndex<'a, String, PositionInfo, String>,
+    pub chain_id: MultiIndex<'a, String, PositionInfo, String>,
+}
+
+impl<'a> IndexList<PositionInfo> for PositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PositionInfo>> + '_> {
+        let v: Vec<&dyn Index<PositionInfo>> = vec![&self.owner, &self.pool_id, &self.chain_id];
+        Box::new(v.into_iter())
+    }
+}
+
+fn position_owner_idx(_pk: &[u8], info: &PositionInfo) -> Addr {
+    info.owner.clone()
+}
+
+fn position_pool_idx(_pk: &[u8], info: &PositionInfo) -> String {
+    info.position.pool_id.clone()
+}
+
+fn position_chain_idx(_pk: &[u8], info: &PositionInfo) -> String {
+    info.position.chain_id.clone()
+}
+
+// A router call awaiting its reply, keyed by operation id
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingOperation {
+    pub id: u64,
+    pub sender: Addr,
+    pub action: EuclidAction,
+    pub pool_id: String,
+    pub chain_id: String,
+    pub token_id: Option<String>,
+    pub funds: Vec<Coin>,
+    pub submitted_at: u64,
+}
+
+pub struct PendingOperationIndexes<'a> {
+    pub sender: MultiIndex<'a, Addr, PendingOperation, u64>,
+}
+
+impl<'a> IndexList<PendingOperation> for PendingOperationIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PendingOperation>> + '_> {
+        let v: Vec<&dyn Index<PendingOperation>> = vec![&self.sender];
+        Box::new(v.into_iter())
+    }
+}
+
+fn pending_operation_sender_idx(_pk: &[u8], op: &PendingOperation) -> Addr {
+    op.sender.clone()
+}
+
+// A router operation that failed, with the router's error and any refund
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FailedOperation {
+    pub operation: PendingOperation,
+    pub error: String,
+    pub refunded: Vec<Coin>,
+    pub failed_at: u64,
+}
+
+pub struct FailedOperationIndexes<'a> {
+    pub sender: MultiIndex<'a, Addr, FailedOperation, u64>,
+}
+
+impl<'a> IndexList<FailedOperation> for FailedOperationIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<FailedOperation>> + '_> {
+        let v: Vec<&dyn Index<FailedOperation>> = vec![&self.sender];
+        Box::new(v.into_iter())
+    }
+}
+
+fn failed_operation_sender_idx(_pk: &[u8], failed: &FailedOperation) -> Addr {
+    failed.operation.sender.clone()
+}
+
+// Route and limits of a pending swap, checked again when the router replies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SwapOrder {
+    pub route: Vec<String>,
+    pub max_amount_in: Uint128,
+    pub min_amount_out: Uint128,
+}
+
+// Bounds the router's answer to a withdrawal or cross-chain move must meet
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct OperationLimits {
+    // Least of each token a withdrawal pays out
+    pub min_amounts_out: Vec<Coin>,
+    // Least liquidity arriving on the destination chain
+    pub min_amount_out: Option<Uint128>,
+    pub max_fee: Option<Coin>,
+    pub deadline: Option<u64>,
+    // Least shares a deposit mints
+    #[serde(default)]
+    pub min_shares: Uint128,
+}
+
+// A zap in progress, one router operation per step
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Zap {
+    pub kind: ZapKind,
+    // Set while every step has settled within the originating transaction,
+    // a failure then reverts the whole zap instead of refunding
+    pub atomic: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ZapKind {
+    // Swap part of the input, then deposit both tokens
+    In {
+        token_pair: (String, String),
+        min_shares: Uint128,
+    },
+    // Withdraw, then swap the other token to `output_denom`
+    Out {
+        output_denom: String,
+        min_amount_out: Uint128,
+        // Amount of `output_denom` received so far
+        collected: Uint128,
+        // Passed on to the swap step
+        #[serde(default)]
+        deadline: Option<u64>,
+    },
+}
+
+// A chain positions may live on
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RegisteredChain {
+    pub chain_id: String,
+    pub display_name: String,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStatus {
+    Active,
+    // No liquidity may be added, removed or moved
+    Disabled,
+}
+
+// A pool the wrapper accepts liquidity for
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RegisteredPool {
+    pub pool_id: String,
+    // Sorted, as positions store it
+    pub token_pair: (String, String),
+    // Chains the pool is available on
+    pub chains: Vec<String>,
+    pub status: PoolStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    // Submitted to the router
+    Initiated,
+    // Accepted by the router, waiting for its callback
+    InFlight,
+    Completed,
+    Failed,
+    // Recovered by the owner after the router did not finish in time
+    TimedOut,
+}
+
+// The latest cross-chain move of a position
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Transfer {
+    pub operation_id: u64,
+    pub from_chain_id: String,
+    pub to_chain_id: String,
+    pub amount: Uint128,
+    pub status: TransferStatus,
+    pub initiated_at: u64,
+    pub updated_at: u64,
+    // Why the move failed, or the limit a completed move missed
+    pub error: Option<String>,
+}
+
+// Rewards bookkeeping of a position, kept after the position is closed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardLedger {
+    // Denoms the position has been paid rewards in
+    pub denoms: Vec<String>,
+    // Lifetime total paid out, per denom
+    pub claimed: Vec<Coin>,
+    // Rewards are accruing since this time, the opening or the last claim
+    pub accrued_since: u64,
+    pub last_claimed: Option<u64>,
+}
+
+impl RewardLedger {
+    pub fn new(opened_at: u64) -> Self {
+        RewardLedger {
+            denoms: vec![],
+            claimed: vec![],
+            accrued_since: opened_at,
+            last_claimed: None,
+        }
+    }
+}
+
+// A settled claim, as paid out to the UL-NFT owner
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardClaim {
+    pub operation_id: u64,
+    pub recipient: Addr,
+    pub rewards: Vec<Coin>,
+    pub claimed_at: u64,
+    // Reinvested into the position instead of paid out
+    pub compounded: bool,
+    // Part of `rewards` paid from the boost reserve for a time lock
+    #[serde(default)]
+    pub boost: Vec<Coin>,
+}
+
+// Rewards being reinvested into a position, one router operation per step:
+// claim, swap each reward into the pair, even out both sides, deposit
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Compound {
+    pub keeper: Addr,
+    pub token_pair: (String, String),
+    // Rewards not in the pair, still to be swapped into its first token
+    pub pending: Vec<Coin>,
+    // Tokens of the pair held for the deposit
+    pub held: (Uint128, Uint128),
+    // Set while every step has settled within the keeper's transaction, a
+    // failure then reverts the whole compound instead of refunding
+    pub atomic: bool,
+}
+
+// Locks of at least `duration` seconds earn `multiplier` times the rewards
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockTier {
+    pub duration: u64,
+    pub multiplier: Decimal,
+}
+
+// A position its owner committed to keep until `unlocks_at`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TimeLock {
+    pub locked_at: u64,
+    pub unlocks_at: u64,
+    pub multiplier: Decimal,
+}
+
+impl TimeLock {
+    pub fn is_active(&self, now: u64) -> bool {
+        now < self.unlocks_at
+    }
+}
+
+// A withdrawal leaving a lock early, settled once the router pays out
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EarlyExit {
+    pub recipient: Addr,
+    pub penalty: Decimal,
+}
+
+// Actions that can be paused in an emergency
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PausableAction {
+    // Deposits, including increases, cw20 deposits and zaps in
+    AddLiquidity,
+    // Withdrawals, including decreases and zaps out
+    RemoveLiquidity,
+    // Cross-chain moves
+    Transfer,
+    // Reward claims and compounding
+    ClaimRewards,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct PauseState {
+    // Refused until unpaused, operations already in flight still settle
+    pub paused: Vec<PausableAction>,
+    pub updated_by: Option<Addr>,
+    pub updated_at: Option<u64>,
+}
+
+// Liquidity held through the wrapper, for a pool, a chain or overall
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Tvl {
+    pub liquidity: Uint128,
+    // Open positions, one per deposit not yet fully withdrawn
+    pub positions: u64,
+    // Distinct owners of the open positions
+    pub depositors: u64,
+}
+
 // Store configuration
 pub const CONFIG: Item<Config> = Item::new("config");
 
-// Map token_id to position info
-pub const POSITIONS: Map<&str, PositionInfo> = Map::new("positions");
+// Actions currently paused
+pub const PAUSE_STATE: Item<PauseState> = Item::new("pause_state");
+
+// Number of UL-NFTs minted, used to derive token ids
+pub const TOKEN_COUNT: Item<u64> = Item::new("token_count");
+
+// Map chain_id to the registered chain
+pub const CHAINS: Map<&str, RegisteredChain> = Map::new("chains");
+
+// Map pool_id to the registered pool
+pub const POOLS: Map<&str, RegisteredPool> = Map::new("pools");
+
+// Map token_id to position info, indexed by owner, pool and chain
+pub const POSITIONS: IndexedMap<&str, PositionInfo, PositionIndexes> = IndexedMap::new(
+    "positions",
+    PositionIndexes {
+        owner: MultiIndex::new(position_owner_idx, "positions", "positions__owner"),
+        pool_id: MultiIndex::new(position_pool_idx, "positions", "positions__pool_id"),
+        chain_id: MultiIndex::new(position_chain_idx, "positions", "positions__chain_id"),
+    },
+);
+
+// Map pool_id to the liquidity held in the pool
+pub const POOL_TVL: Map<&str, Tvl> = Map::new("pool_tvl");
+
+// Map chain_id to the liquidity living on the chain
+pub const CHAIN_TVL: Map<&str, Tvl> = Map::new("chain_tvl");
+
+// Liquidity held across all pools and chains
+pub const TOTAL_TVL: Item<Tvl> = Item::new("total_tvl");
+
+// Set while `Reconcile` rebuilds the TVL totals, holding the last token_id
+// counted so far. Positions after it are left out until it reaches them.
+pub const RECONCILING: Item<Option<String>> = Item::new("reconciling");
+
+// Last operation id handed out, ids are never reused
+pub const OPERATION_COUNT: Item<u64> = Item::new("operation_count");
+
+// Store pending operations, indexed by sender
+pub const PENDING_OPERATIONS: IndexedMap<u64, PendingOperation, PendingOperationIndexes> =
+    IndexedMap::new(
+        "pending_operations",
+        PendingOperationIndexes {
+            sender: MultiIndex::new(
+                pending_operation_sender_idx,
+                "pending_operations",
+                "pending_operations__sender",
+            ),
+        },
+    );
+
+// Store failed operations, indexed by sender
+pub const FAILED_OPERATIONS: IndexedMap<u64, FailedOperation, FailedOperationIndexes> =
+    IndexedMap::new(
+        "failed_operations",
+        FailedOperationIndexes {
+            sender: MultiIndex::new(
+                failed_operation_sender_idx,
+                "failed_operations",
+                "failed_operations__sender",
+            ),
+        },
+    );
+
+// Map token_id to the operation currently in flight for it
+pub const LOCKED_POSITIONS: Map<&str, u64> = Map::new("locked_positions");
+
+// Map operation id to the swap it carries
+pub const PENDING_SWAPS: Map<u64, SwapOrder> = Map::new("pending_swaps");
+
+// Map the operation id of a zap's current step to the zap
+pub const PENDING_ZAPS: Map<u64, Zap> = Map::new("pending_zaps");
+
+// Map (owner, cw20 token) to tokens deposited and not yet added as liquidity
+pub const CW20_DEPOSITS: Map<(&Addr, &Addr), Uint128> = Map::new("cw20_deposits");
+
+// Map operation id to the cw20 tokens sent to the router with it
+pub const PENDING_CW20: Map<u64, Vec<Cw20Coin>> = Map::new("pending_cw20");
+
+// Map operation id to the limits its sender set
+pub const OPERATION_LIMITS: Map<u64, OperationLimits> = Map::new("operation_limits");
+
+// Map token_id to its rewards ledger
+pub const REWARD_LEDGERS: Map<&str, RewardLedger> = Map::new("reward_ledgers");
+
+// Map (token_id, operation id) to the claims paid out for the position
+pub const REWARD_HISTORY: Map<(&str, u64), RewardClaim> = Map::new("reward_history");
+
+// Map token_id to the owner that opted the position into auto-compounding
+pub const AUTO_COMPOUND: Map<&str, Addr> = Map::new("auto_compound");
+
+// Map the operation id of a compound's current step to the compound
+pub const PENDING_COMPOUNDS: Map<u64, Compound> = Map::new("pending_compounds");
+
+// Map token_id to its latest cross-chain move
+pub const TRANSFERS: Map<&str, Transfer> = Map::new("transfers");
+
+// Map token_id to the time lock on the position
+pub const TIME_LOCKS: Map<&str, TimeLock> = Map::new("time_locks");
+
+// Map the token_id reserved by a deposit to the lock asked for with it
+pub const PENDING_LOCKS: Map<&str, LockTier> = Map::new("pending_locks");
 
-// Map pool_id to total liquidity
-pub const POOL_LIQUIDITY: Map<&str, Uint128> = Map::new("pool_liquidity");
+// Map operation id to the early exit its withdrawal settles
+pub const PENDING_EXITS: Map<u64, EarlyExit> = Map::new("pending_exits");
 
-// Store pending operations
-pub const PENDING_OPERATIONS: Map<&str, Vec<String>> = Map::new("pending_operations");
+// Map denom to the funds paying reward boosts, fed by early exit penalties
+pub const BOOST_RESERVE: Map<&str, Uint128> = Map::new("boost_reserve");
@@ -3,6 +3,7 @@ use cosmwasm_std::{
     Response, StdResult, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
+use cw721::{ApprovalResponse, Cw721QueryMsg, OwnerOfResponse};
 use cw_storage_plus::Bound;
 
 use crate::error::ContractError;
@@ -75,6 +76,30 @@ pub fn execute_list_nft(
         return Err(ContractError::AlreadyListed {});
     }
 
+    // Seller must own the NFT and let the marketplace transfer it on sale
+    let config = CONFIG.load(deps.storage)?;
+    let owner: OwnerOfResponse = deps.querier.query_wasm_smart(
+        &config.ul_nft_contract,
+        &Cw721QueryMsg::OwnerOf {
+            token_id: token_id.clone(),
+            include_expired: Some(false),
+        },
+    )?;
+    if owner.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let approval: StdResult<ApprovalResponse> = deps.querier.query_wasm_smart(
+        &config.ul_nft_contract,
+        &Cw721QueryMsg::Approval {
+            token_id: token_id.clone(),
+            spender: env.contract.address.to_string(),
+            include_expired: Some(false),
+        },
+    );
+    if approval.is_err() {
+        return Err(ContractError::NotApproved {});
+    }
+
     // Create listing
     let listing = ListingInfo {
         seller: info.sender.clone(),
@@ -269,8 +294,8 @@ fn query_config(deps: Deps) -> StdResult<Config> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmwasm_std::from_json;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_json};
 
     #[test]
     fn proper_initialization() {
@@ -15,6 +15,9 @@ pub enum ContractError {
     #[error("NFT already listed")]
     AlreadyListed {},
 
+    #[error("Marketplace is not approved to transfer this NFT")]
+    NotApproved {},
+
     #[error("Invalid price")]
     InvalidPrice {},
 
@@ -1,13 +1,12 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult,
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
 };
 
 use crate::error::ContractError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
 use crate::state::{Attribute, Config, PoolStats, TokenMetadata, CONFIG, METADATA, POOL_STATS};
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
@@ -38,7 +37,7 @@ pub fn instantiate(
         .add_attribute("ul_nft_contract", msg.ul_nft_contract))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn execute(
     deps: DepsMut,
     env: Env,
@@ -201,7 +200,7 @@ pub fn execute_remove_updater(
     // Remove the updater
     config
         .updater_addresses
-        .retain(|addr| addr != &validated_addr);
+        .retain(|addr| *addr != validated_addr);
 
     // Save updated config
     CONFIG.save(deps.storage, &config)?;
@@ -230,7 +229,7 @@ pub fn execute_update_pool_stats(
         .add_attribute("pool_id", pool_id))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetMetadata { token_id } => to_json_binary(&query_metadata(deps, token_id)?),
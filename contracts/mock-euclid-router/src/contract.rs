@@ -0,0 +1,679 @@
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, DepsMut,
+    Env, MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cw2::set_contract_version;
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::Bound;
+use liquidity_wrapper::euclid::{
+    AddLiquidityRequest, AddLiquidityResponse, ClaimRewardsRequest, ClaimRewardsResponse,
+    EstimateRewardsResponse, EuclidAction, EuclidCallbackMsg, EuclidMsg, EuclidResponse,
+    PoolInfoResponse, PoolResponse, PoolsResponse, PositionResponse, RemoveLiquidityRequest,
+    RemoveLiquidityResponse, SimulateAddLiquidityResponse, SimulateRemoveLiquidityResponse,
+    SimulateSwapResponse, SwapExactInRequest, SwapExactOutRequest, SwapResponse,
+    TransferLiquidityRequest, TransferLiquidityResponse,
+};
+
+use crate::error::ContractError;
+use crate::msg::{Behaviour, InstantiateMsg, QueryMsg, QueueResponse, SudoMsg};
+use crate::state::{
+    Pool, Position, QueuedCall, TransferCost, BEHAVIOURS, POOLS, POSITIONS, QUEUE, QUEUE_COUNT,
+    TRANSFER_COST,
+};
+
+// Contract name and version
+const CONTRACT_NAME: &str = "crates.io:mock-euclid-router";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new().add_attribute("method", "instantiate"))
+}
+
+// Every call speaks the Euclid protocol and is answered per the scripted
+// behaviour for its action
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: EuclidMsg,
+) -> Result<Response, ContractError> {
+    let behaviour = BEHAVIOURS
+        .may_load(deps.storage, msg.action.as_str())?
+        .unwrap_or(Behaviour::Succeed);
+
+    // Responses are reported both in the event and as data
+    match behaviour {
+        Behaviour::Succeed => {
+            let (response, messages) =
+                process(deps.storage, &env, &info.sender, &msg, &info.funds)?;
+            Ok(Response::new()
+                .add_messages(messages)
+                .set_data(to_json_binary(&response)?)
+                .add_attributes(response.to_attributes(&msg.action)))
+        }
+        Behaviour::Fail { error } => {
+            let response = EuclidResponse::failure(error);
+            Ok(Response::new()
+                .add_messages(refund(&info.sender, info.funds))
+                .add_messages(refund_cw20(&info.sender, &msg)?)
+                .set_data(to_json_binary(&response)?)
+                .add_attributes(response.to_attributes(&msg.action)))
+        }
+        Behaviour::Revert { error } => Err(ContractError::Scripted { error }),
+        Behaviour::Delay => {
+            let id = QUEUE_COUNT.may_load(deps.storage)?.unwrap_or_default() + 1;
+            QUEUE_COUNT.save(deps.storage, &id)?;
+            QUEUE.save(
+                deps.storage,
+                id,
+                &QueuedCall {
+                    caller: info.sender,
+                    msg: msg.clone(),
+                    funds: info.funds,
+                },
+            )?;
+            let response = EuclidResponse::accepted();
+            Ok(Response::new()
+                .set_data(to_json_binary(&response)?)
+                .add_attributes(response.to_attributes(&msg.action))
+                .add_attribute("queued_id", id.to_string()))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::SetPool {
+            pool_id,
+            token_pair,
+            reserves,
+            total_shares,
+        } => {
+            let pool = Pool {
+                token_pair,
+                reserves,
+                total_shares,
+                reward_rate: Decimal::zero(),
+                accrued_rewards: vec![],
+                fee_tier: Decimal::zero(),
+            };
+            POOLS.save(deps.storage, &pool_id, &pool)?;
+            Ok(Response::new().add_attribute("action", "set_pool"))
+        }
+        SudoMsg::SetBehaviour { action, behaviour } => {
+            BEHAVIOURS.save(deps.storage, action.as_str(), &behaviour)?;
+            Ok(Response::new().add_attribute("action", "set_behaviour"))
+        }
+        SudoMsg::Complete { id, error } => sudo_complete(deps, env, id, error),
+        SudoMsg::SetRewardRate { pool_id, rate } => {
+            let mut pool = load_pool(deps.storage, &pool_id)?;
+            pool.reward_rate = rate;
+            POOLS.save(deps.storage, &pool_id, &pool)?;
+            Ok(Response::new().add_attribute("action", "set_reward_rate"))
+        }
+        SudoMsg::AccrueRewards { pool_id, rewards } => {
+            let mut pool = load_pool(deps.storage, &pool_id)?;
+            for reward in rewards {
+                match pool
+                    .accrued_rewards
+                    .iter_mut()
+                    .find(|coin| coin.denom == reward.denom)
+                {
+                    Some(coin) => coin.amount += reward.amount,
+                    None => pool.accrued_rewards.push(reward),
+                }
+            }
+            POOLS.save(deps.storage, &pool_id, &pool)?;
+            Ok(Response::new().add_attribute("action", "accrue_rewards"))
+        }
+        SudoMsg::SetTransferCost { fee, slippage } => {
+            TRANSFER_COST.save(deps.storage, &TransferCost { fee, slippage })?;
+            Ok(Response::new().add_attribute("action", "set_transfer_cost"))
+        }
+        SudoMsg::SetPosition { token_id, amount } => {
+            let mut position = load_position(deps.storage, &token_id)?;
+            position.amount = amount;
+            POSITIONS.save(deps.storage, &token_id, &position)?;
+            Ok(Response::new().add_attribute("action", "set_position"))
+        }
+        SudoMsg::SetFeeTier { pool_id, fee_tier } => {
+            let mut pool = load_pool(deps.storage, &pool_id)?;
+            pool.fee_tier = fee_tier;
+            POOLS.save(deps.storage, &pool_id, &pool)?;
+            Ok(Response::new().add_attribute("action", "set_fee_tier"))
+        }
+    }
+}
+
+// Finishes a delayed call and reports back to the caller, returning the
+// escrowed funds along with a failure
+fn sudo_complete(
+    deps: DepsMut,
+    env: Env,
+    id: u64,
+    error: Option<String>,
+) -> Result<Response, ContractError> {
+    let call = QUEUE
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::QueuedCallNotFound { id })?;
+    QUEUE.remove(deps.storage, id);
+    let tx_id = call.msg.tx_id.ok_or(ContractError::MissingTxId { id })?;
+
+    let (response, messages, returned) = match error {
+        Some(error) => (
+            EuclidResponse::failure(error),
+            refund_cw20(&call.caller, &call.msg)?,
+            call.funds,
+        ),
+        None => {
+            let (response, messages) =
+                process(deps.storage, &env, &call.caller, &call.msg, &call.funds)?;
+            (response, messages, vec![])
+        }
+    };
+
+    let callback = WasmMsg::Execute {
+        contract_addr: call.caller.to_string(),
+        msg: to_json_binary(&EuclidCallbackMsg::EuclidCallback { tx_id, response })?,
+        funds: returned,
+    };
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_message(callback)
+        .add_attribute("action", "complete")
+        .add_attribute("queued_id", id.to_string()))
+}
+
+// Executes a call against the simulated pools
+fn process(
+    storage: &mut dyn Storage,
+    env: &Env,
+    caller: &Addr,
+    msg: &EuclidMsg,
+    funds: &[Coin],
+) -> Result<(EuclidResponse, Vec<CosmosMsg>), ContractError> {
+    match msg.action {
+        EuclidAction::AddLiquidity => {
+            let request: AddLiquidityRequest = msg.parse()?;
+            let mut pool = POOLS
+                .may_load(storage, &request.pool_id)?
+                .unwrap_or_else(|| Pool {
+                    token_pair: request.token_pair.clone(),
+                    reserves: (Uint128::zero(), Uint128::zero()),
+                    total_shares: Uint128::zero(),
+                    reward_rate: Decimal::zero(),
+                    accrued_rewards: vec![],
+                    fee_tier: Decimal::zero(),
+                });
+            // Cw20 sides were transferred here before the call
+            let deposited = |denom: &String| {
+                amount_of(funds, denom)
+                    + request
+                        .cw20_assets
+                        .iter()
+                        .filter(|asset| &asset.address == denom)
+                        .map(|asset| asset.amount)
+                        .sum::<Uint128>()
+            };
+            let deposited = (deposited(&pool.token_pair.0), deposited(&pool.token_pair.1));
+            let shares = shares_for(&pool, deposited);
+            if shares.is_zero() {
+                return Err(ContractError::InsufficientFunds {});
+            }
+            if shares < request.min_shares {
+                return Err(ContractError::SlippageExceeded {});
+            }
+            pool.reserves.0 += deposited.0;
+            pool.reserves.1 += deposited.1;
+            pool.total_shares += shares;
+            POOLS.save(storage, &request.pool_id, &pool)?;
+
+            let mut position = POSITIONS
+                .may_load(storage, &request.token_id)?
+                .unwrap_or_else(|| Position {
+                    pool_id: request.pool_id.clone(),
+                    chain_id: request.chain_id.clone(),
+                    amount: Uint128::zero(),
+                });
+            position.amount += shares;
+            POSITIONS.save(storage, &request.token_id, &position)?;
+
+            let response = AddLiquidityResponse {
+                pool_id: request.pool_id,
+                chain_id: request.chain_id,
+                token_pair: pool.token_pair,
+                amount: shares,
+            };
+            Ok((EuclidResponse::ok(&response)?, vec![]))
+        }
+        EuclidAction::RemoveLiquidity => {
+            let request: RemoveLiquidityRequest = msg.parse()?;
+            check_deadline(env, request.deadline)?;
+            let mut pool = load_pool(storage, &request.pool_id)?;
+            if request.amount.is_zero() || request.amount > pool.total_shares {
+                return Err(ContractError::InsufficientLiquidity {});
+            }
+
+            let out = (
+                pool.reserves
+                    .0
+                    .multiply_ratio(request.amount, pool.total_shares),
+                pool.reserves
+                    .1
+                    .multiply_ratio(request.amount, pool.total_shares),
+            );
+            pool.reserves.0 -= out.0;
+            pool.reserves.1 -= out.1;
+            pool.total_shares -= request.amount;
+            POOLS.save(storage, &request.pool_id, &pool)?;
+            update_position(storage, &request.token_id, |position| {
+                position.amount = position.amount.saturating_sub(request.amount);
+            })?;
+
+            let payout = vec![
+                Coin::new(out.0.u128(), pool.token_pair.0),
+                Coin::new(out.1.u128(), pool.token_pair.1),
+            ];
+            let recipient = Addr::unchecked(request.recipient);
+            let response = RemoveLiquidityResponse {
+                token_id: request.token_id,
+                amount: request.amount,
+                assets: payout.clone(),
+            };
+            Ok((EuclidResponse::ok(&response)?, refund(&recipient, payout)))
+        }
+        EuclidAction::TransferLiquidity => {
+            let request: TransferLiquidityRequest = msg.parse()?;
+            check_deadline(env, request.deadline)?;
+            let cost = TRANSFER_COST.may_load(storage)?.unwrap_or_default();
+            let arrived = request.amount - request.amount * cost.slippage;
+            update_position(storage, &request.token_id, |position| {
+                position.chain_id = request.to_chain_id.clone();
+                position.amount = position.amount.saturating_sub(request.amount) + arrived;
+            })?;
+            let response = TransferLiquidityResponse {
+                token_id: request.token_id,
+                new_chain_id: request.to_chain_id,
+                amount: arrived,
+                fee: cost.fee,
+            };
+            Ok((EuclidResponse::ok(&response)?, vec![]))
+        }
+        EuclidAction::ClaimRewards => {
+            let request: ClaimRewardsRequest = msg.parse()?;
+            let mut pool = load_pool(storage, &request.pool_id)?;
+            if request.amount > pool.total_shares {
+                return Err(ContractError::InsufficientLiquidity {});
+            }
+
+            // Pro rata share of everything accrued so far
+            let mut rewards = vec![];
+            for coin in pool.accrued_rewards.iter_mut() {
+                let share = coin
+                    .amount
+                    .multiply_ratio(request.amount, pool.total_shares);
+                coin.amount -= share;
+                rewards.push(Coin::new(share.u128(), coin.denom.clone()));
+            }
+            POOLS.save(storage, &request.pool_id, &pool)?;
+
+            let response = ClaimRewardsResponse {
+                token_id: request.token_id,
+                rewards: rewards.clone(),
+            };
+            Ok((EuclidResponse::ok(&response)?, refund(caller, rewards)))
+        }
+        EuclidAction::SwapExactIn => {
+            let request: SwapExactInRequest = msg.parse()?;
+            check_deadline(env, request.deadline)?;
+            let (offer, ask) = route_ends(&request.route)?;
+            let offered = amount_of(funds, offer);
+            if offered < request.amount_in {
+                return Err(ContractError::InsufficientFunds {});
+            }
+
+            let mut amount = request.amount_in;
+            for hop in request.route.windows(2) {
+                let (pool_id, mut pool) = find_pool(storage, &hop[0], &hop[1])?;
+                let (reserve_in, reserve_out) = reserves_from(&pool, &hop[0]);
+                let out = reserve_out.multiply_ratio(amount, reserve_in + amount);
+                set_reserves_from(&mut pool, &hop[0], reserve_in + amount, reserve_out - out);
+                POOLS.save(storage, &pool_id, &pool)?;
+                amount = out;
+            }
+            if amount < request.min_amount_out {
+                return Err(ContractError::SlippageExceeded {});
+            }
+
+            let payout = vec![
+                Coin::new(amount.u128(), ask),
+                Coin::new((offered - request.amount_in).u128(), offer),
+            ];
+            let response = SwapResponse {
+                amount_in: request.amount_in,
+                amount_out: amount,
+            };
+            Ok((EuclidResponse::ok(&response)?, refund(caller, payout)))
+        }
+        EuclidAction::SwapExactOut => {
+            let request: SwapExactOutRequest = msg.parse()?;
+            check_deadline(env, request.deadline)?;
+            let (offer, ask) = route_ends(&request.route)?;
+
+            // Walk the route backwards to find the input each hop needs
+            let mut amounts = vec![request.amount_out];
+            for hop in request.route.windows(2).rev() {
+                let (_, pool) = find_pool(storage, &hop[0], &hop[1])?;
+                let (reserve_in, reserve_out) = reserves_from(&pool, &hop[0]);
+                let out = amounts[0];
+                if out >= reserve_out {
+                    return Err(ContractError::InsufficientLiquidity {});
+                }
+                let needed = reserve_in.multiply_ratio(out, reserve_out - out) + Uint128::one();
+                amounts.insert(0, needed);
+            }
+            let amount_in = amounts[0];
+            if amount_in > request.max_amount_in {
+                return Err(ContractError::SlippageExceeded {});
+            }
+            let offered = amount_of(funds, offer);
+            if offered < amount_in {
+                return Err(ContractError::InsufficientFunds {});
+            }
+
+            for (i, hop) in request.route.windows(2).enumerate() {
+                let (pool_id, mut pool) = find_pool(storage, &hop[0], &hop[1])?;
+                let (reserve_in, reserve_out) = reserves_from(&pool, &hop[0]);
+                set_reserves_from(
+                    &mut pool,
+                    &hop[0],
+                    reserve_in + amounts[i],
+                    reserve_out - amounts[i + 1],
+                );
+                POOLS.save(storage, &pool_id, &pool)?;
+            }
+
+            let payout = vec![
+                Coin::new(request.amount_out.u128(), ask),
+                Coin::new((offered - amount_in).u128(), offer),
+            ];
+            let response = SwapResponse {
+                amount_in,
+                amount_out: request.amount_out,
+            };
+            Ok((EuclidResponse::ok(&response)?, refund(caller, payout)))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::EstimateRewards {
+            pool_id,
+            amount,
+            duration,
+        } => {
+            let pool = POOLS.load(deps.storage, &pool_id)?;
+            let rewards = (amount * pool.reward_rate) * Uint128::from(duration);
+            to_json_binary(&EstimateRewardsResponse { rewards })
+        }
+        QueryMsg::Pool { pool_id } => {
+            let pool = POOLS.load(deps.storage, &pool_id)?;
+            to_json_binary(&PoolResponse {
+                pool_id,
+                token_pair: pool.token_pair,
+                reserves: pool.reserves,
+                total_shares: pool.total_shares,
+            })
+        }
+        QueryMsg::Position { token_id } => {
+            let position = POSITIONS
+                .may_load(deps.storage, &token_id)?
+                .map(|position| PositionResponse {
+                    token_id,
+                    pool_id: position.pool_id,
+                    chain_id: position.chain_id,
+                    amount: position.amount,
+                });
+            to_json_binary(&position)
+        }
+        QueryMsg::SimulateAddLiquidity { pool_id, amounts } => {
+            // The part of the deposit the pool's price leaves unbalanced is
+            // the impact
+            let pool = POOLS.load(deps.storage, &pool_id)?;
+            let price_impact = if pool.reserves.0.is_zero() || pool.reserves.1.is_zero() {
+                Decimal::zero()
+            } else {
+                let sides = (
+                    amounts.0.full_mul(pool.reserves.1),
+                    amounts.1.full_mul(pool.reserves.0),
+                );
+                let (low, high) = (sides.0.min(sides.1), sides.0.max(sides.1));
+                if high.is_zero() {
+                    Decimal::zero()
+                } else {
+                    Decimal::one()
+                        - Decimal::try_from(Decimal256::from_ratio(low, high))
+                            .map_err(|err| StdError::generic_err(err.to_string()))?
+                }
+            };
+            to_json_binary(&SimulateAddLiquidityResponse {
+                shares: shares_for(&pool, amounts),
+                price_impact,
+            })
+        }
+        QueryMsg::SimulateSwap { route, amount_in } => {
+            // Quotes the route against the current reserves without moving them
+            let mut amount = amount_in;
+            for hop in route.windows(2) {
+                let (_, pool) = find_pool(deps.storage, &hop[0], &hop[1])
+                    .map_err(|err| StdError::generic_err(err.to_string()))?;
+                let (reserve_in, reserve_out) = reserves_from(&pool, &hop[0]);
+                amount = reserve_out.multiply_ratio(amount, reserve_in + amount);
+            }
+            to_json_binary(&SimulateSwapResponse { amount_out: amount })
+        }
+        QueryMsg::SimulateRemoveLiquidity { pool_id, amount } => {
+            let pool = POOLS.load(deps.storage, &pool_id)?;
+            if amount > pool.total_shares {
+                return Err(StdError::generic_err("Insufficient liquidity"));
+            }
+            let assets = vec![
+                Coin::new(
+                    pool.reserves
+                        .0
+                        .multiply_ratio(amount, pool.total_shares)
+                        .u128(),
+                    pool.token_pair.0,
+                ),
+                Coin::new(
+                    pool.reserves
+                        .1
+                        .multiply_ratio(amount, pool.total_shares)
+                        .u128(),
+                    pool.token_pair.1,
+                ),
+            ];
+            to_json_binary(&SimulateRemoveLiquidityResponse { assets })
+        }
+        QueryMsg::Pools { start_after, limit } => {
+            let limit = limit.unwrap_or(10) as usize;
+            let start = start_after.as_deref().map(Bound::exclusive);
+            let pools = POOLS
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(pool_id, pool)| pool_info(pool_id, pool)))
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&PoolsResponse { pools })
+        }
+        QueryMsg::PoolInfo { pool_id } => {
+            let pool = POOLS.load(deps.storage, &pool_id)?;
+            to_json_binary(&pool_info(pool_id, pool))
+        }
+        QueryMsg::Queue {} => {
+            let calls = QUEUE
+                .range(deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            to_json_binary(&QueueResponse { calls })
+        }
+    }
+}
+
+fn pool_info(pool_id: String, pool: Pool) -> PoolInfoResponse {
+    PoolInfoResponse {
+        pool_id,
+        token_pair: pool.token_pair,
+        reserves: pool.reserves,
+        total_shares: pool.total_shares,
+        fee_tier: pool.fee_tier,
+    }
+}
+
+fn load_pool(storage: &dyn Storage, pool_id: &str) -> Result<Pool, ContractError> {
+    POOLS
+        .may_load(storage, pool_id)?
+        .ok_or_else(|| ContractError::PoolNotFound {
+            pool_id: pool_id.to_string(),
+        })
+}
+
+fn load_position(storage: &dyn Storage, token_id: &str) -> Result<Position, ContractError> {
+    POSITIONS
+        .may_load(storage, token_id)?
+        .ok_or_else(|| ContractError::PositionNotFound {
+            token_id: token_id.to_string(),
+        })
+}
+
+// Applies `f` to a tracked position, dropping it once it is empty
+fn update_position(
+    storage: &mut dyn Storage,
+    token_id: &str,
+    f: impl FnOnce(&mut Position),
+) -> StdResult<()> {
+    if let Some(mut position) = POSITIONS.may_load(storage, token_id)? {
+        f(&mut position);
+        if position.amount.is_zero() {
+            POSITIONS.remove(storage, token_id);
+        } else {
+            POSITIONS.save(storage, token_id, &position)?;
+        }
+    }
+    Ok(())
+}
+
+// Shares a deposit of `deposited`, ordered like the pool's pair, mints. The
+// scarcer side sets them; an empty pool mints one per unit of the smaller side.
+fn shares_for(pool: &Pool, deposited: (Uint128, Uint128)) -> Uint128 {
+    if pool.total_shares.is_zero() || pool.reserves.0.is_zero() || pool.reserves.1.is_zero() {
+        return deposited.0.min(deposited.1);
+    }
+    deposited
+        .0
+        .multiply_ratio(pool.total_shares, pool.reserves.0)
+        .min(
+            deposited
+                .1
+                .multiply_ratio(pool.total_shares, pool.reserves.1),
+        )
+}
+
+// First pool trading the pair, in either direction
+fn find_pool(storage: &dyn Storage, a: &str, b: &str) -> Result<(String, Pool), ContractError> {
+    for item in POOLS.range(storage, None, None, Order::Ascending) {
+        let (pool_id, pool) = item?;
+        let (x, y) = (&pool.token_pair.0, &pool.token_pair.1);
+        if (x == a && y == b) || (x == b && y == a) {
+            return Ok((pool_id, pool));
+        }
+    }
+    Err(ContractError::NoPoolForPair(a.to_string(), b.to_string()))
+}
+
+fn route_ends(route: &[String]) -> Result<(&str, &str), ContractError> {
+    match (route.first(), route.last()) {
+        (Some(offer), Some(ask)) if route.len() >= 2 => Ok((offer, ask)),
+        _ => Err(ContractError::InvalidRoute {}),
+    }
+}
+
+// (reserve of the offered token, reserve of the asked token)
+fn reserves_from(pool: &Pool, offer: &str) -> (Uint128, Uint128) {
+    if pool.token_pair.0 == offer {
+        pool.reserves
+    } else {
+        (pool.reserves.1, pool.reserves.0)
+    }
+}
+
+fn set_reserves_from(pool: &mut Pool, offer: &str, reserve_in: Uint128, reserve_out: Uint128) {
+    pool.reserves = if pool.token_pair.0 == offer {
+        (reserve_in, reserve_out)
+    } else {
+        (reserve_out, reserve_in)
+    };
+}
+
+fn check_deadline(env: &Env, deadline: Option<u64>) -> Result<(), ContractError> {
+    match deadline {
+        Some(deadline) if env.block.time.seconds() > deadline => {
+            Err(ContractError::DeadlineExceeded {})
+        }
+        _ => Ok(()),
+    }
+}
+
+fn amount_of(funds: &[Coin], denom: &str) -> Uint128 {
+    funds
+        .iter()
+        .filter(|coin| coin.denom == denom)
+        .map(|coin| coin.amount)
+        .sum()
+}
+
+// Sends the non-zero coins to `to`
+// Returns the cw20 sides of a failed deposit
+fn refund_cw20(to: &Addr, msg: &EuclidMsg) -> StdResult<Vec<CosmosMsg>> {
+    if msg.action != EuclidAction::AddLiquidity {
+        return Ok(vec![]);
+    }
+    let request: AddLiquidityRequest = msg.parse()?;
+    request
+        .cw20_assets
+        .into_iter()
+        .map(|asset| {
+            Ok(WasmMsg::Execute {
+                contract_addr: asset.address,
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: to.to_string(),
+                    amount: asset.amount,
+                })?,
+                funds: vec![],
+            }
+            .into())
+        })
+        .collect()
+}
+
+fn refund(to: &Addr, coins: Vec<Coin>) -> Vec<CosmosMsg> {
+    let amount: Vec<Coin> = coins.into_iter().filter(|c| !c.amount.is_zero()).collect();
+    if amount.is_empty() {
+        return vec![];
+    }
+    vec![BankMsg::Send {
+        to_address: to.to_string(),
+        amount,
+    }
+    .into()]
+}
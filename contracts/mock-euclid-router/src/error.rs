@@ -0,0 +1,42 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Pool not found: {pool_id}")]
+    PoolNotFound { pool_id: String },
+
+    #[error("Position not found: {token_id}")]
+    PositionNotFound { token_id: String },
+
+    #[error("No pool for pair {0}/{1}")]
+    NoPoolForPair(String, String),
+
+    #[error("Invalid swap route")]
+    InvalidRoute {},
+
+    #[error("Insufficient liquidity")]
+    InsufficientLiquidity {},
+
+    #[error("Insufficient funds")]
+    InsufficientFunds {},
+
+    #[error("Slippage exceeded")]
+    SlippageExceeded {},
+
+    #[error("Deadline exceeded")]
+    DeadlineExceeded {},
+
+    #[error("Queued call {id} not found")]
+    QueuedCallNotFound { id: u64 },
+
+    #[error("Queued call {id} has no tx id to report back")]
+    MissingTxId { id: u64 },
+
+    // Raised on purpose by the `Revert` behaviour
+    #[error("{error}")]
+    Scripted { error: String },
+}
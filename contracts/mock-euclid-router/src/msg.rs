@@ -0,0 +1,123 @@
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use liquidity_wrapper::euclid::EuclidAction;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::state::QueuedCall;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {}
+
+/// How the router answers calls for an action
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Behaviour {
+    // Execute the call and reply with its result
+    Succeed,
+    // Refund the funds and reply with `success: false`
+    Fail { error: String },
+    // Return an error so the caller's submessage reverts
+    Revert { error: String },
+    // Queue the call, reply `accepted` and wait for `Complete`
+    Delay,
+}
+
+/// Scripting hooks for tests, only reachable through sudo
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    // Create or overwrite a pool, the router must hold the reserves
+    SetPool {
+        pool_id: String,
+        token_pair: (String, String),
+        reserves: (Uint128, Uint128),
+        total_shares: Uint128,
+    },
+    // Script how calls for `action` are answered
+    SetBehaviour {
+        action: EuclidAction,
+        behaviour: Behaviour,
+    },
+    // Finish a delayed call and report back to its caller, failing it when
+    // `error` is set
+    Complete {
+        id: u64,
+        error: Option<String>,
+    },
+    // Set the reward rate quoted by EstimateRewards
+    SetRewardRate {
+        pool_id: String,
+        rate: Decimal,
+    },
+    // Add claimable rewards to a pool, the router must hold them
+    AccrueRewards {
+        pool_id: String,
+        rewards: Vec<Coin>,
+    },
+    // Set what cross-chain moves report as fee and lost liquidity
+    SetTransferCost {
+        fee: Vec<Coin>,
+        slippage: Decimal,
+    },
+    // Overwrite the liquidity recorded for a position to simulate drift
+    SetPosition {
+        token_id: String,
+        amount: Uint128,
+    },
+    // Set the fee tier the factory queries report for a pool
+    SetFeeTier {
+        pool_id: String,
+        fee_tier: Decimal,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    // Same shape as `EuclidQueryMsg::EstimateRewards`
+    EstimateRewards {
+        pool_id: String,
+        amount: Uint128,
+        duration: u64,
+    },
+    // Same shape as `EuclidQueryMsg::Pool`
+    Pool {
+        pool_id: String,
+    },
+    // Same shape as `EuclidQueryMsg::Position`
+    Position {
+        token_id: String,
+    },
+    // Same shape as `EuclidQueryMsg::SimulateAddLiquidity`
+    SimulateAddLiquidity {
+        pool_id: String,
+        amounts: (Uint128, Uint128),
+    },
+    // Same shape as `EuclidQueryMsg::SimulateRemoveLiquidity`
+    SimulateRemoveLiquidity {
+        pool_id: String,
+        amount: Uint128,
+    },
+    // Same shape as `EuclidQueryMsg::SimulateSwap`
+    SimulateSwap {
+        route: Vec<String>,
+        amount_in: Uint128,
+    },
+    // Same shape as `EuclidFactoryQueryMsg::Pools`, so the router can stand
+    // in for the factory
+    Pools {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Same shape as `EuclidFactoryQueryMsg::PoolInfo`
+    PoolInfo {
+        pool_id: String,
+    },
+    // Calls held back by the `Delay` behaviour
+    Queue {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueueResponse {
+    pub calls: Vec<(u64, QueuedCall)>,
+}
@@ -0,0 +1,59 @@
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cw_storage_plus::{Item, Map};
+use liquidity_wrapper::euclid::EuclidMsg;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::Behaviour;
+
+// A simulated constant product pool
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Pool {
+    pub token_pair: (String, String),
+    pub reserves: (Uint128, Uint128),
+    pub total_shares: Uint128,
+    // Rewards quoted by EstimateRewards, per share per second
+    pub reward_rate: Decimal,
+    // Rewards paid out pro rata to shares on claim
+    pub accrued_rewards: Vec<Coin>,
+    // Reported by the factory queries, swaps are not charged it
+    #[serde(default)]
+    pub fee_tier: Decimal,
+}
+
+// A call held back by the `Delay` behaviour
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QueuedCall {
+    pub caller: Addr,
+    pub msg: EuclidMsg,
+    pub funds: Vec<Coin>,
+}
+
+// What a cross-chain move costs: a flat fee and a share of the liquidity
+// lost on the way
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct TransferCost {
+    pub fee: Vec<Coin>,
+    pub slippage: Decimal,
+}
+
+// Liquidity the router holds for a wrapper position
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Position {
+    pub pool_id: String,
+    pub chain_id: String,
+    pub amount: Uint128,
+}
+
+pub const POOLS: Map<&str, Pool> = Map::new("pools");
+
+// Positions by the token id the wrapper reserved for them
+pub const POSITIONS: Map<&str, Position> = Map::new("positions");
+
+pub const TRANSFER_COST: Item<TransferCost> = Item::new("transfer_cost");
+
+// Scripted behaviour per action, actions without one succeed
+pub const BEHAVIOURS: Map<&str, Behaviour> = Map::new("behaviours");
+
+pub const QUEUE: Map<u64, QueuedCall> = Map::new("queue");
+pub const QUEUE_COUNT: Item<u64> = Item::new("queue_count");
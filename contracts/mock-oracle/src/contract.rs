@@ -0,0 +1,67 @@
+use cosmwasm_std::{
+    to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdError, StdResult,
+};
+use cw2::set_contract_version;
+use liquidity_wrapper::oracle::PriceResponse;
+
+use crate::error::ContractError;
+use crate::msg::{InstantiateMsg, QueryMsg, SudoMsg};
+use crate::state::PRICES;
+
+// Contract name and version
+const CONTRACT_NAME: &str = "crates.io:mock-oracle";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new().add_attribute("method", "instantiate"))
+}
+
+// The oracle takes no calls, prices are scripted through sudo
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn execute(
+    _deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: Empty,
+) -> Result<Response, ContractError> {
+    Err(StdError::generic_err("Prices are set through sudo").into())
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::SetPrice {
+            denom,
+            quote_denom,
+            price,
+        } => {
+            PRICES.save(deps.storage, (&denom, &quote_denom), &price)?;
+            Ok(Response::new()
+                .add_attribute("action", "set_price")
+                .add_attribute("denom", denom)
+                .add_attribute("quote_denom", quote_denom)
+                .add_attribute("price", price.to_string()))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Price { denom, quote_denom } => {
+            let price = PRICES
+                .may_load(deps.storage, (&denom, &quote_denom))?
+                .ok_or_else(|| {
+                    StdError::generic_err(format!("No price for {denom} in {quote_denom}"))
+                })?;
+            to_json_binary(&PriceResponse { price })
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use cosmwasm_std::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {}
+
+/// Scripting hooks for tests, only reachable through sudo
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SudoMsg {
+    // Quote one unit of `denom` at `price` units of `quote_denom`
+    SetPrice {
+        denom: String,
+        quote_denom: String,
+        price: Decimal,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    // Same shape as `OracleQueryMsg::Price`
+    Price { denom: String, quote_denom: String },
+}
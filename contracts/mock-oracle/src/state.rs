@@ -0,0 +1,5 @@
+use cosmwasm_std::Decimal;
+use cw_storage_plus::Map;
+
+// Map (denom, quote denom) to the scripted price
+pub const PRICES: Map<(&str, &str), Decimal> = Map::new("prices");
@@ -1,101 +1,62 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128,
+    to_json_binary, Addr, Binary, BlockInfo, Deps, DepsMut, Empty, Env, MessageInfo, Order,
+    Response, StdError, StdResult, WasmMsg,
+};
+use cw2::set_contract_version;
+use cw721::{
+    AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, Cw721ReceiveMsg,
+    Expiration, NftInfoResponse, NumTokensResponse, OperatorsResponse, OwnerOfResponse,
+    TokensResponse,
+};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::msg::{
+    AttributesResponse, ExecuteMsg, InstantiateMsg, MinterResponse, PositionExecuteMsg,
+    PositionQueryMsg, PositionsResponse, QueryMsg, TransferHookMsg,
+};
+use crate::state::{
+    Approval, Attribute, Config, LiquidityPosition, TokenInfo, CONFIG, OPERATORS, OWNER_TOKENS,
+    TOKENS, TOTAL_SUPPLY,
 };
-use cw_storage_plus::{Item, Map};
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-
-// Contract state and configuration
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct Config {
-    pub name: String,
-    pub symbol: String,
-    pub admin: Addr,
-    pub euclid_router: Addr,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct LiquidityPosition {
-    pub pool_id: String,
-    pub token_pair: (String, String),
-    pub amount: Uint128,
-    pub chain_id: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct TokenInfo {
-    pub owner: Addr,
-    pub positions: Vec<LiquidityPosition>,
-    pub approved: Option<Addr>,
-    pub token_uri: Option<String>,
-}
-
-// State storage
-const CONFIG: Item<Config> = Item::new("config");
-const TOKENS: Map<&str, TokenInfo> = Map::new("tokens");
-const TOTAL_SUPPLY: Item<u64> = Item::new("total_supply");
-const OWNER_TOKENS: Map<&Addr, Vec<String>> = Map::new("owner_tokens");
-
-// Messages
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct InstantiateMsg {
-    pub name: String,
-    pub symbol: String,
-    pub euclid_router: String,
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-pub enum ExecuteMsg {
-    Mint {
-        positions: Vec<LiquidityPosition>,
-        token_uri: Option<String>,
-    },
-    Transfer {
-        recipient: String,
-        token_id: String,
-    },
-    Approve {
-        spender: String,
-        token_id: String,
-    },
-    Burn {
-        token_id: String,
-    },
-    UpdateLiquidityPosition {
-        token_id: String,
-        position_updates: Vec<LiquidityPosition>,
-    },
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-pub enum QueryMsg {
-    GetToken { token_id: String },
-    GetTokensByOwner { owner: String },
-    Config {},
-    TotalSupply {},
-}
+
+// Contract name and version for migration info
+const CONTRACT_NAME: &str = "crates.io:ul-nft-core";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Pagination defaults for enumerable queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
 
 // Contract instantiation
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     // Validate addresses
-    let euclid_router = deps.api.addr_validate(&msg.euclid_router)?;
+    let euclid_router = deps
+        .api
+        .addr_validate(&msg.euclid_router)
+        .map_err(|_| ContractError::InvalidEuclidRouter {})?;
+
+    let minter = match msg.minter {
+        Some(minter) => deps.api.addr_validate(&minter)?,
+        None => info.sender.clone(),
+    };
 
     // Create and store config
     let config = Config {
         name: msg.name,
         symbol: msg.symbol,
         admin: info.sender.clone(),
+        minter: minter.clone(),
+        transfer_hook: None,
         euclid_router,
     };
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     CONFIG.save(deps.storage, &config)?;
 
     // Initialize total supply
@@ -103,40 +64,150 @@ pub fn instantiate(
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
-        .add_attribute("owner", info.sender))
+        .add_attribute("owner", info.sender)
+        .add_attribute("minter", minter))
 }
 
 // Execute entry point
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Mint {
-            positions,
-            token_uri,
-        } => execute_mint(deps, env, info, positions, token_uri),
-        ExecuteMsg::Transfer {
+        ExecuteMsg::TransferNft {
             recipient,
             token_id,
-        } => execute_transfer(deps, env, info, recipient, token_id),
-        ExecuteMsg::Approve { spender, token_id } => {
-            execute_approve(deps, env, info, spender, token_id)
-        }
-        ExecuteMsg::Burn { token_id } => execute_burn(deps, env, info, token_id),
-        ExecuteMsg::UpdateLiquidityPosition {
+        } => execute_transfer_nft(deps, env, info, recipient, token_id),
+        ExecuteMsg::SendNft {
+            contract,
+            token_id,
+            msg,
+        } => execute_send_nft(deps, env, info, contract, token_id, msg),
+        ExecuteMsg::Approve {
+            spender,
             token_id,
-            position_updates,
-        } => execute_update_position(deps, env, info, token_id, position_updates),
+            expires,
+        } => execute_approve(deps, env, info, spender, token_id, expires),
+        ExecuteMsg::Revoke { spender, token_id } => {
+            execute_revoke(deps, env, info, spender, token_id)
+        }
+        ExecuteMsg::ApproveAll { operator, expires } => {
+            execute_approve_all(deps, env, info, operator, expires)
+        }
+        ExecuteMsg::RevokeAll { operator } => execute_revoke_all(deps, env, info, operator),
+        ExecuteMsg::Extension { msg } => match msg {
+            PositionExecuteMsg::Mint {
+                token_id,
+                owner,
+                positions,
+                token_uri,
+            } => execute_mint(deps, env, info, token_id, owner, positions, token_uri),
+            PositionExecuteMsg::UpdateLiquidityPosition {
+                token_id,
+                position_updates,
+            } => execute_update_position(deps, env, info, token_id, position_updates),
+            PositionExecuteMsg::Redeem { token_id } => execute_redeem(deps, env, info, token_id),
+            PositionExecuteMsg::SetAttribute {
+                token_id,
+                attribute,
+            } => execute_set_attribute(deps, info, token_id, attribute),
+            PositionExecuteMsg::RemoveAttribute {
+                token_id,
+                trait_type,
+            } => execute_remove_attribute(deps, info, token_id, trait_type),
+            PositionExecuteMsg::UpdateMinter { minter } => {
+                execute_update_minter(deps, info, minter)
+            }
+            PositionExecuteMsg::UpdateTransferHook { transfer_hook } => {
+                execute_update_transfer_hook(deps, info, transfer_hook)
+            }
+        },
     }
 }
 
 // Query entry point
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetToken { token_id } => to_json_binary(&query_token(deps, token_id)?),
-        QueryMsg::GetTokensByOwner { owner } => to_json_binary(&query_tokens_by_owner(deps, owner)?),
-        QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
-        QueryMsg::TotalSupply {} => to_json_binary(&TOTAL_SUPPLY.load(deps.storage)?),
+        QueryMsg::OwnerOf {
+            token_id,
+            include_expired,
+        } => to_json_binary(&query_owner_of(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Approval {
+            token_id,
+            spender,
+            include_expired,
+        } => to_json_binary(&query_approval(
+            deps,
+            env,
+            token_id,
+            spender,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Approvals {
+            token_id,
+            include_expired,
+        } => to_json_binary(&query_approvals(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::AllOperators {
+            owner,
+            include_expired,
+            start_after,
+            limit,
+        } => to_json_binary(&query_operators(
+            deps,
+            env,
+            owner,
+            include_expired.unwrap_or(false),
+            start_after,
+            limit,
+        )?),
+        QueryMsg::NumTokens {} => to_json_binary(&query_num_tokens(deps)?),
+        QueryMsg::ContractInfo {} => to_json_binary(&query_contract_info(deps)?),
+        QueryMsg::NftInfo { token_id } => to_json_binary(&query_nft_info(deps, token_id)?),
+        QueryMsg::AllNftInfo {
+            token_id,
+            include_expired,
+        } => to_json_binary(&query_all_nft_info(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::Tokens {
+            owner,
+            start_after,
+            limit,
+        } => to_json_binary(&query_tokens(deps, owner, start_after, limit)?),
+        QueryMsg::AllTokens { start_after, limit } => {
+            to_json_binary(&query_all_tokens(deps, start_after, limit)?)
+        }
+        QueryMsg::Minter {} => to_json_binary(&query_minter(deps)?),
+        QueryMsg::Extension { msg } => match msg {
+            PositionQueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+            PositionQueryMsg::Positions { token_id } => {
+                to_json_binary(&query_positions(deps, token_id)?)
+            }
+            PositionQueryMsg::Attributes { token_id } => {
+                let token = TOKENS.load(deps.storage, &token_id)?;
+                to_json_binary(&AttributesResponse {
+                    token_id,
+                    attributes: token.attributes,
+                })
+            }
+        },
     }
 }
 
@@ -145,77 +216,198 @@ pub fn execute_mint(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    token_id: String,
+    owner: String,
     positions: Vec<LiquidityPosition>,
     token_uri: Option<String>,
-) -> StdResult<Response> {
-    // Load config and total supply
-    let mut total_supply = TOTAL_SUPPLY.load(deps.storage)?;
+) -> Result<Response, ContractError> {
+    // Only the minter can create tokens
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.minter {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    // Generate new token ID
-    total_supply += 1;
-    let token_id = format!("ulnft{}", total_supply);
+    let owner = deps.api.addr_validate(&owner)?;
+    if token_id.is_empty() {
+        return Err(ContractError::InvalidTokenId {});
+    }
+    if TOKENS.has(deps.storage, &token_id) {
+        return Err(ContractError::TokenAlreadyExists {});
+    }
+    validate_positions(&positions)?;
 
     // Create token info
     let token = TokenInfo {
-        owner: info.sender.clone(),
+        owner: owner.clone(),
         positions,
-        approved: None,
+        approvals: vec![],
         token_uri,
+        attributes: vec![],
     };
 
     // Update storage
     TOKENS.save(deps.storage, &token_id, &token)?;
-    TOTAL_SUPPLY.save(deps.storage, &total_supply)?;
-
-    // Update owner tokens
-    let mut owner_tokens = OWNER_TOKENS
-        .may_load(deps.storage, &info.sender)?
-        .unwrap_or_default();
-    owner_tokens.push(token_id.clone());
-    OWNER_TOKENS.save(deps.storage, &info.sender, &owner_tokens)?;
+    OWNER_TOKENS.save(deps.storage, (&owner, &token_id), &Empty {})?;
+    TOTAL_SUPPLY.update(deps.storage, |supply| -> StdResult<_> { Ok(supply + 1) })?;
 
     Ok(Response::new()
         .add_attribute("action", "mint")
         .add_attribute("token_id", token_id)
-        .add_attribute("owner", info.sender))
+        .add_attribute("minter", info.sender)
+        .add_attribute("owner", owner))
+}
+
+// A token must be backed by at least one non-empty position
+fn validate_positions(positions: &[LiquidityPosition]) -> Result<(), ContractError> {
+    if positions.is_empty()
+        || positions
+            .iter()
+            .any(|pos| pos.amount.is_zero() || pos.pool_id.is_empty() || pos.chain_id.is_empty())
+    {
+        return Err(ContractError::InvalidLiquidityPosition {});
+    }
+    Ok(())
 }
 
-pub fn execute_transfer(
+pub fn execute_update_minter(
     deps: DepsMut,
-    _env: Env,
+    info: MessageInfo,
+    minter: String,
+) -> Result<Response, ContractError> {
+    // Only admin can change the minter
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.minter = deps.api.addr_validate(&minter)?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_minter")
+        .add_attribute("minter", minter))
+}
+
+pub fn execute_update_transfer_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    transfer_hook: Option<String>,
+) -> Result<Response, ContractError> {
+    // Only admin can change the transfer hook
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.transfer_hook = transfer_hook
+        .as_deref()
+        .map(|addr| deps.api.addr_validate(addr))
+        .transpose()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_transfer_hook")
+        .add_attribute("transfer_hook", transfer_hook.unwrap_or_default()))
+}
+
+pub fn execute_transfer_nft(
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     recipient: String,
     token_id: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
     let recipient = deps.api.addr_validate(&recipient)?;
+    let previous_owner = transfer_token(deps.branch(), &env, &info, &recipient, &token_id)?;
+    let hook_msgs = transfer_hook_msgs(deps.as_ref(), &token_id, &previous_owner, &recipient)?;
 
-    // Load and validate token
-    let mut token = TOKENS.load(deps.storage, &token_id)?;
-    if token.owner != info.sender {
-        return Err(StdError::generic_err("Unauthorized"));
+    Ok(Response::new()
+        .add_messages(hook_msgs)
+        .add_attribute("action", "transfer_nft")
+        .add_attribute("token_id", token_id)
+        .add_attribute("sender", info.sender)
+        .add_attribute("from", previous_owner)
+        .add_attribute("recipient", recipient))
+}
+
+pub fn execute_send_nft(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    token_id: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let contract_addr = deps.api.addr_validate(&contract)?;
+    let previous_owner = transfer_token(deps.branch(), &env, &info, &contract_addr, &token_id)?;
+    let hook_msgs = transfer_hook_msgs(deps.as_ref(), &token_id, &previous_owner, &contract_addr)?;
+
+    // Notify the receiving contract
+    let receive_msg = Cw721ReceiveMsg {
+        sender: info.sender.to_string(),
+        token_id: token_id.clone(),
+        msg,
     }
+    .into_cosmos_msg(contract_addr.clone())?;
+
+    Ok(Response::new()
+        .add_messages(hook_msgs)
+        .add_message(receive_msg)
+        .add_attribute("action", "send_nft")
+        .add_attribute("token_id", token_id)
+        .add_attribute("sender", info.sender)
+        .add_attribute("from", previous_owner)
+        .add_attribute("recipient", contract_addr))
+}
 
-    // Update owner tokens lists
-    let mut old_owner_tokens = OWNER_TOKENS.load(deps.storage, &info.sender)?;
-    old_owner_tokens.retain(|t| t != &token_id);
-    OWNER_TOKENS.save(deps.storage, &info.sender, &old_owner_tokens)?;
+// Moves a token to `recipient`, clearing its approvals, and returns the previous owner
+fn transfer_token(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    recipient: &Addr,
+    token_id: &str,
+) -> Result<Addr, ContractError> {
+    // Load and validate token
+    let mut token = TOKENS
+        .may_load(deps.storage, token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    check_can_send(deps.as_ref(), env, info, &token)?;
+    let previous_owner = token.owner.clone();
 
-    let mut new_owner_tokens = OWNER_TOKENS
-        .may_load(deps.storage, &recipient)?
-        .unwrap_or_default();
-    new_owner_tokens.push(token_id.clone());
-    OWNER_TOKENS.save(deps.storage, &recipient, &new_owner_tokens)?;
+    // Update owner tokens index
+    OWNER_TOKENS.remove(deps.storage, (&previous_owner, token_id));
+    OWNER_TOKENS.save(deps.storage, (recipient, token_id), &Empty {})?;
 
     // Update token owner
     token.owner = recipient.clone();
-    token.approved = None;
-    TOKENS.save(deps.storage, &token_id, &token)?;
+    token.approvals = vec![];
+    TOKENS.save(deps.storage, token_id, &token)?;
 
-    Ok(Response::new()
-        .add_attribute("action", "transfer")
-        .add_attribute("token_id", token_id)
-        .add_attribute("from", info.sender)
-        .add_attribute("to", recipient))
+    Ok(previous_owner)
+}
+
+// Notifies the transfer hook, if any, that a token changed hands
+fn transfer_hook_msgs(
+    deps: Deps,
+    token_id: &str,
+    previous_owner: &Addr,
+    new_owner: &Addr,
+) -> StdResult<Vec<WasmMsg>> {
+    let config = CONFIG.load(deps.storage)?;
+    let Some(hook) = config.transfer_hook else {
+        return Ok(vec![]);
+    };
+
+    Ok(vec![WasmMsg::Execute {
+        contract_addr: hook.to_string(),
+        msg: to_json_binary(&TransferHookMsg::NftTransferred {
+            token_id: token_id.to_string(),
+            previous_owner: previous_owner.to_string(),
+            new_owner: new_owner.to_string(),
+        })?,
+        funds: vec![],
+    }])
 }
 
 pub fn execute_update_position(
@@ -224,13 +416,19 @@ pub fn execute_update_position(
     info: MessageInfo,
     token_id: String,
     position_updates: Vec<LiquidityPosition>,
-) -> StdResult<Response> {
-    // Load and validate token
-    let mut token = TOKENS.load(deps.storage, &token_id)?;
-    if token.owner != info.sender {
-        return Err(StdError::generic_err("Unauthorized"));
+) -> Result<Response, ContractError> {
+    // Only the minter can change what backs a token
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.minter {
+        return Err(ContractError::Unauthorized {});
     }
 
+    // Load and validate token
+    let mut token = TOKENS
+        .may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    validate_positions(&position_updates)?;
+
     // Update positions
     token.positions = position_updates;
     TOKENS.save(deps.storage, &token_id, &token)?;
@@ -240,35 +438,89 @@ pub fn execute_update_position(
         .add_attribute("token_id", token_id))
 }
 
-// Query functions implementation
-fn query_token(deps: Deps, token_id: String) -> StdResult<TokenInfo> {
-    TOKENS.load(deps.storage, &token_id)
+pub fn execute_set_attribute(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_id: String,
+    attribute: Attribute,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.minter {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut token = TOKENS
+        .may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    token
+        .attributes
+        .retain(|existing| existing.trait_type != attribute.trait_type);
+    token.attributes.push(attribute.clone());
+    TOKENS.save(deps.storage, &token_id, &token)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_attribute")
+        .add_attribute("token_id", token_id)
+        .add_attribute("trait_type", attribute.trait_type)
+        .add_attribute("value", attribute.value))
 }
 
-fn query_tokens_by_owner(deps: Deps, owner: String) -> StdResult<Vec<String>> {
-    let owner = deps.api.addr_validate(&owner)?;
-    Ok(OWNER_TOKENS
-        .may_load(deps.storage, &owner)?
-        .unwrap_or_default())
+pub fn execute_remove_attribute(
+    deps: DepsMut,
+    info: MessageInfo,
+    token_id: String,
+    trait_type: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.minter {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut token = TOKENS
+        .may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    token
+        .attributes
+        .retain(|existing| existing.trait_type != trait_type);
+    TOKENS.save(deps.storage, &token_id, &token)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_attribute")
+        .add_attribute("token_id", token_id)
+        .add_attribute("trait_type", trait_type))
 }
 
 pub fn execute_approve(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     spender: String,
     token_id: String,
-) -> StdResult<Response> {
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
     let spender_addr = deps.api.addr_validate(&spender)?;
 
     // Load and validate token
-    let mut token = TOKENS.load(deps.storage, &token_id)?;
-    if token.owner != info.sender {
-        return Err(StdError::generic_err("Unauthorized"));
+    let mut token = TOKENS
+        .may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    check_can_approve(deps.as_ref(), &env, &info, &token)?;
+    if spender_addr == token.owner {
+        return Err(ContractError::CannotSetApprovalForOwnAccount {});
+    }
+
+    // Reject approvals that would already be expired
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
     }
 
-    // Set approval
-    token.approved = Some(spender_addr.clone());
+    // Replace any existing approval for this spender
+    token.approvals.retain(|apr| apr.spender != spender_addr);
+    token.approvals.push(Approval {
+        spender: spender_addr,
+        expires,
+    });
     TOKENS.save(deps.storage, &token_id, &token)?;
 
     Ok(Response::new()
@@ -277,32 +529,1016 @@ pub fn execute_approve(
         .add_attribute("spender", spender))
 }
 
-pub fn execute_burn(
+pub fn execute_revoke(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
+    spender: String,
     token_id: String,
-) -> StdResult<Response> {
+) -> Result<Response, ContractError> {
+    let spender_addr = deps.api.addr_validate(&spender)?;
+
     // Load and validate token
-    let token = TOKENS.load(deps.storage, &token_id)?;
-    if token.owner != info.sender {
-        return Err(StdError::generic_err("Unauthorized"));
+    let mut token = TOKENS
+        .may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    check_can_approve(deps.as_ref(), &env, &info, &token)?;
+
+    // Remove approval
+    if !token
+        .approvals
+        .iter()
+        .any(|apr| apr.spender == spender_addr)
+    {
+        return Err(ContractError::ApprovalNotFound { spender });
     }
+    token.approvals.retain(|apr| apr.spender != spender_addr);
+    TOKENS.save(deps.storage, &token_id, &token)?;
 
-    // Remove token
-    TOKENS.remove(deps.storage, &token_id);
+    Ok(Response::new()
+        .add_attribute("action", "revoke")
+        .add_attribute("token_id", token_id)
+        .add_attribute("spender", spender))
+}
+
+pub fn execute_approve_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let operator_addr = deps.api.addr_validate(&operator)?;
+    if operator_addr == info.sender {
+        return Err(ContractError::CannotSetApprovalForOwnAccount {});
+    }
 
-    // Update owner tokens
-    let mut owner_tokens = OWNER_TOKENS.load(deps.storage, &info.sender)?;
-    owner_tokens.retain(|t| t != &token_id);
-    OWNER_TOKENS.save(deps.storage, &info.sender, &owner_tokens)?;
+    // Reject grants that would already be expired
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::Expired {});
+    }
 
-    // Update total supply
-    let mut total_supply = TOTAL_SUPPLY.load(deps.storage)?;
-    total_supply -= 1;
-    TOTAL_SUPPLY.save(deps.storage, &total_supply)?;
+    OPERATORS.save(deps.storage, (&info.sender, &operator_addr), &expires)?;
 
     Ok(Response::new()
-        .add_attribute("action", "burn")
-        .add_attribute("token_id", token_id))
+        .add_attribute("action", "approve_all")
+        .add_attribute("sender", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_revoke_all(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator_addr = deps.api.addr_validate(&operator)?;
+    OPERATORS.remove(deps.storage, (&info.sender, &operator_addr));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_all")
+        .add_attribute("sender", info.sender)
+        .add_attribute("operator", operator))
+}
+
+pub fn execute_redeem(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    // Only the minter knows when the backing liquidity is gone
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.minter {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let token = TOKENS
+        .may_load(deps.storage, &token_id)?
+        .ok_or(ContractError::TokenNotFound {})?;
+    remove_token(deps, &token_id, &token)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "redeem")
+        .add_attribute("token_id", token_id)
+        .add_attribute("owner", token.owner))
+}
+
+fn remove_token(deps: DepsMut, token_id: &str, token: &TokenInfo) -> Result<(), ContractError> {
+    TOKENS.remove(deps.storage, token_id);
+    OWNER_TOKENS.remove(deps.storage, (&token.owner, token_id));
+
+    // Update total supply
+    TOTAL_SUPPLY.update(deps.storage, |supply| -> StdResult<_> { Ok(supply - 1) })?;
+    Ok(())
+}
+
+// Returns true if `operator` holds an unexpired ApproveAll grant from `owner`
+fn is_operator(deps: Deps, env: &Env, owner: &Addr, operator: &Addr) -> StdResult<bool> {
+    Ok(OPERATORS
+        .may_load(deps.storage, (owner, operator))?
+        .map(|expires| !expires.is_expired(&env.block))
+        .unwrap_or(false))
+}
+
+// The owner or one of its operators may manage approvals on a token
+pub fn check_can_approve(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    token: &TokenInfo,
+) -> Result<(), ContractError> {
+    if token.owner == info.sender || is_operator(deps, env, &token.owner, &info.sender)? {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+// The owner, an unexpired approved spender or an operator may transfer or send a token
+pub fn check_can_send(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    token: &TokenInfo,
+) -> Result<(), ContractError> {
+    if token.owner == info.sender {
+        return Ok(());
+    }
+    if token
+        .approvals
+        .iter()
+        .any(|apr| apr.spender == info.sender && !apr.is_expired(&env.block))
+    {
+        return Ok(());
+    }
+    if is_operator(deps, env, &token.owner, &info.sender)? {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+// Query functions implementation
+fn humanize_approvals(
+    block: &BlockInfo,
+    token: &TokenInfo,
+    include_expired: bool,
+) -> Vec<cw721::Approval> {
+    token
+        .approvals
+        .iter()
+        .filter(|apr| include_expired || !apr.is_expired(block))
+        .map(|apr| cw721::Approval {
+            spender: apr.spender.to_string(),
+            expires: apr.expires,
+        })
+        .collect()
+}
+
+fn query_owner_of(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: bool,
+) -> StdResult<OwnerOfResponse> {
+    let token = TOKENS.load(deps.storage, &token_id)?;
+    Ok(OwnerOfResponse {
+        owner: token.owner.to_string(),
+        approvals: humanize_approvals(&env.block, &token, include_expired),
+    })
+}
+
+fn query_approval(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    spender: String,
+    include_expired: bool,
+) -> StdResult<ApprovalResponse> {
+    let token = TOKENS.load(deps.storage, &token_id)?;
+
+    // The owner always has full access to its own token
+    if token.owner == spender {
+        return Ok(ApprovalResponse {
+            approval: cw721::Approval {
+                spender,
+                expires: Expiration::Never {},
+            },
+        });
+    }
+
+    if let Some(approval) = humanize_approvals(&env.block, &token, include_expired)
+        .into_iter()
+        .find(|apr| apr.spender == spender)
+    {
+        return Ok(ApprovalResponse { approval });
+    }
+
+    // Operators of the owner are approved for every token it holds
+    let spender_addr = deps.api.addr_validate(&spender)?;
+    match OPERATORS.may_load(deps.storage, (&token.owner, &spender_addr))? {
+        Some(expires) if include_expired || !expires.is_expired(&env.block) => {
+            Ok(ApprovalResponse {
+                approval: cw721::Approval { spender, expires },
+            })
+        }
+        _ => Err(StdError::not_found("Approval not found")),
+    }
+}
+
+fn query_approvals(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: bool,
+) -> StdResult<ApprovalsResponse> {
+    let token = TOKENS.load(deps.storage, &token_id)?;
+    Ok(ApprovalsResponse {
+        approvals: humanize_approvals(&env.block, &token, include_expired),
+    })
+}
+
+fn query_operators(
+    deps: Deps,
+    env: Env,
+    owner: String,
+    include_expired: bool,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OperatorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let owner = deps.api.addr_validate(&owner)?;
+    let start_addr = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?;
+    let start = start_addr.as_ref().map(Bound::exclusive);
+
+    let operators = OPERATORS
+        .prefix(&owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, expires)) => include_expired || !expires.is_expired(&env.block),
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|item| {
+            let (spender, expires) = item?;
+            Ok(cw721::Approval {
+                spender: spender.to_string(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(OperatorsResponse { operators })
+}
+
+fn query_num_tokens(deps: Deps) -> StdResult<NumTokensResponse> {
+    Ok(NumTokensResponse {
+        count: TOTAL_SUPPLY.load(deps.storage)?,
+    })
+}
+
+fn query_contract_info(deps: Deps) -> StdResult<ContractInfoResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ContractInfoResponse {
+        name: config.name,
+        symbol: config.symbol,
+    })
+}
+
+fn query_nft_info(
+    deps: Deps,
+    token_id: String,
+) -> StdResult<NftInfoResponse<Vec<LiquidityPosition>>> {
+    let token = TOKENS.load(deps.storage, &token_id)?;
+    Ok(NftInfoResponse {
+        token_uri: token.token_uri,
+        extension: token.positions,
+    })
+}
+
+fn query_all_nft_info(
+    deps: Deps,
+    env: Env,
+    token_id: String,
+    include_expired: bool,
+) -> StdResult<AllNftInfoResponse<Vec<LiquidityPosition>>> {
+    let token = TOKENS.load(deps.storage, &token_id)?;
+    Ok(AllNftInfoResponse {
+        access: OwnerOfResponse {
+            owner: token.owner.to_string(),
+            approvals: humanize_approvals(&env.block, &token, include_expired),
+        },
+        info: NftInfoResponse {
+            token_uri: token.token_uri,
+            extension: token.positions,
+        },
+    })
+}
+
+fn query_tokens(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let owner = deps.api.addr_validate(&owner)?;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let tokens = OWNER_TOKENS
+        .prefix(&owner)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TokensResponse { tokens })
+}
+
+fn query_all_tokens(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+
+    let tokens = TOKENS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TokensResponse { tokens })
+}
+
+fn query_minter(deps: Deps) -> StdResult<MinterResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(MinterResponse {
+        minter: config.minter.to_string(),
+    })
+}
+
+fn query_positions(deps: Deps, token_id: String) -> StdResult<PositionsResponse> {
+    let token = TOKENS.load(deps.storage, &token_id)?;
+    Ok(PositionsResponse {
+        token_id,
+        positions: token.positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{from_json, to_json_binary, CosmosMsg, OwnedDeps, Uint128, WasmMsg};
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            name: "Unified Liquidity NFT".to_string(),
+            symbol: "ULNFT".to_string(),
+            minter: Some("wrapper".to_string()),
+            euclid_router: "router".to_string(),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        deps
+    }
+
+    fn position(amount: u128) -> LiquidityPosition {
+        LiquidityPosition {
+            pool_id: "pool1".to_string(),
+            token_pair: ("uarch".to_string(), "uusdc".to_string()),
+            amount: Uint128::new(amount),
+            chain_id: "archway".to_string(),
+        }
+    }
+
+    fn mint(deps: DepsMut, owner: &str) -> String {
+        let token_id = format!("ulp-{}", owner);
+        let msg = ExecuteMsg::Extension {
+            msg: PositionExecuteMsg::Mint {
+                token_id: token_id.clone(),
+                owner: owner.to_string(),
+                positions: vec![position(1000)],
+                token_uri: None,
+            },
+        };
+        execute(deps, mock_env(), mock_info("wrapper", &[]), msg).unwrap();
+        token_id
+    }
+
+    #[test]
+    fn cw721_queries() {
+        let mut deps = setup();
+        let token_id = mint(deps.as_mut(), "alice");
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::ContractInfo {}).unwrap();
+        let info: ContractInfoResponse = from_json(&res).unwrap();
+        assert_eq!(info.symbol, "ULNFT");
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::NumTokens {}).unwrap();
+        let num: NumTokensResponse = from_json(&res).unwrap();
+        assert_eq!(num.count, 1);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AllNftInfo {
+                token_id: token_id.clone(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+        let all: AllNftInfoResponse<Vec<LiquidityPosition>> = from_json(&res).unwrap();
+        assert_eq!(all.access.owner, "alice");
+        assert_eq!(all.info.extension[0].pool_id, "pool1");
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Tokens {
+                owner: "alice".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let tokens: TokensResponse = from_json(&res).unwrap();
+        assert_eq!(tokens.tokens, vec![token_id]);
+    }
+
+    #[test]
+    fn transfer_and_send_nft() {
+        let mut deps = setup();
+        let token_id = mint(deps.as_mut(), "alice");
+
+        // Only the owner can transfer
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::TransferNft {
+                recipient: "bob".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::TransferNft {
+                recipient: "bob".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap();
+
+        // Sending to a contract triggers ReceiveNft on it
+        let payload = to_json_binary("hook").unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::SendNft {
+                contract: "escrow".to_string(),
+                token_id: token_id.clone(),
+                msg: payload.clone(),
+            },
+        )
+        .unwrap();
+        let expected = Cw721ReceiveMsg {
+            sender: "bob".to_string(),
+            token_id: token_id.clone(),
+            msg: payload,
+        };
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "escrow".to_string(),
+                msg: expected.into_binary().unwrap(),
+                funds: vec![],
+            })
+        );
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::OwnerOf {
+                token_id,
+                include_expired: None,
+            },
+        )
+        .unwrap();
+        let owner: OwnerOfResponse = from_json(&res).unwrap();
+        assert_eq!(owner.owner, "escrow");
+    }
+
+    #[test]
+    fn approvals_and_operators_are_listed() {
+        let mut deps = setup();
+        let token_id = mint(deps.as_mut(), "alice");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Approve {
+                spender: "market".to_string(),
+                token_id: token_id.clone(),
+                expires: Some(Expiration::AtHeight(mock_env().block.height + 10)),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::ApproveAll {
+                operator: "vault".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Approval {
+                token_id: token_id.clone(),
+                spender: "market".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+        let approval: ApprovalResponse = from_json(&res).unwrap();
+        assert_eq!(approval.approval.spender, "market");
+
+        // Expired approvals are hidden unless requested
+        let mut later = mock_env();
+        later.block.height += 20;
+        let res = query(
+            deps.as_ref(),
+            later.clone(),
+            QueryMsg::Approvals {
+                token_id: token_id.clone(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+        let approvals: ApprovalsResponse = from_json(&res).unwrap();
+        assert!(approvals.approvals.is_empty());
+        let res = query(
+            deps.as_ref(),
+            later,
+            QueryMsg::Approvals {
+                token_id: token_id.clone(),
+                include_expired: Some(true),
+            },
+        )
+        .unwrap();
+        let approvals: ApprovalsResponse = from_json(&res).unwrap();
+        assert_eq!(approvals.approvals.len(), 1);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AllOperators {
+                owner: "alice".to_string(),
+                include_expired: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let operators: OperatorsResponse = from_json(&res).unwrap();
+        assert_eq!(operators.operators[0].spender, "vault");
+
+        // Revoking removes the approval again
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Revoke {
+                spender: "market".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap();
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Approvals {
+                token_id,
+                include_expired: Some(true),
+            },
+        )
+        .unwrap();
+        let approvals: ApprovalsResponse = from_json(&res).unwrap();
+        assert!(approvals.approvals.is_empty());
+    }
+
+    #[test]
+    fn approved_spenders_and_operators_can_transfer() {
+        let mut deps = setup();
+        let token_id = mint(deps.as_mut(), "alice");
+        let expires = Expiration::AtHeight(mock_env().block.height + 10);
+
+        // Approvals cannot be created already expired
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Approve {
+                spender: "market".to_string(),
+                token_id: token_id.clone(),
+                expires: Some(Expiration::AtHeight(mock_env().block.height)),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Expired {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Approve {
+                spender: "market".to_string(),
+                token_id: token_id.clone(),
+                expires: Some(expires),
+            },
+        )
+        .unwrap();
+
+        // The approval stops working once it expires
+        let mut later = mock_env();
+        later.block.height += 10;
+        let err = execute(
+            deps.as_mut(),
+            later,
+            mock_info("market", &[]),
+            ExecuteMsg::TransferNft {
+                recipient: "bob".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("market", &[]),
+            ExecuteMsg::TransferNft {
+                recipient: "bob".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap();
+
+        // Approvals do not survive a transfer
+        let token = TOKENS.load(deps.as_ref().storage, &token_id).unwrap();
+        assert_eq!(token.owner, Addr::unchecked("bob"));
+        assert!(token.approvals.is_empty());
+
+        // An operator can approve others on behalf of the owner
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::ApproveAll {
+                operator: "vault".to_string(),
+                expires: None,
+            },
+        )
+        .unwrap();
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Approval {
+                token_id: token_id.clone(),
+                spender: "vault".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap();
+        let approval: ApprovalResponse = from_json(&res).unwrap();
+        assert_eq!(approval.approval.expires, Expiration::Never {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("vault", &[]),
+            ExecuteMsg::Approve {
+                spender: "escrow".to_string(),
+                token_id: token_id.clone(),
+                expires: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::RevokeAll {
+                operator: "vault".to_string(),
+            },
+        )
+        .unwrap();
+        let transfer = ExecuteMsg::TransferNft {
+            recipient: "carol".to_string(),
+            token_id: token_id.clone(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("vault", &[]),
+            transfer.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // The approval the operator granted outlives the operator
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("escrow", &[]),
+            transfer,
+        )
+        .unwrap();
+        let token = TOKENS.load(deps.as_ref().storage, &token_id).unwrap();
+        assert_eq!(token.owner, Addr::unchecked("carol"));
+    }
+
+    #[test]
+    fn backed_tokens_cannot_be_burnt_by_holders() {
+        // Only Redeem destroys a token, the cw721 Burn is not accepted
+        let burn = r#"{"burn":{"token_id":"ulp-alice"}}"#;
+        assert!(from_json::<ExecuteMsg>(burn.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn only_minter_mints_and_updates_positions() {
+        let mut deps = setup();
+        let mint_msg = ExecuteMsg::Extension {
+            msg: PositionExecuteMsg::Mint {
+                token_id: "ulp-1".to_string(),
+                owner: "alice".to_string(),
+                positions: vec![position(1000)],
+                token_uri: None,
+            },
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            mint_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // Positions without liquidity cannot back a token
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrapper", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::Mint {
+                    token_id: "ulp-1".to_string(),
+                    owner: "alice".to_string(),
+                    positions: vec![position(0)],
+                    token_uri: None,
+                },
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidLiquidityPosition {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrapper", &[]),
+            mint_msg.clone(),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrapper", &[]),
+            mint_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TokenAlreadyExists {});
+
+        // The owner cannot rewrite its own positions
+        let update_msg = ExecuteMsg::Extension {
+            msg: PositionExecuteMsg::UpdateLiquidityPosition {
+                token_id: "ulp-1".to_string(),
+                position_updates: vec![position(5000)],
+            },
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            update_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // Only the admin can hand over the minter role
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrapper", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::UpdateMinter {
+                    minter: "new_wrapper".to_string(),
+                },
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::UpdateMinter {
+                    minter: "new_wrapper".to_string(),
+                },
+            },
+        )
+        .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Minter {}).unwrap();
+        let minter: MinterResponse = from_json(&res).unwrap();
+        assert_eq!(minter.minter, "new_wrapper");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_wrapper", &[]),
+            update_msg,
+        )
+        .unwrap();
+        let token = TOKENS.load(deps.as_ref().storage, "ulp-1").unwrap();
+        assert_eq!(token.positions, vec![position(5000)]);
+
+        // Redeeming burns the token once its liquidity is withdrawn
+        let redeem_msg = ExecuteMsg::Extension {
+            msg: PositionExecuteMsg::Redeem {
+                token_id: "ulp-1".to_string(),
+            },
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            redeem_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("new_wrapper", &[]),
+            redeem_msg,
+        )
+        .unwrap();
+        assert!(!TOKENS.has(deps.as_ref().storage, "ulp-1"));
+        assert_eq!(TOTAL_SUPPLY.load(deps.as_ref().storage).unwrap(), 0);
+    }
+
+    #[test]
+    fn transfer_hook_is_notified() {
+        let mut deps = setup();
+        let token_id = mint(deps.as_mut(), "alice");
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::UpdateTransferHook {
+                    transfer_hook: Some("wrapper".to_string()),
+                },
+            },
+        )
+        .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::TransferNft {
+                recipient: "bob".to_string(),
+                token_id: token_id.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "wrapper".to_string(),
+                msg: to_json_binary(&TransferHookMsg::NftTransferred {
+                    token_id,
+                    previous_owner: "alice".to_string(),
+                    new_owner: "bob".to_string(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn minter_manages_attributes() {
+        let mut deps = setup();
+        let token_id = mint(deps.as_mut(), "alice");
+        let locked = |value: &str| Attribute {
+            trait_type: "locked_until".to_string(),
+            value: value.to_string(),
+            display_type: Some("date".to_string()),
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::SetAttribute {
+                    token_id: token_id.clone(),
+                    attribute: locked("100"),
+                },
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        for value in ["100", "200"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("wrapper", &[]),
+                ExecuteMsg::Extension {
+                    msg: PositionExecuteMsg::SetAttribute {
+                        token_id: token_id.clone(),
+                        attribute: locked(value),
+                    },
+                },
+            )
+            .unwrap();
+        }
+        let query_attributes = |deps: Deps| -> AttributesResponse {
+            let res = query(
+                deps,
+                mock_env(),
+                QueryMsg::Extension {
+                    msg: PositionQueryMsg::Attributes {
+                        token_id: token_id.clone(),
+                    },
+                },
+            )
+            .unwrap();
+            from_json(&res).unwrap()
+        };
+        assert_eq!(
+            query_attributes(deps.as_ref()).attributes,
+            vec![locked("200")]
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrapper", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::RemoveAttribute {
+                    token_id: token_id.clone(),
+                    trait_type: "locked_until".to_string(),
+                },
+            },
+        )
+        .unwrap();
+        assert!(query_attributes(deps.as_ref()).attributes.is_empty());
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("wrapper", &[]),
+            ExecuteMsg::Extension {
+                msg: PositionExecuteMsg::RemoveAttribute {
+                    token_id: "ulp-missing".to_string(),
+                    trait_type: "locked_until".to_string(),
+                },
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::TokenNotFound {});
+    }
 }
@@ -15,6 +15,12 @@ pub enum ContractError {
     #[error("Cannot set approval for own account")]
     CannotSetApprovalForOwnAccount {},
 
+    #[error("Approval has already expired")]
+    Expired {},
+
+    #[error("Approval not found for: {spender}")]
+    ApprovalNotFound { spender: String },
+
     #[error("Invalid token ID")]
     InvalidTokenId {},
 
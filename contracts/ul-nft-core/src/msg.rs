@@ -1,7 +1,9 @@
+use cosmwasm_std::Binary;
+use cw721::Expiration;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{Config, LiquidityPosition, TokenInfo};
+use crate::state::{Attribute, LiquidityPosition};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MigrateMsg {}
@@ -10,53 +12,187 @@ pub struct MigrateMsg {}
 pub struct InstantiateMsg {
     pub name: String,
     pub symbol: String,
+    // Defaults to the instantiator until the liquidity wrapper is deployed
+    pub minter: Option<String>,
     pub euclid_router: String,
 }
 
+/// The cw721 execute surface, with the UL-NFT specific messages nested
+/// under `Extension` so standard cw721 clients can talk to this contract
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Mint {
-        positions: Vec<LiquidityPosition>,
-        token_uri: Option<String>,
-    },
-    Transfer {
+    // Move a token to another account without triggering actions
+    TransferNft {
         recipient: String,
         token_id: String,
     },
+    // Move a token to a contract and trigger `ReceiveNft` on it
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    // Allow spender to transfer / send the token, optionally until `expires`
     Approve {
         spender: String,
         token_id: String,
+        expires: Option<Expiration>,
     },
-    Burn {
+    // Remove a previously granted approval
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    // Allow operator to transfer / send any of the sender's tokens
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    // Remove a previously granted ApproveAll permission
+    RevokeAll {
+        operator: String,
+    },
+    // There is no cw721 Burn: every token is backed by liquidity held in the
+    // minter, and burning it would strand that liquidity. The minter burns a
+    // token through Redeem once its liquidity is withdrawn.
+    // Liquidity position specific messages
+    Extension {
+        msg: PositionExecuteMsg,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionExecuteMsg {
+    // Mint a token backed by `positions`, minter only
+    Mint {
         token_id: String,
+        owner: String,
+        positions: Vec<LiquidityPosition>,
+        token_uri: Option<String>,
     },
+    // Replace the positions backing a token, minter only
     UpdateLiquidityPosition {
         token_id: String,
         position_updates: Vec<LiquidityPosition>,
     },
+    // Burn a token whose liquidity has been fully withdrawn, minter only
+    Redeem {
+        token_id: String,
+    },
+    // Add an attribute or replace the one of the same trait type, minter only
+    SetAttribute {
+        token_id: String,
+        attribute: Attribute,
+    },
+    // Remove the attribute of a trait type, minter only
+    RemoveAttribute {
+        token_id: String,
+        trait_type: String,
+    },
+    // Hand the minter role to another address, admin only
+    UpdateMinter {
+        minter: String,
+    },
+    // Set or clear the contract notified on every transfer, admin only
+    UpdateTransferHook {
+        transfer_hook: Option<String>,
+    },
+}
+
+/// Sent to the configured transfer hook whenever a token changes hands
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferHookMsg {
+    NftTransferred {
+        token_id: String,
+        previous_owner: String,
+        new_owner: String,
+    },
 }
 
+/// The cw721 query surface, with the UL-NFT specific queries nested
+/// under `Extension`
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetToken { token_id: String },
-    GetTokensByOwner { owner: String },
+    // Owner of the token and its approvals: OwnerOfResponse
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    // Approval of a single spender on a token: ApprovalResponse
+    Approval {
+        token_id: String,
+        spender: String,
+        include_expired: Option<bool>,
+    },
+    // All approvals on a token: ApprovalsResponse
+    Approvals {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    // Operators the owner has granted ApproveAll to: OperatorsResponse
+    AllOperators {
+        owner: String,
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Number of tokens in circulation: NumTokensResponse
+    NumTokens {},
+    // Collection name and symbol: ContractInfoResponse
+    ContractInfo {},
+    // Token uri and liquidity positions: NftInfoResponse<Vec<LiquidityPosition>>
+    NftInfo {
+        token_id: String,
+    },
+    // NftInfo and OwnerOf in one query: AllNftInfoResponse<Vec<LiquidityPosition>>
+    AllNftInfo {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    // Tokens owned by an address: TokensResponse
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // All tokens of the collection: TokensResponse
+    AllTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // Address allowed to mint: MinterResponse
+    Minter {},
+    // Liquidity position specific queries
+    Extension {
+        msg: PositionQueryMsg,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionQueryMsg {
     Config {},
-    TotalSupply {},
+    Positions { token_id: String },
+    Attributes { token_id: String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct GetTokenResponse {
-    pub token: TokenInfo,
+pub struct MinterResponse {
+    pub minter: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct GetTokensByOwnerResponse {
-    pub tokens: Vec<String>,
+pub struct PositionsResponse {
+    pub token_id: String,
+    pub positions: Vec<LiquidityPosition>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct ConfigResponse {
-    pub config: Config,
+pub struct AttributesResponse {
+    pub token_id: String,
+    pub attributes: Vec<Attribute>,
 }
@@ -1,4 +1,5 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, BlockInfo, Empty, Uint128};
+use cw721::Expiration;
 use cw_storage_plus::{Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -8,6 +9,10 @@ pub struct Config {
     pub name: String,
     pub symbol: String,
     pub admin: Addr,
+    // Only the minter (the liquidity wrapper) can mint or update positions
+    pub minter: Addr,
+    // Notified whenever a token changes hands, so position owners stay in sync
+    pub transfer_hook: Option<Addr>,
     pub euclid_router: Addr,
 }
 
@@ -19,15 +24,43 @@ pub struct LiquidityPosition {
     pub chain_id: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Approval {
+    pub spender: Addr,
+    pub expires: Expiration,
+}
+
+impl Approval {
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        self.expires.is_expired(block)
+    }
+}
+
+// A trait of the token, in the usual NFT metadata shape
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Attribute {
+    pub trait_type: String,
+    pub value: String,
+    pub display_type: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TokenInfo {
     pub owner: Addr,
     pub positions: Vec<LiquidityPosition>,
-    pub approved: Option<Addr>,
+    pub approvals: Vec<Approval>,
     pub token_uri: Option<String>,
+    // Set by the minter, one per trait type
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const TOKENS: Map<&str, TokenInfo> = Map::new("tokens");
 pub const TOTAL_SUPPLY: Item<u64> = Item::new("total_supply");
-pub const OWNER_TOKENS: Map<&Addr, Vec<String>> = Map::new("owner_tokens");
+
+// (owner, token_id) -> presence, so an owner's tokens can be paginated
+pub const OWNER_TOKENS: Map<(&Addr, &str), Empty> = Map::new("owner_tokens");
+
+// (owner, operator) -> expiration of an ApproveAll grant
+pub const OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("operators");
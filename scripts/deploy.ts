@@ -164,6 +164,40 @@ async function deploy() {
       `Liquidity Wrapper instantiated at: ${wrapperInstance.contractAddress}`,
     );
 
+    // 7. Hand the UL-NFT minter role to the Liquidity Wrapper
+    console.log("Setting Liquidity Wrapper as UL-NFT minter...");
+    await client.execute(
+      account.address,
+      config.contracts.ulNft.address!,
+      {
+        extension: {
+          msg: {
+            update_minter: {
+              minter: config.contracts.liquidityWrapper.address,
+            },
+          },
+        },
+      },
+      "auto",
+    );
+
+    // 8. Keep wrapper position owners in sync with UL-NFT transfers
+    console.log("Registering Liquidity Wrapper as UL-NFT transfer hook...");
+    await client.execute(
+      account.address,
+      config.contracts.ulNft.address!,
+      {
+        extension: {
+          msg: {
+            update_transfer_hook: {
+              transfer_hook: config.contracts.liquidityWrapper.address,
+            },
+          },
+        },
+      },
+      "auto",
+    );
+
     // Save deployment config
     writeFileSync(
       path.join(__dirname, `../config/deployment.${NETWORK}.json`),